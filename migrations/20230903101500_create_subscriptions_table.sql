@@ -0,0 +1,21 @@
+CREATE TABLE IF NOT EXISTS subscriptions (
+  id uuid NOT NULL,
+  PRIMARY KEY (id),
+  user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  service_id uuid NOT NULL REFERENCES services (id),
+  name TEXT NOT NULL,
+  description TEXT NULL,
+  amount BIGINT NOT NULL CHECK (amount >= 0),
+  currency TEXT NOT NULL,
+  next_renewal_date DATE NOT NULL,
+  billing_period SMALLINT NOT NULL CHECK (billing_period > 0),
+  billing_period_unit TEXT NOT NULL,
+  subscribed_at DATE NULL,
+  cancelled_at timestamptz NULL,
+  cancel_reason TEXT NULL,
+  deleted_at timestamptz NULL,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS subscriptions_user_id_idx ON subscriptions (user_id);
@@ -0,0 +1,12 @@
+ALTER TABLE users
+  ADD COLUMN IF NOT EXISTS preferred_currency TEXT NOT NULL DEFAULT 'EUR';
+
+-- Daily rates, `1 base_currency = rate quote_currency`
+CREATE TABLE IF NOT EXISTS exchange_rates (
+  base_currency TEXT NOT NULL,
+  quote_currency TEXT NOT NULL,
+  rate_date DATE NOT NULL,
+  PRIMARY KEY (base_currency, quote_currency, rate_date),
+  rate NUMERIC NOT NULL CHECK (rate > 0),
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
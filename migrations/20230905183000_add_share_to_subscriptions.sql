@@ -0,0 +1,3 @@
+-- Percentage of the price paid by the user, e.g. 50 when split with a flatmate
+ALTER TABLE subscriptions
+  ADD COLUMN IF NOT EXISTS share SMALLINT NOT NULL DEFAULT 100 CHECK (share BETWEEN 0 AND 100);
@@ -0,0 +1,25 @@
+-- Default category of a service, subscriptions can override it
+ALTER TABLE services
+  ADD COLUMN category TEXT NOT NULL DEFAULT 'other';
+
+ALTER TABLE subscriptions
+  ADD COLUMN category TEXT;
+
+-- User-defined tags
+CREATE TABLE IF NOT EXISTS tags(
+  id uuid NOT NULL,
+  PRIMARY KEY (id),
+  user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  name TEXT NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS tags_user_id_name_idx ON tags (user_id, lower(name));
+
+CREATE TABLE IF NOT EXISTS subscription_tags(
+  subscription_id uuid NOT NULL REFERENCES subscriptions (id) ON DELETE CASCADE,
+  tag_id uuid NOT NULL REFERENCES tags (id) ON DELETE CASCADE,
+  PRIMARY KEY (subscription_id, tag_id)
+);
+
+CREATE INDEX IF NOT EXISTS subscription_tags_tag_id_idx ON subscription_tags (tag_id);
@@ -0,0 +1,33 @@
+-- Monthly budgets, `category` is NULL for the overall budget of the user
+CREATE TABLE IF NOT EXISTS budgets(
+  id uuid NOT NULL,
+  PRIMARY KEY (id),
+  user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  category TEXT,
+  amount BIGINT NOT NULL CHECK (amount > 0),
+  currency TEXT NOT NULL,
+  thresholds SMALLINT[] NOT NULL DEFAULT '{80,100}',
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS budgets_user_id_category_idx
+  ON budgets (user_id, (COALESCE(category, '')));
+
+-- Rendered notifications waiting for delivery, `dedup_key` prevents sending the same alert twice
+CREATE TABLE IF NOT EXISTS notification_outbox(
+  id uuid NOT NULL,
+  PRIMARY KEY (id),
+  user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  channel TEXT NOT NULL,
+  subject TEXT,
+  body TEXT NOT NULL,
+  dedup_key TEXT NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  sent_at timestamptz
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS notification_outbox_dedup_idx
+  ON notification_outbox (user_id, channel, dedup_key);
+CREATE INDEX IF NOT EXISTS notification_outbox_pending_idx
+  ON notification_outbox (created_at) WHERE sent_at IS NULL;
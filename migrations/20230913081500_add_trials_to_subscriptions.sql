@@ -0,0 +1,9 @@
+-- Optional trial phase, the regular `amount` is charged from `trial_ends_on` on
+ALTER TABLE subscriptions
+  ADD COLUMN trial_ends_on DATE,
+  ADD COLUMN trial_amount BIGINT CHECK (trial_amount >= 0),
+  ADD COLUMN trial_reminder_days SMALLINT CHECK (trial_reminder_days >= 0),
+  ADD CONSTRAINT subscriptions_trial_check CHECK (
+    (trial_ends_on IS NULL AND trial_amount IS NULL AND trial_reminder_days IS NULL)
+    OR (trial_ends_on IS NOT NULL AND trial_amount IS NOT NULL AND trial_reminder_days IS NOT NULL)
+  );
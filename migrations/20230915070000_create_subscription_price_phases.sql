@@ -0,0 +1,8 @@
+-- Introductory prices charged in `position` order before the regular `amount` of the subscription
+CREATE TABLE IF NOT EXISTS subscription_price_phases(
+  subscription_id uuid NOT NULL REFERENCES subscriptions (id) ON DELETE CASCADE,
+  position SMALLINT NOT NULL CHECK (position > 0),
+  PRIMARY KEY (subscription_id, position),
+  cycles SMALLINT NOT NULL CHECK (cycles > 0),
+  amount BIGINT NOT NULL CHECK (amount >= 0)
+);
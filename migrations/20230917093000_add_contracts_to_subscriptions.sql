@@ -0,0 +1,12 @@
+-- Optional minimum term, cancelling has to be announced a notice period before `contract_ends_on`
+ALTER TABLE subscriptions
+  ADD COLUMN contract_ends_on DATE,
+  ADD COLUMN contract_auto_renew BOOLEAN,
+  ADD COLUMN notice_period SMALLINT CHECK (notice_period >= 0),
+  ADD COLUMN notice_period_unit TEXT,
+  ADD CONSTRAINT subscriptions_contract_check CHECK (
+    (contract_ends_on IS NULL AND contract_auto_renew IS NULL
+      AND notice_period IS NULL AND notice_period_unit IS NULL)
+    OR (contract_ends_on IS NOT NULL AND contract_auto_renew IS NOT NULL
+      AND notice_period IS NOT NULL AND notice_period_unit IS NOT NULL)
+  );
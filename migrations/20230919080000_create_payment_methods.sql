@@ -0,0 +1,18 @@
+-- Cards and accounts paying for subscriptions, full numbers are never stored
+CREATE TABLE IF NOT EXISTS payment_methods(
+  id uuid NOT NULL,
+  PRIMARY KEY (id),
+  user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  label TEXT NOT NULL,
+  kind TEXT NOT NULL,
+  last_four TEXT CHECK (last_four ~ '^[0-9]{4}$'),
+  expiry_month SMALLINT CHECK (expiry_month BETWEEN 1 AND 12),
+  expiry_year SMALLINT,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT payment_methods_expiry_check CHECK ((expiry_month IS NULL) = (expiry_year IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS payment_methods_user_id_idx ON payment_methods (user_id);
+
+ALTER TABLE subscriptions
+  ADD COLUMN payment_method_id uuid REFERENCES payment_methods (id) ON DELETE SET NULL;
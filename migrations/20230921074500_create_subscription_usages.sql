@@ -0,0 +1,22 @@
+-- "Last used" check-ins logged by the user
+CREATE TABLE IF NOT EXISTS subscription_usages(
+  id uuid NOT NULL,
+  PRIMARY KEY (id),
+  subscription_id uuid NOT NULL REFERENCES subscriptions (id) ON DELETE CASCADE,
+  used_on DATE NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS subscription_usages_subscription_id_used_on_idx
+  ON subscription_usages (subscription_id, used_on);
+
+-- Optional target of how often the user wants to use the subscription, e.g. 4 times a month
+ALTER TABLE subscriptions
+  ADD COLUMN usage_target_times SMALLINT CHECK (usage_target_times > 0),
+  ADD COLUMN usage_target_unit TEXT,
+  ADD CONSTRAINT subscriptions_usage_target_check
+    CHECK ((usage_target_times IS NULL) = (usage_target_unit IS NULL));
+
+-- Subscriptions without use for this many billing cycles are flagged as unused
+ALTER TABLE users
+  ADD COLUMN unused_after_cycles SMALLINT NOT NULL DEFAULT 2 CHECK (unused_after_cycles > 0);
@@ -0,0 +1,14 @@
+-- Plans offered by a service, e.g. monthly and yearly billing of the same tier
+CREATE TABLE IF NOT EXISTS service_plans(
+  id uuid NOT NULL,
+  PRIMARY KEY (id),
+  service_id uuid NOT NULL REFERENCES services (id) ON DELETE CASCADE,
+  name TEXT NOT NULL,
+  amount BIGINT NOT NULL CHECK (amount >= 0),
+  currency TEXT NOT NULL,
+  billing_period SMALLINT NOT NULL CHECK (billing_period > 0),
+  billing_period_unit TEXT NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS service_plans_service_id_idx ON service_plans (service_id);
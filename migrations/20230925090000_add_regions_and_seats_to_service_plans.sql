@@ -0,0 +1,11 @@
+-- Plans are priced per currency and optionally per region (ISO 3166 country code),
+-- subscriptions remember the plan they were created from
+ALTER TABLE service_plans
+  ADD COLUMN region TEXT CHECK (region ~ '^[A-Z]{2}$'),
+  ADD COLUMN seats SMALLINT NOT NULL DEFAULT 1 CHECK (seats > 0),
+  ADD COLUMN updated_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP;
+
+ALTER TABLE subscriptions
+  ADD COLUMN service_plan_id uuid REFERENCES service_plans (id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS subscriptions_service_plan_id_idx ON subscriptions (service_plan_id);
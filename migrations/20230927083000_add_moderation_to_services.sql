@@ -0,0 +1,10 @@
+-- Services created by users are private suggestions until an admin reviews them,
+-- the existing catalog stays public
+ALTER TABLE services
+  ADD COLUMN status TEXT NOT NULL DEFAULT 'approved',
+  ADD COLUMN suggested_by uuid REFERENCES users (id) ON DELETE SET NULL,
+  ADD COLUMN reviewed_at timestamptz,
+  ADD COLUMN rejection_reason TEXT,
+  ADD COLUMN merged_into uuid REFERENCES services (id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS services_status_idx ON services (status);
@@ -0,0 +1,27 @@
+-- Alternative names of services (e.g. `netflix.com`, `NFLX*`) and trigram indexes for fuzzy
+-- search, names are matched lowercased without accents and punctuation
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+CREATE EXTENSION IF NOT EXISTS unaccent;
+
+ALTER TABLE services
+  ADD COLUMN normalized_name TEXT NOT NULL DEFAULT '';
+
+UPDATE services
+SET normalized_name = btrim(regexp_replace(lower(unaccent(name)), '[^[:alnum:]]+', ' ', 'g'));
+
+CREATE INDEX IF NOT EXISTS services_normalized_name_trgm_idx
+  ON services USING GIN (normalized_name gin_trgm_ops);
+
+CREATE TABLE IF NOT EXISTS service_aliases(
+  id uuid NOT NULL,
+  PRIMARY KEY (id),
+  service_id uuid NOT NULL REFERENCES services (id) ON DELETE CASCADE,
+  alias TEXT NOT NULL,
+  normalized TEXT NOT NULL CHECK (normalized <> ''),
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS service_aliases_service_id_normalized_idx
+  ON service_aliases (service_id, normalized);
+CREATE INDEX IF NOT EXISTS service_aliases_normalized_trgm_idx
+  ON service_aliases USING GIN (normalized gin_trgm_ops);
@@ -0,0 +1,12 @@
+-- Administrative changes, kept after the changed rows are gone
+CREATE TABLE IF NOT EXISTS audit_log(
+  id uuid NOT NULL,
+  PRIMARY KEY (id),
+  actor_id uuid REFERENCES users (id) ON DELETE SET NULL,
+  action TEXT NOT NULL,
+  subject_id uuid NOT NULL,
+  details JSONB NOT NULL DEFAULT '{}',
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_subject_id_idx ON audit_log (subject_id);
@@ -0,0 +1,8 @@
+-- Services from the bundled catalog (`data/services.json`) remember their key and the catalog
+-- version they were last updated from, edits by admins stop further catalog updates
+ALTER TABLE services
+  ADD COLUMN website_url TEXT,
+  ADD COLUMN cancellation_url TEXT,
+  ADD COLUMN seed_key TEXT UNIQUE,
+  ADD COLUMN seed_version INTEGER,
+  ADD COLUMN edited_at timestamptz;
@@ -0,0 +1,14 @@
+-- Cancellation instructions of a service, every edit adds a version and the highest one is
+-- current
+CREATE TABLE IF NOT EXISTS service_cancellation_guides(
+  service_id uuid NOT NULL REFERENCES services (id) ON DELETE CASCADE,
+  version INTEGER NOT NULL CHECK (version > 0),
+  PRIMARY KEY (service_id, version),
+  url TEXT,
+  steps TEXT[] NOT NULL DEFAULT '{}',
+  notice_days SMALLINT CHECK (notice_days >= 0),
+  contact_email TEXT,
+  contact_phone TEXT,
+  edited_by uuid REFERENCES users (id) ON DELETE SET NULL,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,3 @@
+-- Logos live in the blob store, every upload gets a new version so that the images can be
+-- cached for a long time
+ALTER TABLE services ADD COLUMN logo_version INTEGER;
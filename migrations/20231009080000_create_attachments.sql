@@ -0,0 +1,17 @@
+-- Files such as invoices kept with a subscription or one of its payments, the content lives in
+-- the blob store under `blob_key`
+CREATE TABLE IF NOT EXISTS attachments(
+  id uuid NOT NULL,
+  PRIMARY KEY (id),
+  user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  subscription_id uuid NOT NULL REFERENCES subscriptions (id) ON DELETE CASCADE,
+  payment_date DATE,
+  file_name TEXT NOT NULL,
+  content_type TEXT NOT NULL,
+  size_bytes BIGINT NOT NULL CHECK (size_bytes > 0),
+  blob_key TEXT NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS attachments_subscription_id_idx ON attachments (subscription_id);
+CREATE INDEX IF NOT EXISTS attachments_user_id_idx ON attachments (user_id);
@@ -0,0 +1,49 @@
+//! Record of administrative changes in the `audit_log` table
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::domain::UserId;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AuditAction {
+    /// Duplicate service was merged into the subject
+    ServiceMerged,
+    /// Service was deleted, its subscriptions possibly moved to another one
+    ServiceDeleted,
+}
+
+impl AsRef<str> for AuditAction {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::ServiceMerged => "service_merged",
+            Self::ServiceDeleted => "service_deleted",
+        }
+    }
+}
+
+/// Records the action within the `transaction` making the change, so that either both or
+/// neither are saved
+#[tracing::instrument(name = "Record audit event", skip(transaction, details))]
+pub(crate) async fn record_audit_event(
+    transaction: &mut Transaction<'_, Postgres>,
+    actor_id: UserId,
+    action: AuditAction,
+    subject_id: Uuid,
+    details: serde_json::Value,
+) -> sqlx::Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO audit_log (id, actor_id, action, subject_id, details)
+        VALUES ($1, $2, $3, $4, $5::text::jsonb)
+        "#,
+        Uuid::new_v4(),
+        Uuid::from(actor_id),
+        action.as_ref(),
+        subject_id,
+        details.to_string()
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(())
+}
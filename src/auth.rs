@@ -1,4 +1,5 @@
 use axum_login::PostgresStore;
+use hyper::StatusCode;
 
 use axum_login::{
     axum_sessions::SessionLayer as AxumSessionLayer, AuthLayer as AxumAuthLayer,
@@ -43,6 +44,23 @@ pub(crate) async fn setup_auth(
     (auth_layer, session_layer)
 }
 
+/// Logged in user, the route is expected to be wrapped with [`RequireAuth::login`]
+pub(crate) fn current_user(auth: AuthContext) -> Result<User, (StatusCode, String)> {
+    auth.current_user
+        .ok_or_else(|| (StatusCode::UNAUTHORIZED, "Login required".to_string()))
+}
+
+/// Logged in administrator, the route is expected to be wrapped with
+/// [`RequireAuth::login_with_role`]
+pub(crate) fn current_admin(auth: AuthContext) -> Result<User, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    if user.role == UserRole::Admin {
+        Ok(user)
+    } else {
+        Err((StatusCode::FORBIDDEN, "Admin role required".to_string()))
+    }
+}
+
 // #[async_trait]
 // impl SessionStore for DatabaseUserStore {
 //     /// Get a session from the storage backend.
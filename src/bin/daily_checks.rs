@@ -0,0 +1,69 @@
+//! Queues budget alerts and reminders of all users, meant to be scheduled daily
+//!
+//! ```sh
+//! cargo run --bin daily_checks
+//! ```
+use chrono::Utc;
+use sqlx::PgPool;
+
+use recurio::{budget_alerts::check_all_budgets, configuration, reminders, telemetry};
+
+#[tokio::main]
+async fn main() -> Result<(), String> {
+    telemetry::init_subscriber("info".into());
+
+    let configuration = configuration::get_configuration().expect("Failed to read configuration");
+    let database = PgPool::connect_lazy_with(configuration.database.with_db());
+    let today = Utc::now().date_naive();
+
+    // Jobs run independently, one failing does not hold back the others
+    let mut failed = false;
+
+    match check_all_budgets(&database).await {
+        Ok(users) => tracing::info!("Checked budgets of {} users", users),
+        Err(e) => {
+            tracing::error!("Failed to check budgets: {}", e);
+            failed = true;
+        }
+    }
+
+    failed |= !queued(
+        "trial reminders",
+        reminders::send_trial_reminders(&database, today).await,
+    );
+    failed |= !queued(
+        "price increase reminders",
+        reminders::send_price_increase_reminders(&database, today).await,
+    );
+    failed |= !queued(
+        "cancellation deadline reminders",
+        reminders::send_cancellation_deadline_reminders(&database, today).await,
+    );
+    failed |= !queued(
+        "card expiry alerts",
+        reminders::send_card_expiry_alerts(&database, today).await,
+    );
+    failed |= !queued(
+        "payment digests",
+        reminders::send_payment_digests(&database, today).await,
+    );
+
+    match failed {
+        true => Err("Some of the daily checks failed".into()),
+        false => Ok(()),
+    }
+}
+
+/// Logs the outcome of a reminder job, returns whether it succeeded
+fn queued(name: &str, result: Result<usize, String>) -> bool {
+    match result {
+        Ok(count) => {
+            tracing::info!("Queued {} {}", count, name);
+            true
+        }
+        Err(e) => {
+            tracing::error!("Failed to queue {}: {}", name, e);
+            false
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! Imports exchange rates from European Central Bank reference-rate files
+//!
+//! ```sh
+//! cargo run --bin import_rates -- eurofxref-hist.zip eurofxref-daily.xml
+//! ```
+use chrono::Utc;
+use iso_currency::Currency;
+use sqlx::PgPool;
+
+use recurio::{
+    configuration,
+    exchange_rate_store::ExchangeRateStore,
+    rate_providers::{EcbFileProvider, RateProvider},
+    telemetry,
+};
+
+#[tokio::main]
+async fn main() -> Result<(), String> {
+    telemetry::init_subscriber("info".into());
+
+    let paths: Vec<String> = std::env::args().skip(1).collect();
+    if paths.is_empty() {
+        return Err("Usage: import_rates <FILE>...".into());
+    }
+
+    let configuration = configuration::get_configuration().expect("Failed to read configuration");
+    let store = ExchangeRateStore::new(PgPool::connect_lazy_with(configuration.database.with_db()));
+
+    let mut currencies = vec![Currency::EUR];
+    for path in paths {
+        let provider = EcbFileProvider::new(path);
+        let rates = provider.fetch_rates().await?;
+        tracing::info!("{}: read {} rates", provider.name(), rates.len());
+
+        for rate in &rates {
+            if !currencies.contains(&rate.quote) {
+                currencies.push(rate.quote);
+            }
+        }
+
+        store.save(&rates).await.map_err(|e| e.to_string())?;
+    }
+
+    let today = Utc::now().date_naive();
+    let rates = store.load(&currencies).await.map_err(|e| e.to_string())?;
+    for currency in currencies {
+        if rates.is_stale(Currency::EUR, currency, today) {
+            tracing::warn!(
+                "Rates for {} are stale, latest one is from {:?}",
+                currency.code(),
+                rates.latest_date(Currency::EUR, currency)
+            );
+        }
+    }
+
+    Ok(())
+}
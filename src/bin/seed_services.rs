@@ -0,0 +1,39 @@
+//! Upserts the bundled catalog of popular services, or the given catalog file
+//!
+//! ```sh
+//! cargo run --bin seed_services
+//! cargo run --bin seed_services -- data/services.json
+//! ```
+use sqlx::PgPool;
+
+use recurio::{
+    configuration,
+    service_catalog::{seed_services, SeedCatalog, BUNDLED_CATALOG},
+    telemetry,
+};
+
+#[tokio::main]
+async fn main() -> Result<(), String> {
+    telemetry::init_subscriber("info".into());
+
+    let json = match std::env::args().nth(1) {
+        Some(path) => std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path, e))?,
+        None => BUNDLED_CATALOG.to_owned(),
+    };
+    let catalog = SeedCatalog::parse(&json)?;
+
+    let configuration = configuration::get_configuration().expect("Failed to read configuration");
+    let database = PgPool::connect_lazy_with(configuration.database.with_db());
+
+    let summary = seed_services(&database, &catalog).await?;
+    tracing::info!(
+        "Catalog version {}: {} inserted, {} updated, {} linked, {} skipped",
+        catalog.version,
+        summary.inserted,
+        summary.updated,
+        summary.linked,
+        summary.skipped
+    );
+
+    Ok(())
+}
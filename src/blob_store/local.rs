@@ -0,0 +1,80 @@
+use std::{io::ErrorKind, path::PathBuf};
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use super::{validate_key, BlobStore};
+
+/// Blobs stored as files below a directory, for single-server installations
+#[derive(Debug, Clone)]
+pub struct LocalBlobStore {
+    root: PathBuf,
+}
+
+impl LocalBlobStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, String> {
+        validate_key(key)?;
+        Ok(self.root.join(key))
+    }
+}
+
+#[async_trait]
+impl BlobStore for LocalBlobStore {
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<(), String> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        // Readers never see a partially written file
+        let temporary = path.with_extension(format!("{}.tmp", Uuid::new_v4()));
+        tokio::fs::write(&temporary, bytes)
+            .await
+            .map_err(|e| e.to_string())?;
+        tokio::fs::rename(&temporary, &path)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+        match tokio::fs::read(self.path(key)?).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), String> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_none, assert_ok};
+
+    use super::LocalBlobStore;
+    use crate::blob_store::BlobStore;
+
+    #[tokio::test]
+    async fn blobs_round_trip() {
+        let root = std::env::temp_dir().join(format!("recurio-blobs-{}", uuid::Uuid::new_v4()));
+        let store = LocalBlobStore::new(&root);
+
+        assert_ok!(store.put("logos/1/64.png", vec![1, 2, 3]).await);
+        assert_eq!(store.get("logos/1/64.png").await, Ok(Some(vec![1, 2, 3])));
+        assert_ok!(store.delete("logos/1/64.png").await);
+        assert_ok!(store.delete("logos/1/64.png").await);
+        assert_none!(store.get("logos/1/64.png").await.unwrap());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
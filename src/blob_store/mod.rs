@@ -0,0 +1,55 @@
+mod local;
+
+pub use local::*;
+
+use async_trait::async_trait;
+
+/// Storage of binary files such as uploaded images, addressed by keys like `logos/<id>/1.png`
+#[async_trait]
+pub trait BlobStore: std::fmt::Debug + Send + Sync {
+    /// Replaces the blob stored under the key, if any
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<(), String>;
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String>;
+
+    /// Deleting a missing blob is not an error
+    async fn delete(&self, key: &str) -> Result<(), String>;
+}
+
+/// Keys are relative paths of lowercase ASCII segments, so that every backend can map them to
+/// its own namespace without escaping
+pub fn validate_key(key: &str) -> Result<(), String> {
+    let is_valid_segment = |segment: &str| {
+        !segment.is_empty()
+            && !segment.starts_with('.')
+            && segment.chars().all(|c| {
+                c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_' || c == '.'
+            })
+    };
+    if !key.split('/').all(is_valid_segment) {
+        return Err(format!("{} is not a valid blob key.", key));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+
+    use super::validate_key;
+
+    #[test]
+    fn keys_cannot_leave_the_namespace() {
+        assert_ok!(validate_key("logos/0b7c/3/64.png"));
+        for key in [
+            "",
+            "/logos/1.png",
+            "logos//1.png",
+            "logos/../secret",
+            "Logos/1.png",
+        ] {
+            assert_err!(validate_key(key));
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! Alerts about budgets queued in the notification outbox. Every threshold is announced once a
+//! month and every subscription pushing the forecast over a budget once.
+use chrono::Utc;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    domain::{BudgetStatus, Locale, Subscription, UserId},
+    exchange_rate_store::ExchangeRateStore,
+    notification_store::NotificationStore,
+    notifications::{Channel, Notification},
+    routes::{fetch_user_budgets, fetch_user_subscriptions},
+};
+
+/// Budget alerts are not urgent enough for an SMS
+const ALERT_CHANNELS: [Channel; 2] = [Channel::Email, Channel::Push];
+
+/// Checks the budgets of the user, `new_subscription` was just added and is checked for pushing
+/// the forecast over a budget
+#[tracing::instrument(name = "Check budget alerts", skip(database, new_subscription))]
+pub(crate) async fn check_budgets(
+    database: &PgPool,
+    user_id: UserId,
+    locale: Locale,
+    new_subscription: Option<&Subscription>,
+) -> Result<(), String> {
+    let budgets = fetch_user_budgets(database, user_id).await?;
+    if budgets.is_empty() {
+        return Ok(());
+    }
+    let subscriptions = fetch_user_subscriptions(database, user_id).await?;
+
+    let rates = ExchangeRateStore::new(database.clone())
+        .load_for(
+            budgets.iter().map(|budget| budget.limit.currency()),
+            subscriptions.iter().map(|s| s.price.currency()),
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let store = NotificationStore::new(database.clone());
+    let enqueue = |notification: Notification, dedup_key: String| {
+        let store = store.clone();
+        async move {
+            store
+                .enqueue_notification(user_id, &notification, locale, &ALERT_CHANNELS, &dedup_key)
+                .await
+                .map_err(|e| e.to_string())
+        }
+    };
+
+    let today = Utc::now().date_naive();
+    for budget in &budgets {
+        let status = BudgetStatus::compute(budget, &subscriptions, &rates, today)?;
+        let budget_id = Uuid::from(budget.id);
+
+        for threshold in &status.reached_thresholds {
+            let notification = Notification::BudgetThresholdReached {
+                category: budget.category,
+                threshold: *threshold,
+                committed: status.committed,
+                limit: budget.limit,
+            };
+            let dedup_key = format!(
+                "budget:{}:{}:{}",
+                budget_id,
+                status.month.format("%Y-%m"),
+                threshold
+            );
+            enqueue(notification, dedup_key).await?;
+        }
+
+        let Some(new_subscription) = new_subscription else {
+            continue;
+        };
+        if !budget.applies_to(new_subscription) || !status.is_forecast_over(budget) {
+            continue;
+        }
+        let others: Vec<Subscription> = subscriptions
+            .iter()
+            .filter(|subscription| subscription.id != new_subscription.id)
+            .cloned()
+            .collect();
+        let previous = BudgetStatus::compute(budget, &others, &rates, today)?;
+        if !previous.is_forecast_over(budget) {
+            let notification = Notification::BudgetForecastExceeded {
+                subscription: new_subscription.name.clone(),
+                category: budget.category,
+                forecast: status.forecast,
+                limit: budget.limit,
+            };
+            let dedup_key = format!(
+                "budget:{}:subscription:{}",
+                budget_id,
+                Uuid::from(new_subscription.id)
+            );
+            enqueue(notification, dedup_key).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks budgets of all users, meant to run periodically so thresholds reached by the charges
+/// of a new month get announced. Returns the number of checked users.
+pub async fn check_all_budgets(database: &PgPool) -> Result<usize, String> {
+    let users = sqlx::query!(
+        r#"
+        SELECT DISTINCT users.id, users.locale
+        FROM users
+        JOIN budgets ON budgets.user_id = users.id
+        "#
+    )
+    .fetch_all(database)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    for user in &users {
+        let locale = Locale::parse(&user.locale)?;
+        if let Err(e) = check_budgets(database, user.id.into(), locale, None).await {
+            tracing::error!("Failed to check budgets of user {}: {}", user.id, e);
+        }
+    }
+
+    Ok(users.len())
+}
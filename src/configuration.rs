@@ -10,6 +10,7 @@ pub struct Settings {
     pub database: DatabaseSettings,
     pub application: ApplicationSettings,
     pub auth: AuthSettings,
+    pub storage: StorageSettings,
 }
 
 #[derive(serde::Deserialize, Clone)]
@@ -35,6 +36,12 @@ pub struct AuthSettings {
     pub session_secret: Secret<String>,
 }
 
+#[derive(serde::Deserialize, Clone)]
+pub struct StorageSettings {
+    /// Directory of the local blob store, relative to the working directory
+    pub blob_path: String,
+}
+
 impl DatabaseSettings {
     pub fn without_db(&self) -> PgConnectOptions {
         let ssl_mode = if self.require_ssl {
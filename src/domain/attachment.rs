@@ -0,0 +1,230 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use unicode_segmentation::UnicodeSegmentation;
+use uuid::Uuid;
+
+use super::{Subscription, SubscriptionId};
+
+/// Largest file accepted for a single attachment
+pub const MAX_ATTACHMENT_BYTES: usize = 10 * 1024 * 1024;
+/// Storage every user has for all their attachments together
+pub const ATTACHMENT_QUOTA_BYTES: i64 = 100 * 1024 * 1024;
+
+/// Content types accepted for attachments with the bytes their files start with
+const ATTACHMENT_TYPES: [(&str, &[u8]); 4] = [
+    ("application/pdf", b"%PDF-"),
+    ("image/png", b"\x89PNG\r\n\x1a\n"),
+    ("image/jpeg", b"\xff\xd8\xff"),
+    ("image/webp", b"RIFF"),
+];
+
+/// File such as an invoice or a receipt kept with a subscription, optionally for one of its
+/// payments
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Attachment {
+    pub id: AttachmentId,
+    pub subscription_id: SubscriptionId,
+    /// Date of the charge the file belongs to, `None` for the subscription as a whole
+    pub payment_date: Option<NaiveDate>,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct AttachmentId(Uuid);
+
+impl From<Uuid> for AttachmentId {
+    fn from(value: Uuid) -> Self {
+        Self(value)
+    }
+}
+
+impl From<AttachmentId> for Uuid {
+    fn from(value: AttachmentId) -> Self {
+        value.0
+    }
+}
+
+/// Uploaded file about to be saved
+#[derive(Debug, PartialEq)]
+pub struct NewAttachment {
+    pub file_name: String,
+    pub content_type: &'static str,
+    pub payment_date: Option<NaiveDate>,
+}
+
+impl NewAttachment {
+    /// Accepts the file only when its content matches the declared `content_type`
+    pub fn parse(
+        file_name: &str,
+        content_type: &str,
+        bytes: &[u8],
+        payment_date: Option<NaiveDate>,
+    ) -> Result<Self, String> {
+        const FILE_NAME_MAX_LENGTH: usize = 128;
+
+        if bytes.is_empty() {
+            return Err("The file is empty.".to_string());
+        }
+        if bytes.len() > MAX_ATTACHMENT_BYTES {
+            return Err(format!(
+                "Attachments can be at most {} MiB.",
+                MAX_ATTACHMENT_BYTES / 1024 / 1024
+            ));
+        }
+        let content_type = ATTACHMENT_TYPES
+            .into_iter()
+            .find(|(accepted, _)| content_type.trim().eq_ignore_ascii_case(accepted))
+            .map(|(accepted, _)| accepted)
+            .ok_or_else(|| format!("{} is not a supported attachment type.", content_type))?;
+        if sniff_content_type(bytes) != Some(content_type) {
+            return Err(format!("The file is not a valid {} file.", content_type));
+        }
+
+        // Browsers send Windows paths for some uploads, only the last segment is the name
+        let file_name: String = file_name
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .filter(|c| !c.is_control() && *c != '"')
+            .collect();
+        let file_name = file_name.trim();
+        if file_name.is_empty()
+            || file_name.starts_with('.')
+            || file_name.graphemes(true).count() > FILE_NAME_MAX_LENGTH
+        {
+            return Err(format!("{} is not a valid file name.", file_name));
+        }
+
+        Ok(Self {
+            file_name: file_name.to_owned(),
+            content_type,
+            payment_date,
+        })
+    }
+
+    /// Payments are the charges of the subscription, see [`Subscription::charges_between`]
+    pub(crate) fn ensure_payment_of(&self, subscription: &Subscription) -> Result<(), String> {
+        let Some(date) = self.payment_date else {
+            return Ok(());
+        };
+        let next_day = date.succ_opt().ok_or("date out of range")?;
+        if subscription.charges_between(date, next_day)?.is_empty() {
+            return Err(format!("{} has no payment on {}.", subscription.name, date));
+        }
+
+        Ok(())
+    }
+}
+
+/// Content type of the file judging by its first bytes
+fn sniff_content_type(bytes: &[u8]) -> Option<&'static str> {
+    let (content_type, _) = ATTACHMENT_TYPES
+        .into_iter()
+        .find(|(_, magic)| bytes.starts_with(magic))?;
+    // RIFF is a container for other formats too
+    if content_type == "image/webp" && bytes.get(8..12) != Some(b"WEBP".as_slice()) {
+        return None;
+    }
+
+    Some(content_type)
+}
+
+/// Error unless the user has room for another `size` bytes next to the `used` ones
+pub fn ensure_attachment_quota(used: i64, size: usize) -> Result<(), String> {
+    let size = i64::try_from(size).map_err(|_| "file too large")?;
+    if used.saturating_add(size) > ATTACHMENT_QUOTA_BYTES {
+        return Err(format!(
+            "The file does not fit into your storage of {} MiB, {} MiB are left.",
+            ATTACHMENT_QUOTA_BYTES / 1024 / 1024,
+            (ATTACHMENT_QUOTA_BYTES - used).max(0) / 1024 / 1024
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use claims::{assert_err, assert_ok};
+    use iso_currency::Currency;
+
+    use crate::domain::{
+        ensure_attachment_quota, fixtures::monthly_subscription, NewAttachment,
+        ATTACHMENT_QUOTA_BYTES,
+    };
+
+    const PDF: &[u8] = b"%PDF-1.7\n%\xe2\xe3\xcf\xd3\n";
+
+    #[test]
+    fn content_has_to_match_the_type() {
+        assert_ok!(NewAttachment::parse(
+            "invoice.pdf",
+            "application/pdf",
+            PDF,
+            None
+        ));
+        assert_err!(NewAttachment::parse("invoice.pdf", "image/png", PDF, None));
+        assert_err!(NewAttachment::parse(
+            "invoice.html",
+            "text/html",
+            b"<html>",
+            None
+        ));
+        assert_err!(NewAttachment::parse(
+            "clip.webp",
+            "image/webp",
+            b"RIFF\0\0\0\0AVI LIST",
+            None
+        ));
+        assert_err!(NewAttachment::parse(
+            "empty.pdf",
+            "application/pdf",
+            b"",
+            None
+        ));
+    }
+
+    #[test]
+    fn file_names_lose_their_path() {
+        let attachment = NewAttachment::parse(
+            "C:\\Users\\me\\Invoice \"2023\".pdf",
+            "application/pdf",
+            PDF,
+            None,
+        )
+        .unwrap();
+        assert_eq!(attachment.file_name, "Invoice 2023.pdf");
+        assert_err!(NewAttachment::parse("../", "application/pdf", PDF, None));
+        assert_err!(NewAttachment::parse(
+            ".htaccess",
+            "application/pdf",
+            PDF,
+            None
+        ));
+    }
+
+    #[test]
+    fn payment_date_has_to_be_a_charge() {
+        let renewal = NaiveDate::from_ymd_opt(2023, 10, 15).unwrap();
+        let subscription = monthly_subscription(1299, Currency::EUR, renewal);
+        let attachment = |date: NaiveDate| {
+            NewAttachment::parse("invoice.pdf", "application/pdf", PDF, Some(date)).unwrap()
+        };
+
+        assert_ok!(attachment(renewal).ensure_payment_of(&subscription));
+        assert_err!(attachment(renewal.succ_opt().unwrap()).ensure_payment_of(&subscription));
+    }
+
+    #[test]
+    fn quota_covers_all_attachments() {
+        assert_ok!(ensure_attachment_quota(0, 1024));
+        assert_ok!(ensure_attachment_quota(ATTACHMENT_QUOTA_BYTES - 1024, 1024));
+        assert_err!(ensure_attachment_quota(ATTACHMENT_QUOTA_BYTES - 1023, 1024));
+    }
+}
@@ -0,0 +1,225 @@
+use chrono::{DateTime, Datelike, Months, NaiveDate, Utc};
+use uuid::Uuid;
+
+use super::{charges_between, Category, ExchangeRates, Money, SpendingSummary, Subscription};
+
+/// Percentages of the limit at which the user gets alerted unless configured otherwise
+pub const DEFAULT_BUDGET_THRESHOLDS: [u8; 2] = [80, 100];
+
+/// Monthly spending limit, either overall or for a single category
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Budget {
+    pub id: BudgetId,
+    /// `None` for the overall budget
+    pub category: Option<Category>,
+    /// Limit for a calendar month, in the preferred currency of the user at the time it was set
+    pub limit: Money,
+    /// Percentages of the [`limit`](Budget::limit) at which the user gets alerted, ascending
+    pub thresholds: Vec<u8>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Budget {
+    /// Whether spending of the subscription counts towards the budget
+    pub fn applies_to(&self, subscription: &Subscription) -> bool {
+        self.category
+            .is_none_or(|category| category == subscription.category)
+    }
+
+    /// Sorts and deduplicates the thresholds, each has to be a positive percentage
+    pub fn parse_thresholds(mut thresholds: Vec<u8>) -> Result<Vec<u8>, String> {
+        if thresholds.contains(&0) {
+            return Err("Budget thresholds have to be greater than 0%.".into());
+        }
+        thresholds.sort_unstable();
+        thresholds.dedup();
+        Ok(thresholds)
+    }
+}
+
+/// Budget about to be set by the user
+pub(crate) struct NewBudget {
+    pub category: Option<Category>,
+    pub limit: Money,
+    pub thresholds: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub(crate) struct BudgetId(Uuid);
+
+impl From<Uuid> for BudgetId {
+    fn from(value: Uuid) -> Self {
+        Self(value)
+    }
+}
+
+impl From<BudgetId> for Uuid {
+    fn from(value: BudgetId) -> Self {
+        value.0
+    }
+}
+
+/// Spending counted towards a budget in the current month
+#[derive(Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BudgetStatus {
+    /// First day of the month
+    pub month: NaiveDate,
+    /// All charges of the month, both already paid and upcoming
+    pub committed: Money,
+    /// Left to spend, negative when over budget
+    pub remaining: Money,
+    /// [`committed`](BudgetStatus::committed) as a percentage of the limit, rounded down
+    pub percent_used: u32,
+    /// Thresholds of the budget reached by the committed spend
+    pub reached_thresholds: Vec<u8>,
+    /// Average monthly spend over the next 12 months
+    pub forecast: Money,
+}
+
+impl BudgetStatus {
+    pub fn compute(
+        budget: &Budget,
+        subscriptions: &[Subscription],
+        rates: &ExchangeRates,
+        today: NaiveDate,
+    ) -> Result<Self, String> {
+        let currency = budget.limit.currency();
+        let subscriptions: Vec<Subscription> = subscriptions
+            .iter()
+            .filter(|subscription| budget.applies_to(subscription))
+            .cloned()
+            .collect();
+
+        let month = today.with_day(1).ok_or("date out of range")?;
+        let next_month = month
+            .checked_add_months(Months::new(1))
+            .ok_or("date out of range")?;
+
+        let charges = charges_between(&subscriptions, month, next_month)?
+            .iter()
+            .map(|charge| charge.convert(rates, currency, today))
+            .collect::<Result<Vec<_>, _>>()?;
+        let committed = Money::total(currency, charges)?;
+        let forecast =
+            SpendingSummary::compute(&subscriptions, rates, currency, today)?.monthly_equivalent;
+
+        // Compared in minor units to avoid rounding errors around the thresholds
+        let used = i128::from(committed.minor_units()) * 100;
+        let limit = i128::from(budget.limit.minor_units()).max(1);
+
+        Ok(Self {
+            month,
+            committed,
+            remaining: budget.limit.checked_sub(committed)?,
+            percent_used: u32::try_from(used / limit).unwrap_or(u32::MAX),
+            reached_thresholds: budget
+                .thresholds
+                .iter()
+                .copied()
+                .filter(|threshold| used >= limit * i128::from(*threshold))
+                .collect(),
+            forecast,
+        })
+    }
+
+    /// Whether the average monthly spend is projected to exceed the limit
+    pub fn is_forecast_over(&self, budget: &Budget) -> bool {
+        self.forecast.minor_units() > budget.limit.minor_units()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use claims::{assert_err, assert_ok_eq};
+    use iso_currency::Currency;
+    use uuid::Uuid;
+
+    use crate::domain::{
+        fixtures::{date, monthly_subscription},
+        Budget, BudgetStatus, Category, ExchangeRates, Money,
+    };
+
+    fn budget(category: Option<Category>, limit: i64) -> Budget {
+        let created_at = Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap();
+        Budget {
+            id: Uuid::new_v4().into(),
+            category,
+            limit: Money::new(limit, Currency::EUR),
+            thresholds: vec![80, 100],
+            created_at,
+            updated_at: created_at,
+        }
+    }
+
+    #[test]
+    fn thresholds_are_sorted_and_deduplicated() {
+        assert_ok_eq!(Budget::parse_thresholds(vec![100, 80, 100]), vec![80, 100]);
+        assert_err!(Budget::parse_thresholds(vec![0, 50]));
+    }
+
+    #[test]
+    fn committed_spend_includes_upcoming_charges_of_the_month() {
+        let subscriptions = [
+            monthly_subscription(1000, Currency::EUR, date(2023, 9, 5)),
+            monthly_subscription(700, Currency::EUR, date(2023, 9, 25)),
+        ];
+
+        let status = BudgetStatus::compute(
+            &budget(None, 2000),
+            &subscriptions,
+            &ExchangeRates::new([]),
+            date(2023, 9, 10),
+        )
+        .unwrap();
+
+        assert_eq!(status.month, date(2023, 9, 1));
+        assert_eq!(status.committed, Money::new(1700, Currency::EUR));
+        assert_eq!(status.remaining, Money::new(300, Currency::EUR));
+        assert_eq!(status.percent_used, 85);
+        assert_eq!(status.reached_thresholds, vec![80]);
+    }
+
+    #[test]
+    fn category_budget_only_counts_its_subscriptions() {
+        let mut cloud = monthly_subscription(500, Currency::EUR, date(2023, 9, 5));
+        cloud.category = Category::Cloud;
+        let subscriptions = [
+            cloud,
+            monthly_subscription(1000, Currency::EUR, date(2023, 9, 5)),
+        ];
+
+        let status = BudgetStatus::compute(
+            &budget(Some(Category::Cloud), 500),
+            &subscriptions,
+            &ExchangeRates::new([]),
+            date(2023, 9, 10),
+        )
+        .unwrap();
+
+        assert_eq!(status.committed, Money::new(500, Currency::EUR));
+        assert!(status.remaining.is_zero());
+        assert_eq!(status.reached_thresholds, vec![80, 100]);
+    }
+
+    #[test]
+    fn forecast_over_the_limit_is_detected() {
+        let budget = budget(None, 1500);
+        let subscriptions = [
+            monthly_subscription(1000, Currency::EUR, date(2023, 10, 5)),
+            monthly_subscription(1000, Currency::EUR, date(2023, 10, 5)),
+        ];
+        let rates = ExchangeRates::new([]);
+        let today = date(2023, 9, 10);
+
+        let within = BudgetStatus::compute(&budget, &subscriptions[..1], &rates, today).unwrap();
+        let over = BudgetStatus::compute(&budget, &subscriptions, &rates, today).unwrap();
+
+        assert!(!within.is_forecast_over(&budget));
+        assert!(over.is_forecast_over(&budget));
+    }
+}
@@ -0,0 +1,226 @@
+use chrono::{DateTime, Utc};
+use unicode_segmentation::UnicodeSegmentation;
+use uuid::Uuid;
+
+use super::{ServiceId, ServiceUrl};
+
+/// Instructions for cancelling a subscription of a service. Every edit saves a new version, the
+/// one with the highest number is current.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancellationGuide {
+    pub service_id: ServiceId,
+    pub version: i32,
+    /// Page where the subscription is cancelled
+    pub url: Option<String>,
+    /// Steps in the order they have to be done
+    pub steps: Vec<String>,
+    /// Days of notice the service requires before the end of the billing period
+    pub notice_days: Option<u16>,
+    pub contact_email: Option<String>,
+    pub contact_phone: Option<String>,
+    /// `None` once the user who wrote the version is deleted
+    pub edited_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl CancellationGuide {
+    /// Guide as plain text with numbered steps, for embedding in messages
+    pub fn to_text(&self) -> String {
+        let mut lines: Vec<String> = self
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(index, step)| format!("{}. {}", index + 1, step))
+            .collect();
+        if let Some(url) = &self.url {
+            lines.push(format!("Cancel online: {}", url));
+        }
+        if let Some(days) = self.notice_days.filter(|days| *days > 0) {
+            lines.push(format!(
+                "Notice required: {} days before the end of the billing period",
+                days
+            ));
+        }
+        let contact: Vec<&str> = [&self.contact_email, &self.contact_phone]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        if !contact.is_empty() {
+            lines.push(format!("Contact: {}", contact.join(", ")));
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// Version of a guide about to be saved
+#[derive(Debug, PartialEq)]
+pub struct NewCancellationGuide {
+    pub url: Option<ServiceUrl>,
+    pub steps: Vec<String>,
+    pub notice_days: Option<u16>,
+    pub contact_email: Option<String>,
+    pub contact_phone: Option<String>,
+}
+
+impl NewCancellationGuide {
+    pub fn parse(
+        url: Option<ServiceUrl>,
+        steps: Vec<String>,
+        notice_days: Option<u16>,
+        contact_email: Option<&str>,
+        contact_phone: Option<&str>,
+    ) -> Result<Self, String> {
+        const MAX_STEPS: usize = 20;
+        const STEP_MAX_LENGTH: usize = 500;
+        const MAX_NOTICE_DAYS: u16 = 365;
+
+        let steps: Vec<String> = steps
+            .iter()
+            .map(|step| step.trim().to_owned())
+            .filter(|step| !step.is_empty())
+            .collect();
+        if steps.len() > MAX_STEPS {
+            return Err(format!("A guide can have at most {} steps.", MAX_STEPS));
+        }
+        if let Some(step) = steps
+            .iter()
+            .find(|step| step.graphemes(true).count() > STEP_MAX_LENGTH)
+        {
+            return Err(format!("{} is too long for a step.", step));
+        }
+        if notice_days.is_some_and(|days| days > MAX_NOTICE_DAYS) {
+            return Err(format!("Notice can be at most {} days.", MAX_NOTICE_DAYS));
+        }
+        let contact_email = parse_optional(contact_email, parse_email)?;
+        let contact_phone = parse_optional(contact_phone, parse_phone)?;
+        if url.is_none() && steps.is_empty() && contact_email.is_none() && contact_phone.is_none() {
+            return Err("A guide needs a URL, steps or contact details.".to_string());
+        }
+
+        Ok(Self {
+            url,
+            steps,
+            notice_days,
+            contact_email,
+            contact_phone,
+        })
+    }
+}
+
+/// Blank values count as missing
+fn parse_optional(
+    value: Option<&str>,
+    parse: fn(&str) -> Result<String, String>,
+) -> Result<Option<String>, String> {
+    value
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(parse)
+        .transpose()
+}
+
+fn parse_email(value: &str) -> Result<String, String> {
+    const EMAIL_MAX_LENGTH: usize = 254;
+    let is_valid = value.len() <= EMAIL_MAX_LENGTH
+        && !value.chars().any(char::is_whitespace)
+        && value.split_once('@').is_some_and(|(local, domain)| {
+            !local.is_empty() && domain.contains('.') && !domain.contains('@')
+        });
+    if !is_valid {
+        return Err(format!("{} is not a valid email address.", value));
+    }
+
+    Ok(value.to_owned())
+}
+
+fn parse_phone(value: &str) -> Result<String, String> {
+    let digits = value.chars().filter(char::is_ascii_digit).count();
+    let is_valid = (5..=15).contains(&digits)
+        && value
+            .chars()
+            .enumerate()
+            .all(|(index, c)| c.is_ascii_digit() || " -()".contains(c) || (c == '+' && index == 0));
+    if !is_valid {
+        return Err(format!("{} is not a valid phone number.", value));
+    }
+
+    Ok(value.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use claims::{assert_err, assert_ok};
+    use uuid::Uuid;
+
+    use crate::domain::{CancellationGuide, NewCancellationGuide};
+
+    #[test]
+    fn guide_needs_some_instructions() {
+        assert_err!(NewCancellationGuide::parse(
+            None,
+            vec!["  ".into()],
+            Some(30),
+            None,
+            Some("")
+        ));
+        assert_ok!(NewCancellationGuide::parse(
+            None,
+            vec![],
+            None,
+            Some("cancel@example.com"),
+            None
+        ));
+    }
+
+    #[test]
+    fn contact_details_are_validated() {
+        for (email, phone) in [
+            (Some("support"), None),
+            (Some("support@localhost"), None),
+            (None, Some("call us")),
+            (None, Some("12+34567")),
+        ] {
+            assert_err!(NewCancellationGuide::parse(
+                None,
+                vec!["Call the hotline".into()],
+                None,
+                email,
+                phone
+            ));
+        }
+        let guide = NewCancellationGuide::parse(
+            None,
+            vec![" Call the hotline ".into()],
+            None,
+            None,
+            Some(" +49 (30) 1234-567 "),
+        )
+        .unwrap();
+        assert_eq!(guide.steps, vec!["Call the hotline"]);
+        assert_eq!(guide.contact_phone.as_deref(), Some("+49 (30) 1234-567"));
+    }
+
+    #[test]
+    fn guide_text_numbers_the_steps() {
+        let guide = CancellationGuide {
+            service_id: Uuid::new_v4().into(),
+            version: 2,
+            url: Some("https://www.netflix.com/cancelplan".into()),
+            steps: vec!["Open Account".into(), "Click Cancel Membership".into()],
+            notice_days: Some(0),
+            contact_email: None,
+            contact_phone: Some("+1 555 0100".into()),
+            edited_by: None,
+            created_at: Utc::now(),
+        };
+
+        assert_eq!(
+            guide.to_text(),
+            "1. Open Account\n2. Click Cancel Membership\nCancel online: https://www.netflix.com/cancelplan\nContact: +1 555 0100"
+        );
+    }
+}
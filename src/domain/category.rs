@@ -0,0 +1,99 @@
+/// Kind of service used to group spending
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum Category {
+    Streaming,
+    Music,
+    Software,
+    Cloud,
+    Gaming,
+    News,
+    Fitness,
+    Utilities,
+    #[default]
+    Other,
+}
+
+impl Category {
+    pub const ALL: [Category; 9] = [
+        Self::Streaming,
+        Self::Music,
+        Self::Software,
+        Self::Cloud,
+        Self::Gaming,
+        Self::News,
+        Self::Fitness,
+        Self::Utilities,
+        Self::Other,
+    ];
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let normalized = value.trim().to_lowercase();
+
+        Self::ALL
+            .into_iter()
+            .find(|category| category.as_ref() == normalized)
+            .ok_or_else(|| format!("{} is not a valid category.", value))
+    }
+}
+
+impl AsRef<str> for Category {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Streaming => "streaming",
+            Self::Music => "music",
+            Self::Software => "software",
+            Self::Cloud => "cloud",
+            Self::Gaming => "gaming",
+            Self::News => "news",
+            Self::Fitness => "fitness",
+            Self::Utilities => "utilities",
+            Self::Other => "other",
+        }
+    }
+}
+
+impl TryFrom<String> for Category {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::parse(&value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok_eq};
+
+    use crate::domain::Category;
+
+    #[test]
+    fn categories_are_parsed_case_insensitively() {
+        assert_ok_eq!(Category::parse("Streaming"), Category::Streaming);
+        assert_ok_eq!(Category::parse(" cloud "), Category::Cloud);
+    }
+
+    #[test]
+    fn unknown_category_is_rejected() {
+        assert_err!(Category::parse("groceries"));
+    }
+
+    #[test]
+    fn every_category_round_trips() {
+        for category in Category::ALL {
+            assert_ok_eq!(Category::parse(category.as_ref()), category);
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use std::ops::Deref;
+
+use iso_currency::Currency;
+use sqlx::{error::BoxDynError, postgres::PgValueRef, Postgres};
+
+/// [`Currency`] that can be stored in the database as its ISO 4217 code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+#[repr(transparent)]
+pub struct CurrencyCode(Currency);
+
+impl CurrencyCode {
+    pub fn parse(code: &str) -> Result<Self, String> {
+        Currency::from_code(&code.trim().to_uppercase())
+            .map(Self)
+            .ok_or_else(|| format!("{} is not a valid currency code.", code))
+    }
+}
+
+impl Deref for CurrencyCode {
+    type Target = Currency;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl AsRef<str> for CurrencyCode {
+    fn as_ref(&self) -> &str {
+        self.0.code()
+    }
+}
+
+impl From<Currency> for CurrencyCode {
+    fn from(value: Currency) -> Self {
+        Self(value)
+    }
+}
+
+impl From<CurrencyCode> for Currency {
+    fn from(value: CurrencyCode) -> Self {
+        value.0
+    }
+}
+
+impl TryFrom<String> for CurrencyCode {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::parse(&value)
+    }
+}
+
+impl sqlx::Type<Postgres> for CurrencyCode {
+    fn type_info() -> <Postgres as sqlx::Database>::TypeInfo {
+        <String as sqlx::Type<Postgres>>::type_info()
+    }
+}
+
+impl<'r> sqlx::Decode<'r, Postgres> for CurrencyCode {
+    fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+        let code = <&str as sqlx::Decode<Postgres>>::decode(value)?;
+        Ok(Self::parse(code)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::CurrencyCode;
+    use claims::{assert_err, assert_ok};
+    use iso_currency::Currency;
+
+    #[test]
+    fn a_valid_code_is_parsed_successfully() {
+        assert_ok!(CurrencyCode::parse("EUR"));
+    }
+
+    #[test]
+    fn code_is_case_insensitive() {
+        let code = CurrencyCode::parse(" usd ").unwrap();
+        assert_eq!(*code, Currency::USD);
+    }
+
+    #[test]
+    fn unknown_code_is_rejected() {
+        assert_err!(CurrencyCode::parse("ABC"));
+    }
+
+    #[test]
+    fn empty_code_is_rejected() {
+        assert_err!(CurrencyCode::parse(""));
+    }
+}
@@ -0,0 +1,123 @@
+use chrono::{Days, NaiveDate};
+use iso_currency::Currency;
+
+use super::{charges_between, ExchangeRates, Money, Subscription};
+
+/// Upcoming charges are summed up in a digest for this many days
+pub const PAYMENT_DIGEST_DAYS: u64 = 7;
+
+/// Charges of the next [`PAYMENT_DIGEST_DAYS`] expressed in a single (preferred) currency
+#[derive(Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PaymentDigest {
+    pub currency: Currency,
+    pub starts_on: NaiveDate,
+    /// Last day of the digest, inclusive
+    pub ends_on: NaiveDate,
+    /// Charged subscriptions in the order of their first charge
+    pub subscriptions: Vec<String>,
+    /// All charges converted with the latest rates
+    pub total: Money,
+}
+
+impl PaymentDigest {
+    /// `None` when nothing is charged within the digest
+    pub fn compute(
+        subscriptions: &[Subscription],
+        rates: &ExchangeRates,
+        currency: Currency,
+        today: NaiveDate,
+    ) -> Result<Option<Self>, String> {
+        let end = today
+            .checked_add_days(Days::new(PAYMENT_DIGEST_DAYS))
+            .ok_or("date out of range")?;
+        let charges = charges_between(subscriptions, today, end)?;
+        if charges.is_empty() {
+            return Ok(None);
+        }
+
+        let mut names: Vec<String> = vec![];
+        for charge in &charges {
+            let name = subscriptions
+                .iter()
+                .find(|subscription| subscription.id == charge.subscription_id)
+                .map(|subscription| &subscription.name);
+            if let Some(name) = name.filter(|name| !names.contains(name)) {
+                names.push(name.clone());
+            }
+        }
+        // Every charge is still ahead, so all of them are converted with the latest rates
+        let converted = charges
+            .iter()
+            .map(|charge| rates.convert_latest(charge.amount, currency))
+            .collect::<Result<Vec<_>, _>>()?;
+        let total = Money::total(currency, converted)?;
+
+        Ok(Some(Self {
+            currency,
+            starts_on: today,
+            ends_on: end.pred_opt().ok_or("date out of range")?,
+            subscriptions: names,
+            total,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use iso_currency::Currency;
+    use rust_decimal::Decimal;
+
+    use crate::domain::{
+        fixtures::{date, monthly_subscription},
+        ExchangeRate, ExchangeRates, Money,
+    };
+
+    use super::PaymentDigest;
+
+    #[test]
+    fn digest_sums_up_the_week_in_the_preferred_currency() {
+        let netflix = monthly_subscription(1000, Currency::EUR, date(2023, 9, 22));
+        let mut hulu = monthly_subscription(1000, Currency::USD, date(2023, 9, 26));
+        hulu.name = "Hulu".into();
+        let mut later = monthly_subscription(1000, Currency::EUR, date(2023, 9, 27));
+        later.name = "Spotify".into();
+        let rate = |date, rate| ExchangeRate {
+            base: Currency::EUR,
+            quote: Currency::USD,
+            date,
+            rate,
+        };
+        let rates = ExchangeRates::new([
+            rate(date(2023, 8, 1), Decimal::new(2, 0)),
+            rate(date(2023, 9, 1), Decimal::new(125, 2)),
+        ]);
+
+        let digest = PaymentDigest::compute(
+            &[later, hulu, netflix],
+            &rates,
+            Currency::EUR,
+            date(2023, 9, 20),
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(digest.ends_on, date(2023, 9, 26));
+        assert_eq!(digest.subscriptions, vec!["Netflix", "Hulu"]);
+        // 10 EUR and 10 USD at the latest rate of 1.25
+        assert_eq!(digest.total, Money::new(1800, Currency::EUR));
+    }
+
+    #[test]
+    fn digest_is_skipped_without_charges() {
+        let subscription = monthly_subscription(1000, Currency::EUR, date(2023, 10, 1));
+        let digest = PaymentDigest::compute(
+            &[subscription],
+            &ExchangeRates::new([]),
+            Currency::EUR,
+            date(2023, 9, 20),
+        )
+        .unwrap();
+        assert_eq!(digest, None);
+    }
+}
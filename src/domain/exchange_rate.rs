@@ -0,0 +1,282 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{Days, NaiveDate};
+use iso_currency::Currency;
+use rust_decimal::Decimal;
+
+use super::Money;
+
+/// Reference rates are not published over weekends and bank holidays, so the latest rate can be
+/// a few days old before it is considered stale
+pub const MAX_RATE_AGE_DAYS: u64 = 4;
+
+/// Daily rate, `1 base = rate quote`
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExchangeRate {
+    pub base: Currency,
+    pub quote: Currency,
+    pub date: NaiveDate,
+    pub rate: Decimal,
+}
+
+/// In-memory view of the stored daily rates used to convert amounts between currencies
+#[derive(Debug, Default)]
+pub struct ExchangeRates {
+    rates: HashMap<(Currency, Currency), BTreeMap<NaiveDate, Decimal>>,
+}
+
+impl ExchangeRates {
+    pub fn new(rates: impl IntoIterator<Item = ExchangeRate>) -> Self {
+        let mut exchange_rates = Self::default();
+        for rate in rates {
+            exchange_rates.insert(rate);
+        }
+        exchange_rates
+    }
+
+    pub fn insert(&mut self, rate: ExchangeRate) {
+        self.rates
+            .entry((rate.base, rate.quote))
+            .or_default()
+            .insert(rate.date, rate.rate);
+    }
+
+    /// Rate published on `date`. Falls back to the closest earlier rate (weekends, bank holidays)
+    /// and, for dates before the known history, to the oldest rate available.
+    pub fn rate_on(&self, from: Currency, to: Currency, date: NaiveDate) -> Option<Decimal> {
+        self.lookup(from, to, |history| {
+            history
+                .range(..=date)
+                .next_back()
+                .or_else(|| history.iter().next())
+                .map(|(_, rate)| *rate)
+        })
+    }
+
+    /// Most recent known rate, used for projections
+    pub fn latest_rate(&self, from: Currency, to: Currency) -> Option<Decimal> {
+        self.lookup(from, to, |history| history.values().next_back().copied())
+    }
+
+    /// Date of the most recent rate usable to convert between the currencies
+    pub fn latest_date(&self, from: Currency, to: Currency) -> Option<NaiveDate> {
+        if from == to {
+            return Some(NaiveDate::MAX);
+        }
+
+        let latest = |base, quote| {
+            [(base, quote), (quote, base)]
+                .iter()
+                .filter_map(|pair| self.rates.get(pair))
+                .filter_map(|history| history.keys().next_back().copied())
+                .max()
+        };
+
+        latest(from, to).or_else(|| {
+            let via_euro = [latest(from, Currency::EUR)?, latest(Currency::EUR, to)?];
+            via_euro.into_iter().min()
+        })
+    }
+
+    /// Rates between the currencies are missing or older than [`MAX_RATE_AGE_DAYS`]
+    pub fn is_stale(&self, from: Currency, to: Currency, today: NaiveDate) -> bool {
+        let oldest_fresh = today
+            .checked_sub_days(Days::new(MAX_RATE_AGE_DAYS))
+            .unwrap_or(NaiveDate::MIN);
+
+        match self.latest_date(from, to) {
+            Some(date) => date < oldest_fresh,
+            None => true,
+        }
+    }
+
+    /// Converts `amount` into `to` using the historical rate on `date`
+    pub fn convert_on(
+        &self,
+        amount: Money,
+        to: Currency,
+        date: NaiveDate,
+    ) -> Result<Money, String> {
+        let from = amount.currency();
+        let rate = self
+            .rate_on(from, to, date)
+            .ok_or_else(|| missing_rate(from, to))?;
+        amount.convert(rate, to)
+    }
+
+    /// Converts `amount` into `to` using the latest known rate
+    pub fn convert_latest(&self, amount: Money, to: Currency) -> Result<Money, String> {
+        let from = amount.currency();
+        let rate = self
+            .latest_rate(from, to)
+            .ok_or_else(|| missing_rate(from, to))?;
+        amount.convert(rate, to)
+    }
+
+    /// Looks up the rate using the direct pair, the inverse one or triangulating through EUR
+    /// (reference rates are usually published against the euro only)
+    fn lookup(
+        &self,
+        from: Currency,
+        to: Currency,
+        pick: impl Fn(&BTreeMap<NaiveDate, Decimal>) -> Option<Decimal>,
+    ) -> Option<Decimal> {
+        self.pair_rate(from, to, &pick).or_else(|| {
+            let to_euro = self.pair_rate(from, Currency::EUR, &pick)?;
+            let from_euro = self.pair_rate(Currency::EUR, to, &pick)?;
+            Some(to_euro * from_euro)
+        })
+    }
+
+    fn pair_rate(
+        &self,
+        from: Currency,
+        to: Currency,
+        pick: &impl Fn(&BTreeMap<NaiveDate, Decimal>) -> Option<Decimal>,
+    ) -> Option<Decimal> {
+        if from == to {
+            return Some(Decimal::ONE);
+        }
+
+        if let Some(rate) = self.rates.get(&(from, to)).and_then(pick) {
+            return Some(rate);
+        }
+
+        self.rates
+            .get(&(to, from))
+            .and_then(pick)
+            .map(|rate| Decimal::ONE / rate)
+    }
+}
+
+fn missing_rate(from: Currency, to: Currency) -> String {
+    format!("No exchange rate from {} to {}.", from.code(), to.code())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::{ExchangeRate, ExchangeRates, Money};
+    use chrono::NaiveDate;
+    use claims::assert_err;
+    use iso_currency::Currency;
+    use rust_decimal::Decimal;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, 9, day).unwrap()
+    }
+
+    fn rates() -> ExchangeRates {
+        ExchangeRates::new([
+            ExchangeRate {
+                base: Currency::EUR,
+                quote: Currency::USD,
+                date: date(1),
+                rate: Decimal::new(110, 2),
+            },
+            ExchangeRate {
+                base: Currency::EUR,
+                quote: Currency::USD,
+                date: date(4),
+                rate: Decimal::new(120, 2),
+            },
+        ])
+    }
+
+    #[test]
+    fn same_currency_is_not_converted() {
+        let amount = Money::new(999, Currency::EUR);
+        assert_eq!(
+            ExchangeRates::default().convert_latest(amount, Currency::EUR),
+            Ok(amount)
+        );
+    }
+
+    #[test]
+    fn historical_rate_falls_back_to_closest_earlier_date() {
+        let rates = rates();
+        assert_eq!(
+            rates.rate_on(Currency::EUR, Currency::USD, date(3)),
+            Some(Decimal::new(110, 2))
+        );
+        assert_eq!(
+            rates.convert_on(Money::new(1000, Currency::EUR), Currency::USD, date(3)),
+            Ok(Money::new(1100, Currency::USD))
+        );
+    }
+
+    #[test]
+    fn dates_before_known_history_use_the_oldest_rate() {
+        let rates = rates();
+        let before = NaiveDate::from_ymd_opt(2023, 8, 1).unwrap();
+        assert_eq!(
+            rates.convert_on(Money::new(1000, Currency::EUR), Currency::USD, before),
+            Ok(Money::new(1100, Currency::USD))
+        );
+    }
+
+    #[test]
+    fn latest_rate_is_used_for_projections() {
+        assert_eq!(
+            rates().convert_latest(Money::new(1000, Currency::EUR), Currency::USD),
+            Ok(Money::new(1200, Currency::USD))
+        );
+    }
+
+    #[test]
+    fn inverse_rate_is_used_when_pair_is_missing() {
+        assert_eq!(
+            rates().convert_latest(Money::new(1200, Currency::USD), Currency::EUR),
+            Ok(Money::new(1000, Currency::EUR))
+        );
+    }
+
+    #[test]
+    fn currency_exponents_are_respected() {
+        let rates = ExchangeRates::new([ExchangeRate {
+            base: Currency::EUR,
+            quote: Currency::JPY,
+            date: date(1),
+            rate: Decimal::new(15850, 2),
+        }]);
+        // 10.00 EUR -> 1585 JPY (no minor units)
+        assert_eq!(
+            rates.convert_latest(Money::new(1000, Currency::EUR), Currency::JPY),
+            Ok(Money::new(1585, Currency::JPY))
+        );
+    }
+
+    #[test]
+    fn cross_rates_are_triangulated_through_euro() {
+        let mut rates = rates();
+        rates.insert(ExchangeRate {
+            base: Currency::EUR,
+            quote: Currency::PLN,
+            date: date(4),
+            rate: Decimal::new(450, 2),
+        });
+        // 12 USD -> 10 EUR -> 45 PLN
+        assert_eq!(
+            rates.convert_latest(Money::new(1200, Currency::USD), Currency::PLN),
+            Ok(Money::new(4500, Currency::PLN))
+        );
+    }
+
+    #[test]
+    fn rates_older_than_max_age_are_stale() {
+        let rates = rates();
+        assert!(!rates.is_stale(Currency::USD, Currency::EUR, date(8)));
+        assert!(rates.is_stale(Currency::USD, Currency::EUR, date(9)));
+    }
+
+    #[test]
+    fn missing_rates_are_stale() {
+        assert!(rates().is_stale(Currency::EUR, Currency::PLN, date(4)));
+        assert!(!rates().is_stale(Currency::PLN, Currency::PLN, date(4)));
+    }
+
+    #[test]
+    fn missing_rate_is_an_error() {
+        assert_err!(rates().convert_latest(Money::new(1000, Currency::EUR), Currency::PLN));
+    }
+}
@@ -0,0 +1,305 @@
+use std::collections::BTreeMap;
+
+use chrono::{Days, Months, NaiveDate};
+use iso_currency::Currency;
+
+use super::{ExchangeRates, Money, Subscription, SubscriptionId};
+
+/// A single charge of a subscription, either already paid or projected
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Charge {
+    pub subscription_id: SubscriptionId,
+    pub date: NaiveDate,
+    /// Part of the price paid by the user
+    pub amount: Money,
+}
+
+impl Charge {
+    /// Converts the charge using the historical rate for past charges and the latest rate for
+    /// projected ones
+    pub fn convert(
+        &self,
+        rates: &ExchangeRates,
+        currency: Currency,
+        today: NaiveDate,
+    ) -> Result<Money, String> {
+        if self.date <= today {
+            rates.convert_on(self.amount, currency, self.date)
+        } else {
+            rates.convert_latest(self.amount, currency)
+        }
+    }
+}
+
+/// All charges of `subscriptions` within `[from, to)`, ordered by date
+pub(crate) fn charges_between(
+    subscriptions: &[Subscription],
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<Charge>, String> {
+    let mut charges: Vec<Charge> = vec![];
+    for subscription in subscriptions {
+        for (date, amount) in subscription.charges_between(from, to)? {
+            charges.push(Charge {
+                subscription_id: subscription.id,
+                date,
+                amount,
+            });
+        }
+    }
+    charges.sort_by_key(|charge| charge.date);
+    Ok(charges)
+}
+
+/// Trials converting within this many days are listed in the [`SpendingSummary`]
+pub const TRIAL_CONVERSION_WINDOW_DAYS: u64 = 30;
+
+/// Trial about to convert to the regular price
+#[derive(Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TrialConversion {
+    pub subscription_id: SubscriptionId,
+    pub name: String,
+    pub converts_on: NaiveDate,
+    /// First price paid by the user after the conversion, in the currency of the subscription
+    pub price: Money,
+}
+
+/// Spending of the user expressed in a single (preferred) currency
+#[derive(Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SpendingSummary {
+    pub currency: Currency,
+    /// Sum of all charges up to today, each converted with the rate from the payment date
+    pub spent_to_date: Money,
+    /// Projected charges within the next 12 months, converted with the latest rates
+    pub next_12_months: Money,
+    /// [`next_12_months`](SpendingSummary::next_12_months) spread evenly across months
+    pub monthly_equivalent: Money,
+    /// Currencies converted with outdated rates, the amounts might be off
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stale_rates: Vec<Currency>,
+    /// Trials converting within [`TRIAL_CONVERSION_WINDOW_DAYS`], soonest first
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub upcoming_conversions: Vec<TrialConversion>,
+}
+
+impl SpendingSummary {
+    pub fn compute(
+        subscriptions: &[Subscription],
+        rates: &ExchangeRates,
+        currency: Currency,
+        today: NaiveDate,
+    ) -> Result<Self, String> {
+        let tomorrow = today
+            .checked_add_days(Days::new(1))
+            .ok_or("date out of range")?;
+        let year_ahead = tomorrow
+            .checked_add_months(Months::new(12))
+            .ok_or("date out of range")?;
+
+        let total = |charges: Vec<Charge>| -> Result<Money, String> {
+            let converted = charges
+                .iter()
+                .map(|charge| charge.convert(rates, currency, today))
+                .collect::<Result<Vec<_>, _>>()?;
+            Money::total(currency, converted)
+        };
+
+        let spent_to_date = total(charges_between(subscriptions, NaiveDate::MIN, tomorrow)?)?;
+        let next_12_months = total(charges_between(subscriptions, tomorrow, year_ahead)?)?;
+
+        let mut stale_rates: Vec<Currency> = vec![];
+        for subscription in subscriptions {
+            let subscription_currency = subscription.price.currency();
+            if !stale_rates.contains(&subscription_currency)
+                && rates.is_stale(subscription_currency, currency, today)
+            {
+                stale_rates.push(subscription_currency);
+            }
+        }
+
+        let conversion_window = today
+            .checked_add_days(Days::new(TRIAL_CONVERSION_WINDOW_DAYS))
+            .ok_or("date out of range")?;
+        let mut upcoming_conversions: Vec<TrialConversion> = subscriptions
+            .iter()
+            .filter(|subscription| subscription.cancelled_at.is_none())
+            .filter(|subscription| subscription.is_trialing(today))
+            .filter_map(|subscription| {
+                let trial = subscription.trial.as_ref()?;
+                (trial.ends_on <= conversion_window).then(|| {
+                    Ok(TrialConversion {
+                        subscription_id: subscription.id,
+                        name: subscription.name.clone(),
+                        converts_on: trial.ends_on,
+                        price: subscription.price_of_renewal(0).share(subscription.share)?,
+                    })
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        upcoming_conversions.sort_by_key(|conversion| conversion.converts_on);
+
+        Ok(Self {
+            currency,
+            spent_to_date,
+            next_12_months,
+            monthly_equivalent: next_12_months.divide(12)?,
+            stale_rates,
+            upcoming_conversions,
+        })
+    }
+
+    /// Summary of every group of subscriptions, `groups` returns the groups a subscription
+    /// belongs to so it can be counted in several of them (e.g. once per tag)
+    pub fn compute_grouped<K: Ord>(
+        subscriptions: &[Subscription],
+        groups: impl Fn(&Subscription) -> Vec<K>,
+        rates: &ExchangeRates,
+        currency: Currency,
+        today: NaiveDate,
+    ) -> Result<BTreeMap<K, Self>, String> {
+        let mut grouped: BTreeMap<K, Vec<Subscription>> = BTreeMap::new();
+        for subscription in subscriptions {
+            for group in groups(subscription) {
+                grouped.entry(group).or_default().push(subscription.clone());
+            }
+        }
+
+        grouped
+            .into_iter()
+            .map(|(group, subscriptions)| {
+                Self::compute(&subscriptions, rates, currency, today)
+                    .map(|summary| (group, summary))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use iso_currency::Currency;
+    use rust_decimal::Decimal;
+
+    use uuid::Uuid;
+
+    use crate::domain::{
+        charges_between,
+        fixtures::{date, monthly_subscription},
+        Category, ExchangeRate, ExchangeRates, Money, SpendingSummary, TagId, Trial,
+    };
+
+    #[test]
+    fn charges_of_all_subscriptions_are_ordered_by_date() {
+        let subscriptions = [
+            monthly_subscription(1000, Currency::EUR, date(2023, 9, 20)),
+            monthly_subscription(500, Currency::EUR, date(2023, 9, 10)),
+        ];
+        let charges = charges_between(&subscriptions, date(2023, 9, 1), date(2023, 10, 1)).unwrap();
+        let dates: Vec<_> = charges.iter().map(|charge| charge.date).collect();
+        assert_eq!(dates, vec![date(2023, 9, 10), date(2023, 9, 20)]);
+    }
+
+    #[test]
+    fn past_charges_use_historical_rates_and_projections_the_latest_one() {
+        let mut subscription = monthly_subscription(1000, Currency::USD, date(2023, 9, 15));
+        subscription.subscribed_at = Some(date(2023, 8, 15));
+        let rates = ExchangeRates::new([
+            ExchangeRate {
+                base: Currency::EUR,
+                quote: Currency::USD,
+                date: date(2023, 8, 1),
+                rate: Decimal::new(2, 0),
+            },
+            ExchangeRate {
+                base: Currency::EUR,
+                quote: Currency::USD,
+                date: date(2023, 9, 1),
+                rate: Decimal::new(1, 0),
+            },
+        ]);
+
+        let summary =
+            SpendingSummary::compute(&[subscription], &rates, Currency::EUR, date(2023, 9, 20))
+                .unwrap();
+
+        // 10 USD at 2.0 on 2023-08-15 and 10 USD at 1.0 on 2023-09-15
+        assert_eq!(summary.spent_to_date, Money::new(1500, Currency::EUR));
+        assert_eq!(summary.next_12_months, Money::new(12 * 1000, Currency::EUR));
+        assert_eq!(summary.monthly_equivalent, Money::new(1000, Currency::EUR));
+        assert_eq!(summary.stale_rates, vec![Currency::USD]);
+    }
+
+    #[test]
+    fn subscriptions_are_counted_in_every_group_they_belong_to() {
+        let work = TagId::from(Uuid::new_v4());
+        let family = TagId::from(Uuid::new_v4());
+        let mut streaming = monthly_subscription(1000, Currency::EUR, date(2023, 10, 1));
+        streaming.tag_ids = vec![work, family];
+        let mut cloud = monthly_subscription(500, Currency::EUR, date(2023, 10, 1));
+        cloud.category = Category::Cloud;
+        cloud.tag_ids = vec![work];
+        let subscriptions = [streaming, cloud];
+        let rates = ExchangeRates::new([]);
+        let today = date(2023, 9, 20);
+
+        let by_category = SpendingSummary::compute_grouped(
+            &subscriptions,
+            |subscription| vec![subscription.category],
+            &rates,
+            Currency::EUR,
+            today,
+        )
+        .unwrap();
+        assert_eq!(by_category.len(), 2);
+        assert_eq!(
+            by_category[&Category::Cloud].monthly_equivalent,
+            Money::new(500, Currency::EUR)
+        );
+
+        let by_tag = SpendingSummary::compute_grouped(
+            &subscriptions,
+            |subscription| subscription.tag_ids.clone(),
+            &rates,
+            Currency::EUR,
+            today,
+        )
+        .unwrap();
+        assert_eq!(
+            by_tag[&work].monthly_equivalent,
+            Money::new(1500, Currency::EUR)
+        );
+        assert_eq!(
+            by_tag[&family].monthly_equivalent,
+            Money::new(1000, Currency::EUR)
+        );
+    }
+
+    #[test]
+    fn trials_converting_soon_are_listed() {
+        let trial = |ends_on| Trial {
+            ends_on,
+            price: Money::zero(Currency::EUR),
+            reminder_days: 3,
+        };
+        let mut soon = monthly_subscription(1000, Currency::EUR, date(2023, 10, 1));
+        soon.trial = Some(trial(date(2023, 10, 1)));
+        let mut later = monthly_subscription(1000, Currency::EUR, date(2023, 12, 1));
+        later.trial = Some(trial(date(2023, 12, 1)));
+
+        let summary = SpendingSummary::compute(
+            &[soon, later],
+            &ExchangeRates::new([]),
+            Currency::EUR,
+            date(2023, 9, 20),
+        )
+        .unwrap();
+
+        assert_eq!(summary.upcoming_conversions.len(), 1);
+        assert_eq!(
+            summary.upcoming_conversions[0].converts_on,
+            date(2023, 10, 1)
+        );
+        assert_eq!(summary.spent_to_date, Money::zero(Currency::EUR));
+    }
+}
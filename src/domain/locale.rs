@@ -0,0 +1,238 @@
+use chrono::NaiveDate;
+use sqlx::{error::BoxDynError, postgres::PgValueRef, Postgres};
+
+use super::Money;
+
+/// Locale of the user, decides how amounts and dates are presented
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Locale {
+    #[default]
+    EnUs,
+    EnGb,
+    DeDe,
+    EsEs,
+    FrFr,
+    ItIt,
+    NlNl,
+    PlPl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SymbolPosition {
+    /// `$9.99`
+    Before,
+    /// `€ 9,99`
+    BeforeSpaced,
+    /// `9,99 €`
+    After,
+}
+
+struct FormatRules {
+    decimal_separator: &'static str,
+    grouping_separator: &'static str,
+    symbol_position: SymbolPosition,
+    date_format: &'static str,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 8] = [
+        Self::EnUs,
+        Self::EnGb,
+        Self::DeDe,
+        Self::EsEs,
+        Self::FrFr,
+        Self::ItIt,
+        Self::NlNl,
+        Self::PlPl,
+    ];
+
+    /// Accepts BCP 47 tags (`de-DE`), POSIX style (`de_DE`) and bare languages (`de`)
+    pub fn parse(tag: &str) -> Result<Self, String> {
+        let normalized = tag.trim().replace('_', "-").to_lowercase();
+
+        Self::ALL
+            .into_iter()
+            .find(|locale| locale.as_ref().to_lowercase() == normalized)
+            .or_else(|| {
+                Self::ALL
+                    .into_iter()
+                    .find(|locale| locale.language() == normalized)
+            })
+            .ok_or_else(|| format!("{} is not a supported locale.", tag))
+    }
+
+    pub fn language(&self) -> &'static str {
+        &self.tag()[..2]
+    }
+
+    /// `9,99 €` for `de-DE`, `$9.99` for `en-US`
+    pub fn format_money(&self, money: Money) -> String {
+        let rules = self.rules();
+        let amount = money.format_amount();
+        let (sign, amount) = match amount.strip_prefix('-') {
+            Some(amount) => ("-", amount),
+            None => ("", amount.as_str()),
+        };
+
+        let number = match amount.split_once('.') {
+            Some((whole, fraction)) => format!(
+                "{}{}{}",
+                group_thousands(whole, rules.grouping_separator),
+                rules.decimal_separator,
+                fraction
+            ),
+            None => group_thousands(amount, rules.grouping_separator),
+        };
+        let symbol = money.currency().symbol().symbol;
+
+        match rules.symbol_position {
+            SymbolPosition::Before => format!("{}{}{}", sign, symbol, number),
+            SymbolPosition::BeforeSpaced => format!("{}{} {}", sign, symbol, number),
+            SymbolPosition::After => format!("{}{} {}", sign, number, symbol),
+        }
+    }
+
+    /// `15.09.2023` for `de-DE`, `09/15/2023` for `en-US`
+    pub fn format_date(&self, date: NaiveDate) -> String {
+        date.format(self.rules().date_format).to_string()
+    }
+
+    fn tag(&self) -> &'static str {
+        match self {
+            Self::EnUs => "en-US",
+            Self::EnGb => "en-GB",
+            Self::DeDe => "de-DE",
+            Self::EsEs => "es-ES",
+            Self::FrFr => "fr-FR",
+            Self::ItIt => "it-IT",
+            Self::NlNl => "nl-NL",
+            Self::PlPl => "pl-PL",
+        }
+    }
+
+    fn rules(&self) -> FormatRules {
+        let (decimal_separator, grouping_separator, symbol_position, date_format) = match self {
+            Self::EnUs => (".", ",", SymbolPosition::Before, "%m/%d/%Y"),
+            Self::EnGb => (".", ",", SymbolPosition::Before, "%d/%m/%Y"),
+            Self::DeDe => (",", ".", SymbolPosition::After, "%d.%m.%Y"),
+            Self::EsEs => (",", ".", SymbolPosition::After, "%d/%m/%Y"),
+            Self::FrFr => (",", "\u{202f}", SymbolPosition::After, "%d/%m/%Y"),
+            Self::ItIt => (",", ".", SymbolPosition::After, "%d/%m/%Y"),
+            Self::NlNl => (",", ".", SymbolPosition::BeforeSpaced, "%d-%m-%Y"),
+            Self::PlPl => (",", "\u{a0}", SymbolPosition::After, "%d.%m.%Y"),
+        };
+
+        FormatRules {
+            decimal_separator,
+            grouping_separator,
+            symbol_position,
+            date_format,
+        }
+    }
+}
+
+fn group_thousands(digits: &str, separator: &str) -> String {
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push_str(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+impl AsRef<str> for Locale {
+    fn as_ref(&self) -> &str {
+        self.tag()
+    }
+}
+
+impl serde::Serialize for Locale {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.tag())
+    }
+}
+
+impl TryFrom<String> for Locale {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::parse(&value)
+    }
+}
+
+impl sqlx::Type<Postgres> for Locale {
+    fn type_info() -> <Postgres as sqlx::Database>::TypeInfo {
+        <String as sqlx::Type<Postgres>>::type_info()
+    }
+}
+
+impl<'r> sqlx::Decode<'r, Postgres> for Locale {
+    fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+        let tag = <&str as sqlx::Decode<Postgres>>::decode(value)?;
+        Ok(Self::parse(tag)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use claims::{assert_err, assert_ok_eq};
+    use iso_currency::Currency;
+
+    use crate::domain::{Locale, Money};
+
+    #[test]
+    fn locale_tags_are_parsed_leniently() {
+        assert_ok_eq!(Locale::parse("de-DE"), Locale::DeDe);
+        assert_ok_eq!(Locale::parse("de_de"), Locale::DeDe);
+        assert_ok_eq!(Locale::parse("pl"), Locale::PlPl);
+        assert_ok_eq!(Locale::parse("en"), Locale::EnUs);
+    }
+
+    #[test]
+    fn unsupported_locale_is_rejected() {
+        assert_err!(Locale::parse("xx-YY"));
+        assert_err!(Locale::parse(""));
+    }
+
+    #[test]
+    fn money_uses_locale_separators_and_symbol_placement() {
+        let price = Money::new(999, Currency::EUR);
+        assert_eq!(Locale::DeDe.format_money(price), "9,99 €");
+        assert_eq!(Locale::NlNl.format_money(price), "€ 9,99");
+        assert_eq!(
+            Locale::EnUs.format_money(Money::new(999, Currency::USD)),
+            "$9.99"
+        );
+    }
+
+    #[test]
+    fn thousands_are_grouped() {
+        let price = Money::new(123_456_789, Currency::EUR);
+        assert_eq!(Locale::DeDe.format_money(price), "1.234.567,89 €");
+        assert_eq!(
+            Locale::EnUs.format_money(Money::new(123_456_789, Currency::USD)),
+            "$1,234,567.89"
+        );
+        assert_eq!(
+            Locale::EnUs.format_money(Money::new(1500, Currency::JPY)),
+            "¥1,500"
+        );
+    }
+
+    #[test]
+    fn negative_amounts_keep_the_sign_in_front() {
+        let difference = Money::new(-250, Currency::USD);
+        assert_eq!(Locale::EnUs.format_money(difference), "-$2.50");
+    }
+
+    #[test]
+    fn dates_use_locale_order() {
+        let date = NaiveDate::from_ymd_opt(2023, 9, 15).unwrap();
+        assert_eq!(Locale::EnUs.format_date(date), "09/15/2023");
+        assert_eq!(Locale::DeDe.format_date(date), "15.09.2023");
+        assert_eq!(Locale::EnGb.format_date(date), "15/09/2023");
+    }
+}
@@ -1,7 +1,37 @@
+mod attachment;
+mod budget;
+mod cancellation_guide;
+mod category;
+mod currency;
+mod digest;
+mod exchange_rate;
+mod forecast;
+mod locale;
+mod money;
+mod payment_method;
+mod scenario;
 mod service;
+mod service_search;
 mod subscription;
+mod tag;
+mod usage;
 mod user;
 
+pub use attachment::*;
+pub use budget::*;
+pub use cancellation_guide::*;
+pub use category::*;
+pub use currency::*;
+pub use digest::*;
+pub use exchange_rate::*;
+pub use forecast::*;
+pub use locale::*;
+pub use money::*;
+pub use payment_method::*;
+pub use scenario::*;
 pub use service::*;
+pub use service_search::*;
 pub use subscription::*;
+pub use tag::*;
+pub use usage::*;
 pub use user::*;
@@ -0,0 +1,348 @@
+use std::fmt;
+
+use iso_currency::Currency;
+use rust_decimal::{prelude::ToPrimitive, Decimal, RoundingStrategy};
+use serde::ser::SerializeStruct;
+
+/// Rounding used when taking a percentage share of an amount (e.g. split between flatmates),
+/// half a minor unit is rounded up
+pub const SHARE_ROUNDING: RoundingStrategy = RoundingStrategy::MidpointAwayFromZero;
+/// Rounding used when converting between currencies, half a minor unit is rounded to the even
+/// neighbour (banker's rounding) so that conversions do not drift in one direction
+pub const CONVERSION_ROUNDING: RoundingStrategy = RoundingStrategy::MidpointNearestEven;
+
+/// Amount of money expressed in minor units of its [`Currency`] (e.g. cents for EUR, yen for JPY,
+/// fils for KWD).
+///
+/// Arithmetic between amounts in different currencies is rejected, convert one of them first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Money {
+    minor_units: i64,
+    currency: Currency,
+}
+
+impl Money {
+    pub fn new(minor_units: i64, currency: Currency) -> Self {
+        Self {
+            minor_units,
+            currency,
+        }
+    }
+
+    pub fn zero(currency: Currency) -> Self {
+        Self::new(0, currency)
+    }
+
+    /// Parses a non-negative amount entered by the user, e.g. `9.99` or `9,99`.
+    /// The number of decimal places cannot exceed the minor units of the currency.
+    pub fn parse(input: &str, currency: Currency) -> Result<Self, String> {
+        let invalid = || format!("{} is not a valid amount of {}.", input, currency.code());
+
+        let normalized = input.trim().replace(',', ".");
+        let (whole, fraction) = match normalized.split_once('.') {
+            Some((whole, fraction)) if !fraction.is_empty() => (whole, fraction),
+            Some(_) => return Err(invalid()),
+            None => (normalized.as_str(), ""),
+        };
+
+        let is_number = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+        if whole.is_empty() || !is_number(whole) || !is_number(fraction) {
+            return Err(invalid());
+        }
+
+        let exponent = exponent(currency);
+        if fraction.len() > exponent as usize {
+            return Err(format!(
+                "{} has more than {} decimal places allowed for {}.",
+                input,
+                exponent,
+                currency.code()
+            ));
+        }
+
+        let digits = format!("{}{:0<width$}", whole, fraction, width = exponent as usize);
+        let minor_units = digits.parse::<i64>().map_err(|_| invalid())?;
+
+        Ok(Self::new(minor_units, currency))
+    }
+
+    pub fn minor_units(&self) -> i64 {
+        self.minor_units
+    }
+
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.minor_units == 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.minor_units < 0
+    }
+
+    /// Amount in major units, e.g. `9.99` for 999 cents
+    pub fn to_decimal(&self) -> Decimal {
+        Decimal::new(self.minor_units, exponent(self.currency))
+    }
+
+    pub fn checked_add(self, other: Money) -> Result<Money, String> {
+        self.ensure_same_currency(other, "add")?;
+        self.minor_units
+            .checked_add(other.minor_units)
+            .map(|minor_units| Self::new(minor_units, self.currency))
+            .ok_or_else(|| "Amount out of range.".to_string())
+    }
+
+    pub fn checked_sub(self, other: Money) -> Result<Money, String> {
+        self.ensure_same_currency(other, "subtract")?;
+        self.minor_units
+            .checked_sub(other.minor_units)
+            .map(|minor_units| Self::new(minor_units, self.currency))
+            .ok_or_else(|| "Amount out of range.".to_string())
+    }
+
+    pub fn checked_mul(self, factor: i64) -> Result<Money, String> {
+        self.minor_units
+            .checked_mul(factor)
+            .map(|minor_units| Self::new(minor_units, self.currency))
+            .ok_or_else(|| "Amount out of range.".to_string())
+    }
+
+    /// Sum of `amounts`, all of them have to be in `currency`
+    pub fn total(
+        currency: Currency,
+        amounts: impl IntoIterator<Item = Money>,
+    ) -> Result<Money, String> {
+        amounts
+            .into_iter()
+            .try_fold(Self::zero(currency), Money::checked_add)
+    }
+
+    /// `percent`% of the amount, rounded with [`SHARE_ROUNDING`]
+    pub fn share(self, percent: u8) -> Result<Money, String> {
+        if percent >= 100 {
+            return Ok(self);
+        }
+        let share = Decimal::from(self.minor_units) * Decimal::from(percent) / Decimal::ONE_HUNDRED;
+        Ok(Self::new(round(share, SHARE_ROUNDING)?, self.currency))
+    }
+
+    /// Amount divided by `divisor` (e.g. monthly average of a yearly total),
+    /// rounded with [`SHARE_ROUNDING`]
+    pub fn divide(self, divisor: u32) -> Result<Money, String> {
+        if divisor == 0 {
+            return Ok(self);
+        }
+        let part = Decimal::from(self.minor_units) / Decimal::from(divisor);
+        Ok(Self::new(round(part, SHARE_ROUNDING)?, self.currency))
+    }
+
+    /// Amount multiplied by `factor` (e.g. the number of renewals in a year),
+    /// rounded with [`SHARE_ROUNDING`]
+    pub fn multiply(self, factor: Decimal) -> Result<Money, String> {
+        let product = Decimal::from(self.minor_units)
+            .checked_mul(factor)
+            .ok_or("Amount out of range.")?;
+        Ok(Self::new(round(product, SHARE_ROUNDING)?, self.currency))
+    }
+
+    /// Divides the amount into `parts` which add up exactly to the original amount,
+    /// leftover minor units go to the first parts
+    pub fn split(self, parts: u32) -> Vec<Money> {
+        if parts == 0 {
+            return vec![];
+        }
+        let parts = i64::from(parts);
+        let base = self.minor_units.div_euclid(parts);
+        let remainder = self.minor_units.rem_euclid(parts);
+
+        (0..parts)
+            .map(|part| Self::new(base + i64::from(part < remainder), self.currency))
+            .collect()
+    }
+
+    /// Converts into `currency` where `1 self.currency = rate currency`,
+    /// rounded with [`CONVERSION_ROUNDING`]
+    pub fn convert(self, rate: Decimal, currency: Currency) -> Result<Money, String> {
+        let converted = self
+            .to_decimal()
+            .checked_mul(rate)
+            .and_then(|amount| amount.checked_mul(Decimal::from(10i64.pow(exponent(currency)))))
+            .ok_or_else(|| format!("Converted amount of {} is out of range.", self))?;
+        converted
+            .round_dp_with_strategy(0, CONVERSION_ROUNDING)
+            .to_i64()
+            .map(|minor_units| Self::new(minor_units, currency))
+            .ok_or_else(|| format!("Converted amount of {} is out of range.", self))
+    }
+
+    /// Amount with the number of decimal places of the currency, without the currency code
+    pub fn format_amount(&self) -> String {
+        format!("{:.*}", exponent(self.currency) as usize, self.to_decimal())
+    }
+
+    fn ensure_same_currency(&self, other: Money, operation: &str) -> Result<(), String> {
+        if self.currency == other.currency {
+            Ok(())
+        } else {
+            Err(format!(
+                "Cannot {} amounts in different currencies ({} and {}).",
+                operation,
+                self.currency.code(),
+                other.currency.code()
+            ))
+        }
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.format_amount(), self.currency.code())
+    }
+}
+
+/// Serialized as `{ "amount": "9.99", "currency": "EUR" }`, the amount is a string to keep it exact
+impl serde::Serialize for Money {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut money = serializer.serialize_struct("Money", 2)?;
+        money.serialize_field("amount", &self.format_amount())?;
+        money.serialize_field("currency", self.currency.code())?;
+        money.end()
+    }
+}
+
+/// Number of minor unit digits, currencies without minor units (or funds/metals) have none
+pub(crate) fn exponent(currency: Currency) -> u32 {
+    currency.exponent().unwrap_or(0).into()
+}
+
+fn round(value: Decimal, strategy: RoundingStrategy) -> Result<i64, String> {
+    value
+        .round_dp_with_strategy(0, strategy)
+        .to_i64()
+        .ok_or_else(|| "Amount out of range.".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::Money;
+    use claims::{assert_err, assert_ok_eq};
+    use iso_currency::Currency;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn amount_with_cents_is_parsed() {
+        assert_ok_eq!(
+            Money::parse("9.99", Currency::EUR),
+            Money::new(999, Currency::EUR)
+        );
+        assert_ok_eq!(
+            Money::parse("9,9", Currency::EUR),
+            Money::new(990, Currency::EUR)
+        );
+        assert_ok_eq!(
+            Money::parse(" 12 ", Currency::EUR),
+            Money::new(1200, Currency::EUR)
+        );
+    }
+
+    #[test]
+    fn currency_exponent_is_respected_when_parsing() {
+        assert_ok_eq!(
+            Money::parse("1500", Currency::JPY),
+            Money::new(1500, Currency::JPY)
+        );
+        assert_err!(Money::parse("15.5", Currency::JPY));
+        assert_ok_eq!(
+            Money::parse("1.5", Currency::KWD),
+            Money::new(1500, Currency::KWD)
+        );
+        assert_err!(Money::parse("9.999", Currency::EUR));
+    }
+
+    #[test]
+    fn malformed_amounts_are_rejected() {
+        for input in ["", " ", "-1", "1.", ".5", "1.2.3", "abc", "1e3", "1 000"] {
+            assert_err!(Money::parse(input, Currency::EUR), "{input}");
+        }
+    }
+
+    #[test]
+    fn amount_is_formatted_with_minor_units_of_currency() {
+        assert_eq!(Money::new(999, Currency::EUR).to_string(), "9.99 EUR");
+        assert_eq!(Money::new(5, Currency::EUR).to_string(), "0.05 EUR");
+        assert_eq!(Money::new(1500, Currency::JPY).to_string(), "1500 JPY");
+        assert_eq!(Money::new(1500, Currency::BHD).to_string(), "1.500 BHD");
+    }
+
+    #[test]
+    fn mixed_currency_arithmetic_is_rejected() {
+        let euros = Money::new(100, Currency::EUR);
+        let dollars = Money::new(100, Currency::USD);
+        assert_err!(euros.checked_add(dollars));
+        assert_err!(euros.checked_sub(dollars));
+        assert_err!(Money::total(Currency::EUR, [euros, dollars]));
+        assert_ok_eq!(
+            Money::total(Currency::EUR, [euros, euros]),
+            Money::new(200, Currency::EUR)
+        );
+    }
+
+    #[test]
+    fn share_is_rounded_half_up() {
+        let amount = Money::new(999, Currency::EUR);
+        assert_ok_eq!(amount.share(50), Money::new(500, Currency::EUR));
+        assert_ok_eq!(amount.share(100), amount);
+        assert_ok_eq!(amount.share(0), Money::zero(Currency::EUR));
+    }
+
+    #[test]
+    fn division_is_rounded_half_up() {
+        assert_ok_eq!(
+            Money::new(1006, Currency::EUR).divide(12),
+            Money::new(84, Currency::EUR)
+        );
+        assert_ok_eq!(
+            Money::new(1002, Currency::EUR).divide(12),
+            Money::new(84, Currency::EUR)
+        );
+    }
+
+    #[test]
+    fn split_parts_add_up_to_the_total() {
+        let parts = Money::new(1000, Currency::EUR).split(3);
+        assert_eq!(
+            parts,
+            vec![
+                Money::new(334, Currency::EUR),
+                Money::new(333, Currency::EUR),
+                Money::new(333, Currency::EUR)
+            ]
+        );
+    }
+
+    #[test]
+    fn conversion_uses_bankers_rounding() {
+        // 0.25 EUR * 0.5 = 0.125 USD -> 0.12 USD
+        let converted = Money::new(25, Currency::EUR).convert(Decimal::new(5, 1), Currency::USD);
+        assert_ok_eq!(converted, Money::new(12, Currency::USD));
+        // 0.35 EUR * 0.5 = 0.175 USD -> 0.18 USD
+        let converted = Money::new(35, Currency::EUR).convert(Decimal::new(5, 1), Currency::USD);
+        assert_ok_eq!(converted, Money::new(18, Currency::USD));
+    }
+
+    #[test]
+    fn conversion_respects_target_exponent() {
+        let converted =
+            Money::new(1000, Currency::EUR).convert(Decimal::new(15861, 2), Currency::JPY);
+        assert_ok_eq!(converted, Money::new(1586, Currency::JPY));
+    }
+
+    #[test]
+    fn conversion_out_of_range_is_an_error() {
+        let converted = Money::new(i64::MAX, Currency::EUR).convert(Decimal::MAX, Currency::USD);
+        assert_err!(converted);
+    }
+}
@@ -0,0 +1,212 @@
+use chrono::{DateTime, Days, Months, NaiveDate, Utc};
+use unicode_segmentation::UnicodeSegmentation;
+use uuid::Uuid;
+
+/// Days before a card expires the user gets alerted
+pub const CARD_EXPIRY_ALERT_DAYS: u64 = 30;
+
+/// Card or account paying for subscriptions, only the last four digits are ever stored
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PaymentMethod {
+    pub id: PaymentMethodId,
+    /// Name chosen by the user, e.g. `Work Visa`
+    pub label: String,
+    pub kind: PaymentMethodKind,
+    pub last_four: Option<LastFour>,
+    /// Only cards expire
+    pub expiry: Option<CardExpiry>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Payment method about to be saved
+#[derive(Debug, PartialEq)]
+pub(crate) struct NewPaymentMethod {
+    pub label: String,
+    pub kind: PaymentMethodKind,
+    pub last_four: Option<LastFour>,
+    pub expiry: Option<CardExpiry>,
+}
+
+impl NewPaymentMethod {
+    pub fn parse(
+        label: &str,
+        kind: PaymentMethodKind,
+        last_four: Option<&str>,
+        expiry: Option<CardExpiry>,
+    ) -> Result<Self, String> {
+        const LABEL_MAX_LENGTH: usize = 64;
+        let label = label.trim();
+        if label.is_empty() || label.graphemes(true).count() > LABEL_MAX_LENGTH {
+            return Err(format!("{} is not a valid payment method label.", label));
+        }
+        if kind == PaymentMethodKind::Card && expiry.is_none() {
+            return Err("Cards require an expiry date.".into());
+        }
+        if kind != PaymentMethodKind::Card && expiry.is_some() {
+            return Err("Only cards have an expiry date.".into());
+        }
+
+        Ok(Self {
+            label: label.to_owned(),
+            kind,
+            last_four: last_four.map(LastFour::parse).transpose()?,
+            expiry,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct PaymentMethodId(Uuid);
+
+impl From<Uuid> for PaymentMethodId {
+    fn from(value: Uuid) -> Self {
+        Self(value)
+    }
+}
+
+impl From<PaymentMethodId> for Uuid {
+    fn from(value: PaymentMethodId) -> Self {
+        value.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PaymentMethodKind {
+    Card,
+    BankAccount,
+    Paypal,
+    Other,
+}
+
+impl AsRef<str> for PaymentMethodKind {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Card => "card",
+            Self::BankAccount => "bank_account",
+            Self::Paypal => "paypal",
+            Self::Other => "other",
+        }
+    }
+}
+
+impl TryFrom<String> for PaymentMethodKind {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "card" => Ok(Self::Card),
+            "bank_account" => Ok(Self::BankAccount),
+            "paypal" => Ok(Self::Paypal),
+            "other" => Ok(Self::Other),
+            other => Err(format!("{} is not a valid payment method.", other)),
+        }
+    }
+}
+
+/// Last four digits of a card or account number
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(transparent)]
+pub struct LastFour(String);
+
+impl LastFour {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        if value.len() == 4 && value.chars().all(|c| c.is_ascii_digit()) {
+            Ok(Self(value.to_owned()))
+        } else {
+            Err("Only the last four digits of the number can be stored.".into())
+        }
+    }
+}
+
+impl AsRef<str> for LastFour {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// FIXME: same as `TagName`, needed by sqlx to map DB results without validation
+impl From<String> for LastFour {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+/// Month and year printed on a card, valid until the end of the month
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CardExpiry {
+    pub month: u8,
+    pub year: u16,
+}
+
+impl CardExpiry {
+    pub fn parse(month: u8, year: u16) -> Result<Self, String> {
+        if !(1..=12).contains(&month) || !(2000..=2099).contains(&year) {
+            return Err(format!("{:02}/{} is not a valid expiry date.", month, year));
+        }
+        Ok(Self { month, year })
+    }
+
+    /// Last day the card can be charged on
+    pub fn last_day(&self) -> NaiveDate {
+        NaiveDate::from_ymd_opt(self.year.into(), self.month.into(), 1)
+            .and_then(|first| first.checked_add_months(Months::new(1)))
+            .and_then(|next| next.pred_opt())
+            .expect("expiry is validated")
+    }
+
+    /// Whether the card is still valid on `today` but expires within [`CARD_EXPIRY_ALERT_DAYS`]
+    pub fn is_expiring(&self, today: NaiveDate) -> bool {
+        let last_day = self.last_day();
+        today <= last_day
+            && today
+                .checked_add_days(Days::new(CARD_EXPIRY_ALERT_DAYS))
+                .is_some_and(|window| last_day <= window)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+
+    use crate::domain::{
+        fixtures::date, CardExpiry, LastFour, NewPaymentMethod, PaymentMethodKind,
+    };
+
+    #[test]
+    fn full_card_numbers_are_rejected() {
+        assert_ok!(LastFour::parse("4242"));
+        assert_err!(LastFour::parse("4242424242424242"));
+        assert_err!(LastFour::parse("42a2"));
+    }
+
+    #[test]
+    fn cards_require_an_expiry() {
+        assert_err!(NewPaymentMethod::parse(
+            "Visa",
+            PaymentMethodKind::Card,
+            Some("4242"),
+            None
+        ));
+        assert_err!(NewPaymentMethod::parse(
+            "Savings",
+            PaymentMethodKind::BankAccount,
+            None,
+            Some(CardExpiry::parse(1, 2025).unwrap())
+        ));
+    }
+
+    #[test]
+    fn card_is_valid_until_the_end_of_the_month() {
+        let expiry = CardExpiry::parse(2, 2024).unwrap();
+        assert_eq!(expiry.last_day(), date(2024, 2, 29));
+        assert!(!expiry.is_expiring(date(2024, 1, 29)));
+        assert!(expiry.is_expiring(date(2024, 1, 30)));
+        assert!(!expiry.is_expiring(date(2024, 3, 1)));
+        assert_err!(CardExpiry::parse(13, 2024));
+    }
+}
@@ -0,0 +1,219 @@
+use chrono::NaiveDate;
+use iso_currency::Currency;
+
+use super::{
+    BillingPeriodUnit, ExchangeRates, Money, SpendingSummary, Subscription, SubscriptionId,
+};
+
+/// Hypothetical change of a subscription
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ScenarioChange {
+    /// Cancel today, no further renewals
+    Cancel(SubscriptionId),
+    /// Switch to another plan from the next renewal on, e.g. from monthly to yearly billing
+    SwitchPlan {
+        subscription_id: SubscriptionId,
+        billing_period: u8,
+        billing_period_unit: BillingPeriodUnit,
+        price: Money,
+    },
+    ChangeShare {
+        subscription_id: SubscriptionId,
+        share: u8,
+    },
+}
+
+impl ScenarioChange {
+    fn subscription_id(&self) -> SubscriptionId {
+        match self {
+            Self::Cancel(subscription_id)
+            | Self::SwitchPlan {
+                subscription_id, ..
+            }
+            | Self::ChangeShare {
+                subscription_id, ..
+            } => *subscription_id,
+        }
+    }
+
+    fn apply(&self, subscription: &mut Subscription, today: NaiveDate) {
+        match self {
+            Self::Cancel(_) => {
+                let cancelled_at = today.and_hms_opt(0, 0, 0).map(|date| date.and_utc());
+                subscription.cancelled_at = subscription.cancelled_at.or(cancelled_at);
+            }
+            Self::SwitchPlan {
+                billing_period,
+                billing_period_unit,
+                price,
+                ..
+            } => {
+                subscription.billing_period = *billing_period;
+                subscription.billing_period_unit = *billing_period_unit;
+                subscription.price = *price;
+                // Introductory prices belong to the old plan
+                subscription.price_phases.clear();
+            }
+            Self::ChangeShare { share, .. } => subscription.share = *share,
+        }
+    }
+}
+
+/// Upcoming spending in the preferred currency
+#[derive(Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Projection {
+    pub next_12_months: Money,
+    pub monthly_equivalent: Money,
+}
+
+impl From<SpendingSummary> for Projection {
+    fn from(summary: SpendingSummary) -> Self {
+        Self {
+            next_12_months: summary.next_12_months,
+            monthly_equivalent: summary.monthly_equivalent,
+        }
+    }
+}
+
+/// Outcome of a what-if scenario, negative changes are savings
+#[derive(Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Simulation {
+    pub currency: Currency,
+    pub current: Projection,
+    pub simulated: Projection,
+    pub next_12_months_change: Money,
+    pub monthly_equivalent_change: Money,
+}
+
+impl Simulation {
+    /// Runs the forecast with the `changes` applied to copies of the `subscriptions`, nothing
+    /// gets saved
+    pub fn run(
+        subscriptions: &[Subscription],
+        changes: &[ScenarioChange],
+        rates: &ExchangeRates,
+        currency: Currency,
+        today: NaiveDate,
+    ) -> Result<Self, String> {
+        let mut simulated = subscriptions.to_vec();
+        for change in changes {
+            let subscription = simulated
+                .iter_mut()
+                .find(|subscription| subscription.id == change.subscription_id())
+                .ok_or("Scenario changes an unknown subscription.")?;
+            change.apply(subscription, today);
+        }
+
+        let current = Projection::from(SpendingSummary::compute(
+            subscriptions,
+            rates,
+            currency,
+            today,
+        )?);
+        let simulated = Projection::from(SpendingSummary::compute(
+            &simulated, rates, currency, today,
+        )?);
+
+        Ok(Self {
+            currency,
+            next_12_months_change: simulated
+                .next_12_months
+                .checked_sub(current.next_12_months)?,
+            monthly_equivalent_change: simulated
+                .monthly_equivalent
+                .checked_sub(current.monthly_equivalent)?,
+            current,
+            simulated,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::assert_err;
+    use iso_currency::Currency;
+    use uuid::Uuid;
+
+    use crate::domain::{
+        fixtures::{date, monthly_subscription},
+        BillingPeriodUnit, ExchangeRates, Money, ScenarioChange, Simulation,
+    };
+
+    #[test]
+    fn cancelling_saves_all_upcoming_renewals() {
+        let netflix = monthly_subscription(1000, Currency::EUR, date(2023, 10, 1));
+        let spotify = monthly_subscription(500, Currency::EUR, date(2023, 10, 5));
+        let changes = [ScenarioChange::Cancel(netflix.id)];
+
+        let simulation = Simulation::run(
+            &[netflix, spotify],
+            &changes,
+            &ExchangeRates::new([]),
+            Currency::EUR,
+            date(2023, 9, 20),
+        )
+        .unwrap();
+
+        assert_eq!(
+            simulation.current.next_12_months,
+            Money::new(18000, Currency::EUR)
+        );
+        assert_eq!(
+            simulation.next_12_months_change,
+            Money::new(-12000, Currency::EUR)
+        );
+        assert_eq!(
+            simulation.simulated.monthly_equivalent,
+            Money::new(500, Currency::EUR)
+        );
+    }
+
+    #[test]
+    fn switching_to_yearly_billing_and_sharing() {
+        let subscription = monthly_subscription(1000, Currency::EUR, date(2023, 10, 1));
+        let changes = [
+            ScenarioChange::SwitchPlan {
+                subscription_id: subscription.id,
+                billing_period: 1,
+                billing_period_unit: BillingPeriodUnit::Year,
+                price: Money::new(10000, Currency::EUR),
+            },
+            ScenarioChange::ChangeShare {
+                subscription_id: subscription.id,
+                share: 50,
+            },
+        ];
+
+        let simulation = Simulation::run(
+            &[subscription],
+            &changes,
+            &ExchangeRates::new([]),
+            Currency::EUR,
+            date(2023, 9, 20),
+        )
+        .unwrap();
+
+        assert_eq!(
+            simulation.simulated.next_12_months,
+            Money::new(5000, Currency::EUR)
+        );
+        assert_eq!(
+            simulation.next_12_months_change,
+            Money::new(-7000, Currency::EUR)
+        );
+    }
+
+    #[test]
+    fn unknown_subscription_is_rejected() {
+        let subscription = monthly_subscription(1000, Currency::EUR, date(2023, 10, 1));
+        assert_err!(Simulation::run(
+            &[subscription],
+            &[ScenarioChange::Cancel(Uuid::new_v4().into())],
+            &ExchangeRates::new([]),
+            Currency::EUR,
+            date(2023, 9, 20),
+        ));
+    }
+}
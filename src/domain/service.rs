@@ -2,18 +2,278 @@ use chrono::{DateTime, Utc};
 use unicode_segmentation::UnicodeSegmentation;
 use uuid::Uuid;
 
-#[derive(Debug, serde::Serialize)]
+use super::{BillingPeriodUnit, Category, Money, NewServiceAlias, Subscription, User, UserRole};
+
+#[derive(Debug, serde::Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
 pub struct Service {
     pub id: Uuid,
+    #[sqlx(try_from = "String")]
     pub name: ServiceName,
+    /// Category used for subscriptions of the service unless they override it
+    #[sqlx(try_from = "String")]
+    pub category: Category,
     pub created_at: DateTime<Utc>,
+    /// Only approved services are visible to everyone, others only to the user who suggested them
+    #[sqlx(try_from = "String")]
+    pub status: ServiceStatus,
+    /// User who created the service, `None` for services added by admins
+    pub suggested_by: Option<Uuid>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub rejection_reason: Option<String>,
+    /// Approved service a duplicate suggestion was merged into
+    pub merged_into: Option<Uuid>,
+    pub website_url: Option<String>,
+    /// Page where the subscription is cancelled
+    pub cancellation_url: Option<String>,
+    /// `None` without a logo, otherwise served from `/api/services/{id}/logo?v={logoVersion}`
+    pub logo_version: Option<i32>,
+    /// Known plans, loaded separately
+    #[sqlx(skip)]
+    pub plans: Vec<ServicePlan>,
 }
 
-#[derive(Debug, serde::Serialize)]
+impl Service {
+    /// Approved services are public, suggestions are visible to their author and admins
+    pub(crate) fn is_visible_to(&self, user: Option<&User>) -> bool {
+        self.status == ServiceStatus::Approved
+            || user.is_some_and(|user| user.role == UserRole::Admin || self.is_suggested_by(user))
+    }
+
+    /// Admins edit any service, users only their suggestions waiting for a review
+    pub(crate) fn is_editable_by(&self, user: &User) -> bool {
+        user.role == UserRole::Admin
+            || (self.status == ServiceStatus::Pending && self.is_suggested_by(user))
+    }
+
+    fn is_suggested_by(&self, user: &User) -> bool {
+        self.suggested_by == Some(user.id.into())
+    }
+}
+
+/// Price of a plan tier in one currency, e.g. monthly or yearly billing of the same tier are
+/// separate plans
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServicePlan {
+    pub id: ServicePlanId,
+    pub service_id: ServiceId,
+    /// Name of the tier, e.g. `Premium`
+    pub name: String,
+    pub price: Money,
+    /// Country the price applies to, `None` for everywhere else
+    pub region: Option<Region>,
+    pub billing_period: u8,
+    pub billing_period_unit: BillingPeriodUnit,
+    /// Number of people who can use the plan
+    pub seats: u16,
+}
+
+impl ServicePlan {
+    /// Price of the plan for a whole year
+    pub fn yearly_cost(&self) -> Result<Money, String> {
+        self.price.multiply(
+            self.billing_period_unit
+                .renewals_per_year(self.billing_period),
+        )
+    }
+}
+
+/// Plan about to be added to a service
+#[derive(Debug, PartialEq)]
+pub struct NewServicePlan {
+    pub name: String,
+    pub price: Money,
+    pub region: Option<Region>,
+    pub billing_period: u8,
+    pub billing_period_unit: BillingPeriodUnit,
+    pub seats: u16,
+}
+
+impl NewServicePlan {
+    pub fn parse(
+        name: &str,
+        price: Money,
+        region: Option<Region>,
+        billing_period: u8,
+        billing_period_unit: BillingPeriodUnit,
+        seats: u16,
+    ) -> Result<Self, String> {
+        const PLAN_NAME_MAX_LENGTH: usize = 64;
+        let name = name.trim();
+        if name.is_empty() || name.graphemes(true).count() > PLAN_NAME_MAX_LENGTH {
+            return Err(format!("{} is not a valid plan name.", name));
+        }
+        if billing_period == 0 {
+            return Err("Billing period has to be greater than 0.".into());
+        }
+        if price.is_negative() {
+            return Err("Price cannot be negative.".into());
+        }
+        if seats == 0 || seats > i16::MAX as u16 {
+            return Err("Plan has to have at least one seat.".into());
+        }
+
+        Ok(Self {
+            name: name.to_owned(),
+            price,
+            region,
+            billing_period,
+            billing_period_unit,
+            seats,
+        })
+    }
+}
+
+/// Two letter ISO 3166 country code, e.g. `DE`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(transparent)]
+pub struct Region(String);
+
+impl Region {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim().to_ascii_uppercase();
+        if value.len() == 2 && value.chars().all(|c| c.is_ascii_uppercase()) {
+            Ok(Self(value))
+        } else {
+            Err(format!("{} is not a valid country code.", value))
+        }
+    }
+}
+
+impl AsRef<str> for Region {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// FIXME: same as `TagName`, needed to map DB results without validation
+impl From<String> for Region {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct ServicePlanId(Uuid);
+
+impl From<Uuid> for ServicePlanId {
+    fn from(value: Uuid) -> Self {
+        Self(value)
+    }
+}
+
+impl From<ServicePlanId> for Uuid {
+    fn from(value: ServicePlanId) -> Self {
+        value.0
+    }
+}
+
+/// Yearly cost of a subscription compared to the other plans of its service
+#[derive(Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PlanComparison {
+    /// What the user pays in a year now
+    pub yearly_cost: Money,
+    pub plans: Vec<PlanAlternative>,
+}
+
+#[derive(Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PlanAlternative {
+    #[serde(flatten)]
+    pub plan: ServicePlan,
+    /// Part of the yearly price of the plan paid by the user
+    pub yearly_cost: Money,
+    /// Change of the yearly cost when switching, negative values are savings
+    pub yearly_difference: Money,
+}
+
+impl PlanComparison {
+    /// Compares with plans in the currency of the subscription, the share of the user applies to
+    /// all of them. Cheapest plans come first.
+    pub fn compute(subscription: &Subscription, plans: &[ServicePlan]) -> Result<Self, String> {
+        let yearly_cost = subscription.yearly_cost()?;
+        let mut alternatives = plans
+            .iter()
+            .filter(|plan| plan.price.currency() == subscription.price.currency())
+            .map(|plan| {
+                let plan_cost = plan.yearly_cost()?.share(subscription.share)?;
+                Ok(PlanAlternative {
+                    plan: plan.clone(),
+                    yearly_cost: plan_cost,
+                    yearly_difference: plan_cost.checked_sub(yearly_cost)?,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        alternatives.sort_by_key(|alternative| alternative.yearly_cost.minor_units());
+
+        Ok(Self {
+            yearly_cost,
+            plans: alternatives,
+        })
+    }
+}
+
+/// Review state of a service in the catalog
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ServiceStatus {
+    /// Suggested by a user, waiting for an admin
+    Pending,
+    Approved,
+    Rejected,
+    /// Duplicate of another service which took over its subscriptions
+    Merged,
+}
+
+impl AsRef<str> for ServiceStatus {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Pending => "pending",
+            Self::Approved => "approved",
+            Self::Rejected => "rejected",
+            Self::Merged => "merged",
+        }
+    }
+}
+
+impl TryFrom<String> for ServiceStatus {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "pending" => Ok(Self::Pending),
+            "approved" => Ok(Self::Approved),
+            "rejected" => Ok(Self::Rejected),
+            "merged" => Ok(Self::Merged),
+            other => Err(format!("{} is not a valid service status.", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
 pub struct ServiceId(Uuid);
 
+impl From<Uuid> for ServiceId {
+    fn from(value: Uuid) -> Self {
+        Self(value)
+    }
+}
+
+impl From<ServiceId> for Uuid {
+    fn from(value: ServiceId) -> Self {
+        value.0
+    }
+}
+
 pub struct NewService {
     pub name: ServiceName,
+    pub category: Category,
+    pub plans: Vec<NewServicePlan>,
+    pub aliases: Vec<NewServiceAlias>,
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -51,10 +311,162 @@ impl From<String> for ServiceName {
     }
 }
 
+/// Absolute `https` link to a page of the service
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceUrl(String);
+
+impl ServiceUrl {
+    pub fn parse(s: &str) -> Result<ServiceUrl, String> {
+        const SERVICE_URL_MAX_LENGTH: usize = 2048;
+        let s = s.trim();
+        let host = s
+            .strip_prefix("https://")
+            .and_then(|rest| rest.split(['/', '?', '#']).next())
+            .unwrap_or_default();
+        let is_valid_host = host.contains('.') && !host.starts_with('.') && !host.ends_with('.');
+        let contains_whitespace = s.chars().any(char::is_whitespace);
+
+        if !is_valid_host || contains_whitespace || s.len() > SERVICE_URL_MAX_LENGTH {
+            Err(format!("{} is not a valid https URL.", s))
+        } else {
+            Ok(Self(s.to_owned()))
+        }
+    }
+}
+
+impl AsRef<str> for ServiceUrl {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::domain::ServiceName;
+    use crate::domain::{
+        fixtures::monthly_subscription, BillingPeriodUnit, Category, Money, NewServicePlan,
+        Password, PlanComparison, Region, Service, ServiceName, ServicePlan, ServiceStatus,
+        ServiceUrl, User, UserRole,
+    };
+    use chrono::NaiveDate;
     use claims::{assert_err, assert_ok};
+    use iso_currency::Currency;
+    use uuid::Uuid;
+
+    fn plan(
+        amount: i64,
+        currency: Currency,
+        billing_period_unit: BillingPeriodUnit,
+    ) -> ServicePlan {
+        ServicePlan {
+            id: Uuid::new_v4().into(),
+            service_id: Uuid::new_v4().into(),
+            name: "Premium".into(),
+            price: Money::new(amount, currency),
+            region: None,
+            billing_period: 1,
+            billing_period_unit,
+            seats: 1,
+        }
+    }
+
+    fn suggestion(author: &User) -> Service {
+        Service {
+            id: Uuid::new_v4(),
+            name: "Mubi".to_string().into(),
+            category: Category::default(),
+            created_at: chrono::Utc::now(),
+            status: ServiceStatus::Pending,
+            suggested_by: Some(author.id.into()),
+            reviewed_at: None,
+            rejection_reason: None,
+            merged_into: None,
+            website_url: None,
+            cancellation_url: None,
+            logo_version: None,
+            plans: vec![],
+        }
+    }
+
+    #[test]
+    fn suggestions_are_private_until_approved() {
+        let author = User::new("author", Password::parse("secret").unwrap());
+        let other = User::new("other", Password::parse("secret").unwrap());
+        let mut admin = User::new("admin", Password::parse("secret").unwrap());
+        admin.role = UserRole::Admin;
+        let mut service = suggestion(&author);
+
+        assert!(service.is_visible_to(Some(&author)));
+        assert!(service.is_visible_to(Some(&admin)));
+        assert!(!service.is_visible_to(Some(&other)));
+        assert!(!service.is_visible_to(None));
+        assert!(service.is_editable_by(&author));
+
+        service.status = ServiceStatus::Approved;
+        assert!(service.is_visible_to(None));
+        assert!(!service.is_editable_by(&author));
+        assert!(service.is_editable_by(&admin));
+    }
+
+    #[test]
+    fn region_is_a_two_letter_country_code() {
+        assert_eq!(Region::parse(" de ").unwrap().as_ref(), "DE");
+        for region in ["", "D", "DEU", "D1", "É"] {
+            assert_err!(Region::parse(region), "{region}");
+        }
+    }
+
+    #[test]
+    fn plan_needs_a_seat() {
+        let price = Money::new(1299, Currency::EUR);
+        assert_ok!(NewServicePlan::parse(
+            "Family",
+            price,
+            None,
+            1,
+            BillingPeriodUnit::Month,
+            6
+        ));
+        assert_err!(NewServicePlan::parse(
+            "Family",
+            price,
+            None,
+            1,
+            BillingPeriodUnit::Month,
+            0
+        ));
+    }
+
+    #[test]
+    fn switching_to_yearly_billing_shows_the_yearly_saving() {
+        let mut subscription = monthly_subscription(
+            1299,
+            Currency::EUR,
+            NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(),
+        );
+        subscription.share = 50;
+        let plans = [
+            plan(12999, Currency::EUR, BillingPeriodUnit::Year),
+            plan(1299, Currency::EUR, BillingPeriodUnit::Month),
+            plan(1399, Currency::USD, BillingPeriodUnit::Month),
+        ];
+
+        let comparison = PlanComparison::compute(&subscription, &plans).unwrap();
+
+        assert_eq!(comparison.yearly_cost, Money::new(7794, Currency::EUR));
+        assert_eq!(comparison.plans.len(), 2);
+        assert_eq!(
+            comparison.plans[0].yearly_cost,
+            Money::new(6500, Currency::EUR)
+        );
+        assert_eq!(
+            comparison.plans[0].yearly_difference,
+            Money::new(-1294, Currency::EUR)
+        );
+        assert_eq!(
+            comparison.plans[1].yearly_difference,
+            Money::zero(Currency::EUR)
+        );
+    }
 
     #[test]
     fn a_64_grapheme_long_name_is_valid() {
@@ -101,6 +513,15 @@ mod tests {
         assert_eq!(service_name.as_ref(), "Netflix");
     }
 
+    #[test]
+    fn only_absolute_https_urls_are_accepted() {
+        assert_ok!(ServiceUrl::parse(" https://www.netflix.com/cancelplan "));
+        assert_err!(ServiceUrl::parse("http://www.netflix.com"));
+        assert_err!(ServiceUrl::parse("https:///cancelplan"));
+        assert_err!(ServiceUrl::parse("https://localhost/account"));
+        assert_err!(ServiceUrl::parse("https://netflix.com/cancel plan"));
+    }
+
     #[test]
     #[ignore] // Check comment above `impl From<String>`
     fn from_string() {
@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+use unicode_segmentation::UnicodeSegmentation;
+use uuid::Uuid;
+
+use super::Category;
+
+/// Number of results returned by the search unless the client asks for another one
+pub const DEFAULT_SEARCH_LIMIT: u8 = 10;
+pub const MAX_SEARCH_LIMIT: u8 = 25;
+
+/// Form of a name used for matching: lowercase, without accents, punctuation and repeated
+/// whitespace, e.g. `Crème+ TV` becomes `creme tv`
+pub fn normalize_name(name: &str) -> String {
+    let mapped: String = name
+        .nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+
+    mapped.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Alternative name of a service, e.g. the descriptor on a bank statement
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceAlias {
+    pub id: Uuid,
+    pub service_id: Uuid,
+    pub alias: String,
+}
+
+/// Alias about to be added to a service
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewServiceAlias {
+    pub alias: String,
+    pub normalized: String,
+}
+
+impl NewServiceAlias {
+    pub fn parse(alias: &str) -> Result<Self, String> {
+        const ALIAS_MAX_LENGTH: usize = 64;
+        let alias = alias.trim();
+        let normalized = normalize_name(alias);
+        if normalized.is_empty() || alias.graphemes(true).count() > ALIAS_MAX_LENGTH {
+            return Err(format!("{} is not a valid alias.", alias));
+        }
+
+        Ok(Self {
+            alias: alias.to_owned(),
+            normalized,
+        })
+    }
+}
+
+/// Name or alias of a service matching the search query
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchCandidate {
+    pub service_id: Uuid,
+    pub name: String,
+    pub category: Category,
+    /// `None` when the canonical name matched
+    pub alias: Option<String>,
+    /// Trigram similarity of the query to the best matching word sequence, between 0 and 1
+    pub similarity: f32,
+    /// The matched name starts with the query
+    pub is_prefix: bool,
+    /// Number of active subscriptions of the service
+    pub popularity: i64,
+}
+
+/// Search result, the best match of a service
+#[derive(Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceMatch {
+    pub id: Uuid,
+    pub name: String,
+    pub category: Category,
+    /// Alias which matched the query instead of the name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matched_alias: Option<String>,
+    pub popularity: i64,
+    pub score: f32,
+}
+
+impl SearchCandidate {
+    /// Similarity boosted for prefixes (the user is still typing) and, logarithmically, for
+    /// popular services so that a few subscriptions do not outweigh a better match
+    fn score(&self) -> f32 {
+        let prefix_bonus = if self.is_prefix { 0.2 } else { 0.0 };
+        let popularity_bonus = 0.05 * (1.0 + self.popularity.max(0) as f32).ln();
+        self.similarity + prefix_bonus + popularity_bonus
+    }
+}
+
+/// Keeps the best match of every service and orders them by score, at most `limit` of them
+pub fn rank_matches(candidates: Vec<SearchCandidate>, limit: usize) -> Vec<ServiceMatch> {
+    let mut best: HashMap<Uuid, (f32, SearchCandidate)> = HashMap::new();
+    for candidate in candidates {
+        let score = candidate.score();
+        match best.get(&candidate.service_id) {
+            Some((best_score, _)) if *best_score >= score => {}
+            _ => {
+                best.insert(candidate.service_id, (score, candidate));
+            }
+        }
+    }
+
+    let mut matches: Vec<ServiceMatch> = best
+        .into_values()
+        .map(|(score, candidate)| ServiceMatch {
+            id: candidate.service_id,
+            name: candidate.name,
+            category: candidate.category,
+            matched_alias: candidate.alias,
+            popularity: candidate.popularity,
+            score,
+        })
+        .collect();
+    matches.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| b.popularity.cmp(&a.popularity))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    matches.truncate(limit);
+
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::{normalize_name, rank_matches, Category, NewServiceAlias, SearchCandidate};
+    use claims::assert_err;
+    use uuid::Uuid;
+
+    fn candidate(
+        service_id: Uuid,
+        name: &str,
+        similarity: f32,
+        popularity: i64,
+    ) -> SearchCandidate {
+        SearchCandidate {
+            service_id,
+            name: name.into(),
+            category: Category::default(),
+            alias: None,
+            similarity,
+            is_prefix: false,
+            popularity,
+        }
+    }
+
+    #[test]
+    fn names_are_normalized_for_matching() {
+        assert_eq!(normalize_name("  Netflix.com "), "netflix com");
+        assert_eq!(normalize_name("NFLX*"), "nflx");
+        assert_eq!(normalize_name("Crème+ TV"), "creme tv");
+        assert_eq!(normalize_name("Кинопоиск"), "кинопоиск");
+    }
+
+    #[test]
+    fn alias_needs_a_letter_or_digit() {
+        assert_err!(NewServiceAlias::parse("***"));
+        assert_eq!(NewServiceAlias::parse(" NFLX* ").unwrap().alias, "NFLX*");
+    }
+
+    #[test]
+    fn popular_services_win_close_matches() {
+        let netflix = Uuid::new_v4();
+        let netfly = Uuid::new_v4();
+        let candidates = vec![
+            candidate(netfly, "Netfly", 0.62, 0),
+            candidate(netflix, "Netflix", 0.6, 120),
+            SearchCandidate {
+                alias: Some("NFLX*".into()),
+                ..candidate(netflix, "Netflix", 0.2, 120)
+            },
+        ];
+
+        let matches = rank_matches(candidates, 10);
+
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].id, netflix);
+        assert_eq!(matches[0].matched_alias, None);
+        assert_eq!(matches[1].id, netfly);
+    }
+}
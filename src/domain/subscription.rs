@@ -1,45 +1,64 @@
-use std::time::Duration;
-
 use chrono::{DateTime, Days, Months, NaiveDate, Utc};
-use iso_currency::Currency;
+use rust_decimal::Decimal;
 use uuid::Uuid;
 
-use super::{ServiceId, UserId};
+use super::{
+    Category, Money, PaymentMethodId, ServiceId, ServicePlanId, TagId, UsageTarget, UserId,
+};
 
-#[derive(Debug, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
 pub(crate) struct Subscription {
-    id: SubscriptionId,
+    pub id: SubscriptionId,
     /// Owner of the subscription
-    user_id: UserId,
+    pub user_id: UserId,
     /// Name of subscription provided by user (or name of [`Service`](crate::domain::Service))
-    name: String,
+    pub name: String,
     /// Optional description of the subscription
-    description: Option<String>,
-    /// Amount in subunit for specified [`currency_code`] (e.g. cents for EUR)
-    amount: u64,
-    /// The currency code (ISO 4217 format) of the [`amount`](Subscription::amount)
-    currency: Currency,
+    pub description: Option<String>,
+    /// Regular price charged on every renewal once the [`price_phases`](Subscription::price_phases)
+    /// are over
+    pub price: Money,
+    /// Percentage (0 - 100) of the [`price`](Subscription::price) paid by the user, the rest is
+    /// covered by others (e.g. flatmates)
+    pub share: u8,
     /// Calculated next renewal date using [`billing_period`](Subscription::billing_period) and
     /// [`billing_period_unit`](Subscription::billing_period_unit)
-    next_renewal_date: NaiveDate,
+    pub next_renewal_date: NaiveDate,
     /// Specifies that the subscription is reneved every X [`BillingPeriodUnit`](BillingPeriodUnit)
-    billing_period: u8,
+    pub billing_period: u8,
     /// Specifies how frequently is the subscription renewed. Used in combination with [`billing_period`](Subscription::billing_period)
-    billing_period_unit: BillingPeriodUnit,
+    pub billing_period_unit: BillingPeriodUnit,
     /// ID of assigned service that this subscription is bound to
-    service_id: ServiceId,
+    pub service_id: ServiceId,
+    /// Plan of the service the subscription was created from
+    pub plan_id: Option<ServicePlanId>,
+    /// Category of the subscription, defaults to the category of the service
+    pub category: Category,
+    /// Tags assigned by the user
+    pub tag_ids: Vec<TagId>,
+    /// Free or reduced trial preceding the regular billing
+    pub trial: Option<Trial>,
+    /// Introductory prices of the first renewals, in order
+    pub price_phases: Vec<PricePhase>,
+    /// Minimum term with a notice period, e.g. of a gym membership
+    pub contract: Option<Contract>,
+    /// Card or account the subscription is charged to
+    pub payment_method_id: Option<PaymentMethodId>,
+    /// How often the user wants to use the subscription
+    pub usage_target: Option<UsageTarget>,
     /// Date when user added subscription to the system
-    created_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
     /// Last update date
-    updated_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
     /// Describes when the user marked subscription as cancelled
-    cancelled_at: Option<DateTime<Utc>>,
+    pub cancelled_at: Option<DateTime<Utc>>,
     /// The reason for cancelling the subscription
-    cancel_reason: Option<String>,
+    pub cancel_reason: Option<String>,
     /// Allows to set from when the user is subscribed to the service
-    subscribed_at: Option<NaiveDate>,
+    pub subscribed_at: Option<NaiveDate>,
     /// Date when user deleted a subscription
-    deleted_at: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 impl Subscription {
@@ -63,16 +82,706 @@ impl Subscription {
                 .expect("date out of range"),
         }
     }
+
+    pub fn status(&self) -> SubscriptionStatus {
+        if self.deleted_at.is_some() {
+            SubscriptionStatus::Deleted
+        } else if self.cancelled_at.is_some() {
+            SubscriptionStatus::Cancelled
+        } else {
+            SubscriptionStatus::Active
+        }
+    }
+
+    /// Part of the [`price`](Subscription::price) paid by the user
+    pub fn user_price(&self) -> Result<Money, String> {
+        self.price.share(self.share)
+    }
+
+    /// Price of the `occurrence`-th paid renewal (counted from 0), taken from the
+    /// [`price_phases`](Subscription::price_phases) before falling back to the regular price
+    pub fn price_of_renewal(&self, occurrence: u32) -> Money {
+        let mut remaining = occurrence;
+        for phase in &self.price_phases {
+            match remaining.checked_sub(phase.cycles.into()) {
+                Some(rest) => remaining = rest,
+                None => return phase.price,
+            }
+        }
+        self.price
+    }
+
+    /// First renewal after `today` charging a different price than the renewal before it
+    pub fn next_price_change(&self, today: NaiveDate) -> Option<PriceChange> {
+        if self.price_phases.is_empty() {
+            return None;
+        }
+        let first = self.first_renewal()?;
+        let mut occurrence = 0u32;
+        for phase in &self.price_phases {
+            occurrence += u32::from(phase.cycles);
+            let date = self.renewal(first.checked_add(occurrence.try_into().ok()?)?)?;
+            let previous = self.price_of_renewal(occurrence.saturating_sub(1));
+            let price = self.price_of_renewal(occurrence);
+            if date > today && price != previous {
+                return Some(PriceChange {
+                    date,
+                    previous,
+                    price,
+                });
+            }
+        }
+        None
+    }
+
+    /// Regular price paid by the user in a year, regardless of trials, phases and cancellation
+    pub fn yearly_cost(&self) -> Result<Money, String> {
+        let yearly_price = self.price.multiply(
+            self.billing_period_unit
+                .renewals_per_year(self.billing_period),
+        )?;
+        yearly_price.share(self.share)
+    }
+
+    /// Whether the trial has not converted to the regular price yet
+    pub fn is_trialing(&self, today: NaiveDate) -> bool {
+        self.trial
+            .as_ref()
+            .is_some_and(|trial| today < trial.ends_on)
+    }
+
+    /// Amounts paid by the user within `[from, to)`, the trial price when the trial starts and
+    /// the regular price on every renewal after the conversion
+    pub fn charges_between(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<(NaiveDate, Money)>, String> {
+        let mut charges = vec![];
+
+        if let Some(trial) = &self.trial {
+            let start = self.start_date();
+            let cancelled = self
+                .cancelled_at
+                .is_some_and(|cancelled_at| cancelled_at.date_naive() <= start);
+            if !trial.price.is_zero()
+                && self.deleted_at.is_none()
+                && !cancelled
+                && (from..to).contains(&start)
+            {
+                charges.push((start, trial.price.share(self.share)?));
+            }
+        }
+
+        let first = match self.price_phases.is_empty() {
+            true => None,
+            false => self.first_renewal(),
+        };
+        for (n, date) in self.renewals_between(from, to) {
+            let occurrence = first
+                .and_then(|first| u32::try_from(n - first).ok())
+                .unwrap_or(u32::MAX);
+            charges.push((date, self.price_of_renewal(occurrence).share(self.share)?));
+        }
+        Ok(charges)
+    }
+
+    /// End of the contract term running on `today`, auto-renewing contracts are extended by one
+    /// billing period at a time once the minimum term is over
+    pub fn term_end(&self, today: NaiveDate) -> Option<NaiveDate> {
+        let contract = self.contract.as_ref()?;
+        if !contract.auto_renew || contract.ends_on >= today {
+            return Some(contract.ends_on);
+        }
+        if self.billing_period == 0 {
+            return None;
+        }
+        (1..)
+            .map(|n| {
+                self.billing_period_unit
+                    .shift(contract.ends_on, n * i32::from(self.billing_period))
+            })
+            .find(|date| date.is_none_or(|date| date >= today))
+            .flatten()
+    }
+
+    /// Latest day the user can cancel on to avoid being bound for another term, `None` when the
+    /// contract does not renew
+    pub fn cancellation_deadline(&self, today: NaiveDate) -> Option<CancellationDeadline> {
+        let contract = self.contract.as_ref()?;
+        if !contract.auto_renew {
+            return None;
+        }
+
+        let term_ends_on = self.term_end(today)?;
+        let cancel_by = contract.cancel_by(term_ends_on)?;
+        if cancel_by >= today {
+            return Some(CancellationDeadline {
+                cancel_by,
+                term_ends_on,
+            });
+        }
+        // Too late for the current term, the notice applies to the next one
+        let term_ends_on = self
+            .billing_period_unit
+            .shift(term_ends_on, i32::from(self.billing_period))?;
+        Some(CancellationDeadline {
+            cancel_by: contract.cancel_by(term_ends_on)?,
+            term_ends_on,
+        })
+    }
+
+    /// Day the user started paying (or trialing), the creation date when unknown
+    fn start_date(&self) -> NaiveDate {
+        self.subscribed_at
+            .unwrap_or_else(|| self.created_at.date_naive())
+    }
+
+    /// First day a renewal is charged at, the trial is free of renewals
+    fn first_charge_date(&self) -> NaiveDate {
+        match &self.trial {
+            Some(trial) => trial.ends_on.max(self.start_date()),
+            None => self.start_date(),
+        }
+    }
+
+    /// Index (see [`renewal`](Subscription::renewal)) of the first charged renewal
+    fn first_renewal(&self) -> Option<i32> {
+        if self.billing_period == 0 {
+            return None;
+        }
+        let first_charge = self.first_charge_date();
+        let mut n = 0;
+        if self.renewal(0)? >= first_charge {
+            while self.renewal(n - 1).is_some_and(|date| date >= first_charge) {
+                n -= 1;
+            }
+        } else {
+            while self.renewal(n)? < first_charge {
+                n += 1;
+            }
+        }
+        Some(n)
+    }
+
+    /// Dates of all renewals (charges) falling within `[from, to)`.
+    ///
+    /// The schedule is anchored on [`next_renewal_date`](Subscription::next_renewal_date) and
+    /// bounded by [`subscribed_at`](Subscription::subscribed_at) (or the creation date when unknown)
+    /// and the cancellation date. Renewals start only once the [`trial`](Subscription::trial)
+    /// converts. Every date comes with its index as passed to [`renewal`](Subscription::renewal).
+    pub fn renewals_between(&self, from: NaiveDate, to: NaiveDate) -> Vec<(i32, NaiveDate)> {
+        if self.billing_period == 0 || self.deleted_at.is_some() {
+            return vec![];
+        }
+
+        let from = from.max(self.first_charge_date());
+        let to = self
+            .cancelled_at
+            .map(|cancelled_at| to.min(cancelled_at.date_naive()))
+            .unwrap_or(to);
+        let to = match &self.contract {
+            Some(contract) if !contract.auto_renew => to.min(contract.ends_on),
+            _ => to,
+        };
+
+        let mut renewals = vec![];
+
+        let past = (1..)
+            .map(|n| (-n, self.renewal(-n)))
+            .take_while(|(_, date)| date.is_some_and(|date| date >= from));
+        renewals.extend(past.filter_map(|(n, date)| Some((n, date?))));
+        renewals.reverse();
+
+        let upcoming = (0..)
+            .map(|n| (n, self.renewal(n)))
+            .take_while(|(_, date)| date.is_some_and(|date| date < to));
+        renewals.extend(
+            upcoming
+                .filter_map(|(n, date)| Some((n, date?)))
+                .filter(|(_, date)| *date >= from),
+        );
+
+        renewals
+    }
+
+    /// Date of the `n`-th renewal counted from [`next_renewal_date`](Subscription::next_renewal_date),
+    /// negative values go back in time
+    pub fn renewal(&self, n: i32) -> Option<NaiveDate> {
+        let count = n.checked_mul(i32::from(self.billing_period))?;
+        self.billing_period_unit
+            .shift(self.next_renewal_date, count)
+    }
+}
+
+/// Days before the conversion of a trial the user gets reminded unless chosen otherwise
+pub const DEFAULT_TRIAL_REMINDER_DAYS: u8 = 3;
+
+/// Trial phase of a subscription
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Trial {
+    /// Day the subscription converts to the regular price, the first regular charge
+    pub ends_on: NaiveDate,
+    /// Charged once when the trial starts, zero for free trials
+    pub price: Money,
+    /// How many days before [`ends_on`](Trial::ends_on) the user gets reminded
+    pub reminder_days: u8,
+}
+
+impl Trial {
+    pub fn reminder_date(&self) -> Option<NaiveDate> {
+        self.ends_on
+            .checked_sub_days(Days::new(self.reminder_days.into()))
+    }
 }
 
-#[derive(Debug, serde::Serialize)]
+/// Days before the [`cancellation_deadline`](Subscription::cancellation_deadline) the user gets
+/// reminded
+pub const CANCEL_BY_REMINDER_DAYS: u8 = 14;
+
+/// Minimum term of a subscription
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Contract {
+    /// Last day of the minimum term
+    pub ends_on: NaiveDate,
+    /// Whether the contract continues unless cancelled within the notice period, otherwise it
+    /// simply ends and no renewals are charged after [`ends_on`](Contract::ends_on)
+    pub auto_renew: bool,
+    /// Cancellation has to be given this many [`notice_period_unit`](Contract::notice_period_unit)
+    /// before the end of a term
+    pub notice_period: u8,
+    pub notice_period_unit: BillingPeriodUnit,
+}
+
+/// Notice has to be given by [`cancel_by`](CancellationDeadline::cancel_by) to leave the
+/// contract at the end of the term
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CancellationDeadline {
+    pub cancel_by: NaiveDate,
+    pub term_ends_on: NaiveDate,
+}
+
+impl Contract {
+    fn cancel_by(&self, term_end: NaiveDate) -> Option<NaiveDate> {
+        self.notice_period_unit
+            .shift(term_end, -i32::from(self.notice_period))
+    }
+}
+
+/// Introductory price, e.g. "€1 for 3 months" before the regular price
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PricePhase {
+    /// Number of renewals charged at this price
+    pub cycles: u16,
+    pub price: Money,
+}
+
+/// Days before a phase raises the price the user gets reminded
+pub const PRICE_CHANGE_REMINDER_DAYS: u8 = 7;
+
+/// Renewal at which the price of a subscription changes between phases
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceChange {
+    pub date: NaiveDate,
+    /// Price charged by the renewal before
+    pub previous: Money,
+    pub price: Money,
+}
+
+impl PriceChange {
+    pub fn is_increase(&self) -> bool {
+        self.price.minor_units() > self.previous.minor_units()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SubscriptionStatus {
+    Active,
+    /// Marked as cancelled by the user, still listed
+    Cancelled,
+    /// Removed by the user, hidden unless asked for explicitly
+    Deleted,
+}
+
+/// Subscription about to be created by the user
+pub(crate) struct NewSubscription {
+    pub service_id: ServiceId,
+    pub plan_id: Option<ServicePlanId>,
+    pub name: String,
+    pub description: Option<String>,
+    pub price: Money,
+    pub share: u8,
+    pub next_renewal_date: NaiveDate,
+    pub billing_period: u8,
+    pub billing_period_unit: BillingPeriodUnit,
+    pub subscribed_at: Option<NaiveDate>,
+    /// Overrides the category of the service
+    pub category: Option<Category>,
+    pub tag_ids: Vec<TagId>,
+    pub trial: Option<Trial>,
+    pub price_phases: Vec<PricePhase>,
+    pub contract: Option<Contract>,
+    pub payment_method_id: Option<PaymentMethodId>,
+    pub usage_target: Option<UsageTarget>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
 pub(crate) struct SubscriptionId(Uuid);
 
-#[derive(Debug, serde::Serialize)]
+impl From<Uuid> for SubscriptionId {
+    fn from(value: Uuid) -> Self {
+        Self(value)
+    }
+}
+
+impl From<SubscriptionId> for Uuid {
+    fn from(value: SubscriptionId) -> Self {
+        value.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub(crate) enum BillingPeriodUnit {
+pub enum BillingPeriodUnit {
     Day,
     Week,
     Month,
     Year,
 }
+
+impl BillingPeriodUnit {
+    /// Number of renewals in a year when renewed every `billing_period` units, weeks count as
+    /// 52 a year
+    pub fn renewals_per_year(&self, billing_period: u8) -> Decimal {
+        let units = match self {
+            Self::Day => Decimal::from(365),
+            Self::Week => Decimal::from(52),
+            Self::Month => Decimal::from(12),
+            Self::Year => Decimal::ONE,
+        };
+        match billing_period {
+            0 => Decimal::ZERO,
+            billing_period => units / Decimal::from(billing_period),
+        }
+    }
+
+    /// Moves `date` by `count` units, backwards for negative values
+    pub fn shift(&self, date: NaiveDate, count: i32) -> Option<NaiveDate> {
+        let n = count.unsigned_abs();
+        let days = |days: u64| match count < 0 {
+            true => date.checked_sub_days(Days::new(days)),
+            false => date.checked_add_days(Days::new(days)),
+        };
+        let months = |months: u32| match count < 0 {
+            true => date.checked_sub_months(Months::new(months)),
+            false => date.checked_add_months(Months::new(months)),
+        };
+
+        match self {
+            Self::Day => days(n.into()),
+            Self::Week => days(u64::from(n) * 7),
+            Self::Month => months(n),
+            Self::Year => months(n.checked_mul(12)?),
+        }
+    }
+}
+
+impl AsRef<str> for BillingPeriodUnit {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Day => "day",
+            Self::Week => "week",
+            Self::Month => "month",
+            Self::Year => "year",
+        }
+    }
+}
+
+impl TryFrom<String> for BillingPeriodUnit {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "day" => Ok(Self::Day),
+            "week" => Ok(Self::Week),
+            "month" => Ok(Self::Month),
+            "year" => Ok(Self::Year),
+            other => Err(format!("{} is not a valid billing period unit.", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use iso_currency::Currency;
+    use uuid::Uuid;
+
+    use super::{BillingPeriodUnit, Subscription};
+    use crate::domain::{Category, Money};
+
+    /// Shorthand for a date known to be valid
+    pub(crate) fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    /// Monthly subscription created at the beginning of 2023
+    pub(crate) fn monthly_subscription(
+        amount: i64,
+        currency: Currency,
+        next_renewal_date: NaiveDate,
+    ) -> Subscription {
+        let created_at = Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap();
+        Subscription {
+            id: Uuid::new_v4().into(),
+            user_id: Uuid::new_v4().into(),
+            name: "Netflix".into(),
+            description: None,
+            price: Money::new(amount, currency),
+            share: 100,
+            next_renewal_date,
+            billing_period: 1,
+            billing_period_unit: BillingPeriodUnit::Month,
+            service_id: Uuid::new_v4().into(),
+            plan_id: None,
+            category: Category::Streaming,
+            tag_ids: vec![],
+            trial: None,
+            price_phases: vec![],
+            contract: None,
+            payment_method_id: None,
+            usage_target: None,
+            created_at,
+            updated_at: created_at,
+            cancelled_at: None,
+            cancel_reason: None,
+            subscribed_at: None,
+            deleted_at: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use iso_currency::Currency;
+
+    use crate::domain::{
+        fixtures::{self, date},
+        BillingPeriodUnit, CancellationDeadline, Contract, Money, PricePhase, Subscription,
+        SubscriptionStatus, Trial,
+    };
+
+    fn monthly_subscription(next_renewal_date: NaiveDate) -> Subscription {
+        fixtures::monthly_subscription(1299, Currency::EUR, next_renewal_date)
+    }
+
+    fn renewal_dates(
+        subscription: &Subscription,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Vec<NaiveDate> {
+        subscription
+            .renewals_between(from, to)
+            .into_iter()
+            .map(|(_, date)| date)
+            .collect()
+    }
+
+    #[test]
+    fn renewals_include_past_and_upcoming_charges() {
+        let subscription = monthly_subscription(date(2023, 9, 15));
+        let renewals = renewal_dates(&subscription, date(2023, 7, 1), date(2023, 11, 1));
+        assert_eq!(
+            renewals,
+            vec![
+                date(2023, 7, 15),
+                date(2023, 8, 15),
+                date(2023, 9, 15),
+                date(2023, 10, 15)
+            ]
+        );
+    }
+
+    #[test]
+    fn renewals_do_not_start_before_subscribing() {
+        let mut subscription = monthly_subscription(date(2023, 9, 15));
+        subscription.subscribed_at = Some(date(2023, 8, 10));
+        let renewals = renewal_dates(&subscription, date(2023, 1, 1), date(2023, 10, 1));
+        assert_eq!(renewals, vec![date(2023, 8, 15), date(2023, 9, 15)]);
+    }
+
+    #[test]
+    fn cancelled_subscription_does_not_renew() {
+        let mut subscription = monthly_subscription(date(2023, 9, 15));
+        subscription.cancelled_at = Some(Utc.with_ymd_and_hms(2023, 9, 20, 12, 0, 0).unwrap());
+        let renewals = renewal_dates(&subscription, date(2023, 9, 1), date(2024, 1, 1));
+        assert_eq!(renewals, vec![date(2023, 9, 15)]);
+    }
+
+    #[test]
+    fn deleted_subscription_has_no_renewals() {
+        let mut subscription = monthly_subscription(date(2023, 9, 15));
+        subscription.deleted_at = Some(Utc::now());
+        assert!(renewal_dates(&subscription, date(2023, 1, 1), date(2024, 1, 1)).is_empty());
+    }
+
+    #[test]
+    fn regular_charges_start_at_trial_conversion() {
+        let mut subscription = monthly_subscription(date(2023, 9, 15));
+        subscription.subscribed_at = Some(date(2023, 8, 15));
+        subscription.trial = Some(Trial {
+            ends_on: date(2023, 9, 15),
+            price: Money::zero(Currency::EUR),
+            reminder_days: 3,
+        });
+
+        let charges = subscription
+            .charges_between(date(2023, 8, 1), date(2023, 11, 1))
+            .unwrap();
+
+        let dates: Vec<_> = charges.iter().map(|(date, _)| *date).collect();
+        assert_eq!(dates, vec![date(2023, 9, 15), date(2023, 10, 15)]);
+        assert!(subscription.is_trialing(date(2023, 9, 14)));
+        assert!(!subscription.is_trialing(date(2023, 9, 15)));
+    }
+
+    #[test]
+    fn reduced_trial_price_is_charged_when_the_trial_starts() {
+        let mut subscription = monthly_subscription(date(2023, 9, 15));
+        subscription.subscribed_at = Some(date(2023, 8, 15));
+        subscription.share = 50;
+        subscription.trial = Some(Trial {
+            ends_on: date(2023, 9, 15),
+            price: Money::new(100, Currency::EUR),
+            reminder_days: 3,
+        });
+
+        let charges = subscription
+            .charges_between(date(2023, 8, 1), date(2023, 9, 16))
+            .unwrap();
+
+        assert_eq!(
+            charges,
+            vec![
+                (date(2023, 8, 15), Money::new(50, Currency::EUR)),
+                (date(2023, 9, 15), Money::new(650, Currency::EUR))
+            ]
+        );
+    }
+
+    #[test]
+    fn introductory_phases_are_charged_before_the_regular_price() {
+        let mut subscription = monthly_subscription(date(2023, 9, 15));
+        subscription.subscribed_at = Some(date(2023, 8, 15));
+        subscription.price_phases = vec![PricePhase {
+            cycles: 2,
+            price: Money::new(100, Currency::EUR),
+        }];
+
+        let charges = subscription
+            .charges_between(date(2023, 9, 1), date(2023, 11, 1))
+            .unwrap();
+
+        assert_eq!(
+            charges,
+            vec![
+                (date(2023, 9, 15), Money::new(100, Currency::EUR)),
+                (date(2023, 10, 15), Money::new(1299, Currency::EUR))
+            ]
+        );
+    }
+
+    #[test]
+    fn phases_start_when_the_trial_converts() {
+        let mut subscription = monthly_subscription(date(2023, 9, 15));
+        subscription.subscribed_at = Some(date(2023, 8, 15));
+        subscription.trial = Some(Trial {
+            ends_on: date(2023, 9, 15),
+            price: Money::zero(Currency::EUR),
+            reminder_days: 3,
+        });
+        subscription.price_phases = vec![PricePhase {
+            cycles: 1,
+            price: Money::new(499, Currency::EUR),
+        }];
+
+        let change = subscription.next_price_change(date(2023, 9, 1)).unwrap();
+
+        assert_eq!(change.date, date(2023, 10, 15));
+        assert_eq!(change.previous, Money::new(499, Currency::EUR));
+        assert_eq!(change.price, Money::new(1299, Currency::EUR));
+        assert!(change.is_increase());
+        assert_eq!(subscription.next_price_change(date(2023, 10, 15)), None);
+    }
+
+    #[test]
+    fn notice_has_to_be_given_before_the_term_ends() {
+        let mut subscription = monthly_subscription(date(2023, 9, 15));
+        subscription.contract = Some(Contract {
+            ends_on: date(2023, 12, 31),
+            auto_renew: true,
+            notice_period: 1,
+            notice_period_unit: BillingPeriodUnit::Month,
+        });
+
+        assert_eq!(
+            subscription.cancellation_deadline(date(2023, 9, 20)),
+            Some(CancellationDeadline {
+                cancel_by: date(2023, 11, 30),
+                term_ends_on: date(2023, 12, 31)
+            })
+        );
+        // Once missed the deadline moves to the following term
+        assert_eq!(
+            subscription.cancellation_deadline(date(2023, 12, 1)),
+            Some(CancellationDeadline {
+                cancel_by: date(2023, 12, 31),
+                term_ends_on: date(2024, 1, 31)
+            })
+        );
+        assert_eq!(
+            subscription.term_end(date(2024, 1, 5)),
+            Some(date(2024, 1, 31))
+        );
+    }
+
+    #[test]
+    fn fixed_term_contract_stops_renewing() {
+        let mut subscription = monthly_subscription(date(2023, 9, 15));
+        subscription.contract = Some(Contract {
+            ends_on: date(2023, 10, 31),
+            auto_renew: false,
+            notice_period: 0,
+            notice_period_unit: BillingPeriodUnit::Day,
+        });
+
+        let renewals = renewal_dates(&subscription, date(2023, 9, 1), date(2024, 1, 1));
+
+        assert_eq!(renewals, vec![date(2023, 9, 15), date(2023, 10, 15)]);
+        assert_eq!(subscription.cancellation_deadline(date(2023, 9, 20)), None);
+    }
+
+    #[test]
+    fn deletion_takes_precedence_over_cancellation() {
+        let mut subscription = monthly_subscription(date(2023, 9, 15));
+        assert_eq!(subscription.status(), SubscriptionStatus::Active);
+        subscription.cancelled_at = Some(Utc::now());
+        assert_eq!(subscription.status(), SubscriptionStatus::Cancelled);
+        subscription.deleted_at = Some(Utc::now());
+        assert_eq!(subscription.status(), SubscriptionStatus::Deleted);
+    }
+
+    #[test]
+    fn shifting_by_months_is_anchored_on_the_original_date() {
+        let unit = BillingPeriodUnit::Month;
+        assert_eq!(unit.shift(date(2023, 1, 31), 1), Some(date(2023, 2, 28)));
+        assert_eq!(unit.shift(date(2023, 1, 31), 2), Some(date(2023, 3, 31)));
+        assert_eq!(unit.shift(date(2023, 3, 31), -1), Some(date(2023, 2, 28)));
+    }
+}
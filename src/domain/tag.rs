@@ -0,0 +1,97 @@
+use chrono::{DateTime, Utc};
+use unicode_segmentation::UnicodeSegmentation;
+use uuid::Uuid;
+
+/// Label defined by the user to group subscriptions (e.g. `work`, `family`)
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Tag {
+    pub id: TagId,
+    pub name: TagName,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(transparent)]
+pub struct TagId(Uuid);
+
+impl From<Uuid> for TagId {
+    fn from(value: Uuid) -> Self {
+        Self(value)
+    }
+}
+
+impl From<TagId> for Uuid {
+    fn from(value: TagId) -> Self {
+        value.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct TagName(String);
+
+impl TagName {
+    pub fn parse(s: String) -> Result<TagName, String> {
+        const TAG_NAME_MAX_LENGTH: usize = 32;
+        let is_empty_or_whitespace = s.trim().is_empty();
+
+        let is_too_long = s.trim().graphemes(true).count() > TAG_NAME_MAX_LENGTH;
+        let forbidden_characters = [',', '/', '"', '<', '>', '\\'];
+        let contains_forbidden_characters = s.chars().any(|g| forbidden_characters.contains(&g));
+
+        if is_empty_or_whitespace || is_too_long || contains_forbidden_characters {
+            Err(format!("{} is not a valid tag name.", s))
+        } else {
+            Ok(Self(s.trim().to_owned()))
+        }
+    }
+}
+
+impl AsRef<str> for TagName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// FIXME: same as `ServiceName`, needed by sqlx to map DB results without validation
+impl From<String> for TagName {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+
+    use crate::domain::TagName;
+
+    #[test]
+    fn a_32_grapheme_long_name_is_valid() {
+        assert_ok!(TagName::parse("ё".repeat(32)));
+    }
+
+    #[test]
+    fn name_longer_than_32_graphemes_is_invalid() {
+        assert_err!(TagName::parse("ё".repeat(33)));
+    }
+
+    #[test]
+    fn empty_and_whitespace_names_are_rejected() {
+        assert_err!(TagName::parse("".to_string()));
+        assert_err!(TagName::parse("  ".to_string()));
+    }
+
+    #[test]
+    fn names_containing_a_comma_are_rejected() {
+        assert_err!(TagName::parse("work,family".to_string()));
+    }
+
+    #[test]
+    fn additional_whitespace_gets_trimmed() {
+        let name = TagName::parse(" work ".to_string()).unwrap();
+        assert_eq!(name.as_ref(), "work");
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Days, Months, NaiveDate, Utc};
+use iso_currency::Currency;
+use uuid::Uuid;
+
+use super::{BillingPeriodUnit, ExchangeRates, Money, Subscription, SubscriptionId};
+
+/// Subscriptions without use for this many billing cycles are flagged unless the user chose
+/// otherwise
+pub const DEFAULT_UNUSED_AFTER_CYCLES: u8 = 2;
+
+/// "Last used" check-in of a subscription
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Usage {
+    #[sqlx(try_from = "Uuid")]
+    pub id: UsageId,
+    pub used_on: NaiveDate,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct UsageId(Uuid);
+
+impl From<Uuid> for UsageId {
+    fn from(value: Uuid) -> Self {
+        Self(value)
+    }
+}
+
+impl From<UsageId> for Uuid {
+    fn from(value: UsageId) -> Self {
+        value.0
+    }
+}
+
+/// How often the user wants to use a subscription, e.g. 4 times a month
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct UsageTarget {
+    pub times: u16,
+    pub per: BillingPeriodUnit,
+}
+
+/// Check-ins of a subscription within the [`UsageReport`] period
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct UsageSummary {
+    pub uses: u32,
+    /// Latest check-in, also when older than the period
+    pub last_used_on: Option<NaiveDate>,
+}
+
+/// Subscription paid for but not used for the chosen number of billing cycles
+#[derive(Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct UnusedSubscription {
+    pub subscription_id: SubscriptionId,
+    pub name: String,
+    pub last_used_on: Option<NaiveDate>,
+}
+
+/// Spend of the last 12 months divided by the check-ins in the same period
+#[derive(Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CostPerUse {
+    pub subscription_id: SubscriptionId,
+    pub name: String,
+    pub uses: u32,
+    pub spent: Money,
+    /// Missing when the subscription was not used at all
+    pub cost_per_use: Option<Money>,
+    /// Whether the [`UsageTarget`] was met on average, missing without a target
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meets_target: Option<bool>,
+}
+
+#[derive(Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct UsageReport {
+    pub unused: Vec<UnusedSubscription>,
+    pub cost_per_use: Vec<CostPerUse>,
+}
+
+impl UsageReport {
+    /// First day of the period `cost_per_use` is computed for
+    pub fn period_start(today: NaiveDate) -> Result<NaiveDate, String> {
+        today
+            .checked_sub_months(Months::new(12))
+            .and_then(|date| date.checked_add_days(Days::new(1)))
+            .ok_or_else(|| "date out of range".to_string())
+    }
+
+    /// Report of active subscriptions, `usages` holds the check-ins since
+    /// [`period_start`](UsageReport::period_start)
+    pub fn compute(
+        subscriptions: &[Subscription],
+        usages: &HashMap<SubscriptionId, UsageSummary>,
+        rates: &ExchangeRates,
+        currency: Currency,
+        today: NaiveDate,
+        unused_after_cycles: u8,
+    ) -> Result<Self, String> {
+        let period_start = Self::period_start(today)?;
+        let tomorrow = today.succ_opt().ok_or("date out of range")?;
+
+        let mut unused = vec![];
+        let mut cost_per_use = vec![];
+        for subscription in subscriptions {
+            if subscription.cancelled_at.is_some() || subscription.deleted_at.is_some() {
+                continue;
+            }
+            let usage = usages.get(&subscription.id).copied().unwrap_or_default();
+
+            if subscription.is_unused(usage.last_used_on, unused_after_cycles, today) {
+                unused.push(UnusedSubscription {
+                    subscription_id: subscription.id,
+                    name: subscription.name.clone(),
+                    last_used_on: usage.last_used_on,
+                });
+            }
+
+            let spent = subscription
+                .charges_between(period_start, tomorrow)?
+                .into_iter()
+                .map(|(date, amount)| rates.convert_on(amount, currency, date))
+                .collect::<Result<Vec<_>, _>>()?;
+            let spent = Money::total(currency, spent)?;
+            let meets_target = subscription.usage_target.and_then(|target| {
+                let start = period_start.max(subscription.usage_start());
+                let periods = (1..)
+                    .take_while(|n| {
+                        target
+                            .per
+                            .shift(start, *n)
+                            .is_some_and(|end| end <= tomorrow)
+                    })
+                    .count() as u32;
+                (periods > 0).then(|| usage.uses >= periods * u32::from(target.times))
+            });
+
+            cost_per_use.push(CostPerUse {
+                subscription_id: subscription.id,
+                name: subscription.name.clone(),
+                uses: usage.uses,
+                spent,
+                cost_per_use: (usage.uses > 0)
+                    .then(|| spent.divide(usage.uses))
+                    .transpose()?,
+                meets_target,
+            });
+        }
+
+        Ok(Self {
+            unused,
+            cost_per_use,
+        })
+    }
+}
+
+impl Subscription {
+    /// Whether the subscription was paid for `cycles` billing periods in a row without any
+    /// check-in, counted from the last use or the start of the subscription
+    pub fn is_unused(&self, last_used_on: Option<NaiveDate>, cycles: u8, today: NaiveDate) -> bool {
+        let Some(threshold) = self
+            .billing_period_unit
+            .shift(today, -i32::from(cycles) * i32::from(self.billing_period))
+        else {
+            return false;
+        };
+        let last_activity = match last_used_on {
+            Some(last_used_on) => last_used_on.max(self.usage_start()),
+            None => self.usage_start(),
+        };
+        last_activity <= threshold
+    }
+
+    /// Usage is only expected from the start of the subscription
+    fn usage_start(&self) -> NaiveDate {
+        self.subscribed_at
+            .unwrap_or_else(|| self.created_at.date_naive())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use iso_currency::Currency;
+
+    use crate::domain::{
+        fixtures::{date, monthly_subscription},
+        BillingPeriodUnit, ExchangeRates, Money, UsageReport, UsageSummary, UsageTarget,
+    };
+
+    #[test]
+    fn subscription_without_use_for_two_cycles_is_unused() {
+        let mut subscription = monthly_subscription(1299, Currency::EUR, date(2023, 9, 15));
+        subscription.subscribed_at = Some(date(2023, 3, 15));
+        let today = date(2023, 9, 20);
+
+        assert!(!subscription.is_unused(Some(date(2023, 8, 1)), 2, today));
+        assert!(subscription.is_unused(Some(date(2023, 7, 20)), 2, today));
+        assert!(subscription.is_unused(None, 2, today));
+
+        // Recently started subscriptions are not flagged yet
+        subscription.subscribed_at = Some(date(2023, 8, 15));
+        assert!(!subscription.is_unused(None, 2, today));
+    }
+
+    #[test]
+    fn cost_per_use_divides_the_spend_of_the_last_year() {
+        let mut subscription = monthly_subscription(1200, Currency::EUR, date(2023, 9, 15));
+        subscription.subscribed_at = Some(date(2023, 7, 1));
+        subscription.usage_target = Some(UsageTarget {
+            times: 2,
+            per: BillingPeriodUnit::Month,
+        });
+        let usages = HashMap::from([(
+            subscription.id,
+            UsageSummary {
+                uses: 6,
+                last_used_on: Some(date(2023, 9, 18)),
+            },
+        )]);
+
+        let report = UsageReport::compute(
+            &[subscription],
+            &usages,
+            &ExchangeRates::new([]),
+            Currency::EUR,
+            date(2023, 9, 20),
+            2,
+        )
+        .unwrap();
+
+        assert!(report.unused.is_empty());
+        let cost = &report.cost_per_use[0];
+        assert_eq!(cost.spent, Money::new(3600, Currency::EUR));
+        assert_eq!(cost.cost_per_use, Some(Money::new(600, Currency::EUR)));
+        assert_eq!(cost.meets_target, Some(true));
+    }
+}
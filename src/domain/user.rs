@@ -7,10 +7,13 @@ use argon2::{
 };
 use axum_login::AuthUser;
 use chrono::{DateTime, Utc};
+use iso_currency::Currency;
 use secrecy::SecretVec;
 use sqlx::FromRow;
 use uuid::Uuid;
 
+use super::{CurrencyCode, Locale, DEFAULT_UNUSED_AFTER_CYCLES};
+
 #[derive(Debug)]
 #[repr(transparent)]
 pub(crate) struct Password(String);
@@ -61,7 +64,17 @@ impl sqlx::Type<sqlx::Postgres> for PasswordHash {
     }
 }
 
-#[derive(Debug, Clone, sqlx::Encode, sqlx::Decode, serde::Serialize, serde::Deserialize)]
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    sqlx::Encode,
+    sqlx::Decode,
+    serde::Serialize,
+    serde::Deserialize,
+)]
 #[repr(transparent)]
 pub(crate) struct UserId(uuid::Uuid);
 
@@ -93,6 +106,12 @@ pub(crate) struct User {
     pub login: String,
     pub updated_at: DateTime<Utc>,
     pub created_at: DateTime<Utc>,
+    /// Currency used to present statistics and forecasts
+    pub preferred_currency: CurrencyCode,
+    /// Decides how amounts and dates are formatted in notifications and responses
+    pub locale: Locale,
+    /// Subscriptions without use for this many billing cycles are flagged as unused
+    pub unused_after_cycles: i16,
 }
 
 impl User {
@@ -105,6 +124,9 @@ impl User {
             role: UserRole::User,
             updated_at: Utc::now(),
             created_at: Utc::now(),
+            preferred_currency: Currency::EUR.into(),
+            locale: Locale::default(),
+            unused_after_cycles: DEFAULT_UNUSED_AFTER_CYCLES.into(),
         }
     }
 
@@ -152,7 +174,7 @@ impl sqlx::Type<sqlx::Postgres> for UserRole {
 
 impl AuthUser<UserId, UserRole> for User {
     fn get_id(&self) -> UserId {
-        self.id.clone()
+        self.id
     }
 
     fn get_password_hash(&self) -> SecretVec<u8> {
@@ -0,0 +1,140 @@
+use chrono::NaiveDate;
+use iso_currency::Currency;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+
+use crate::domain::{CurrencyCode, ExchangeRate, ExchangeRates};
+
+/// Daily exchange rates persisted in the `exchange_rates` table
+#[derive(Debug, Clone)]
+pub struct ExchangeRateStore {
+    database: PgPool,
+}
+
+impl ExchangeRateStore {
+    pub fn new(database: PgPool) -> Self {
+        Self { database }
+    }
+
+    /// Inserts the rates, replacing already stored ones for the same day
+    #[tracing::instrument(name = "Save exchange rates", skip_all, fields(count = rates.len()))]
+    pub async fn save(&self, rates: &[ExchangeRate]) -> sqlx::Result<()> {
+        // Historical files contain a few hundred thousand rates, insert them in batches
+        const BATCH_SIZE: usize = 10_000;
+
+        let mut transaction = self.database.begin().await?;
+
+        for batch in rates.chunks(BATCH_SIZE) {
+            let bases: Vec<&str> = batch.iter().map(|rate| rate.base.code()).collect();
+            let quotes: Vec<&str> = batch.iter().map(|rate| rate.quote.code()).collect();
+            let dates: Vec<NaiveDate> = batch.iter().map(|rate| rate.date).collect();
+            let values: Vec<Decimal> = batch.iter().map(|rate| rate.rate).collect();
+
+            sqlx::query!(
+                r#"
+                INSERT INTO exchange_rates (base_currency, quote_currency, rate_date, rate)
+                SELECT * FROM UNNEST($1::text[], $2::text[], $3::date[], $4::numeric[])
+                ON CONFLICT (base_currency, quote_currency, rate_date) DO UPDATE SET
+                  rate = EXCLUDED.rate
+                "#,
+                &bases as &[&str],
+                &quotes as &[&str],
+                &dates,
+                &values
+            )
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        transaction.commit().await
+    }
+
+    /// Loads the whole history of rates between any of the given `currencies`. Rates against the
+    /// euro are always included, reference rates are published against it only and other pairs
+    /// are converted through it.
+    #[tracing::instrument(name = "Load exchange rates", skip_all)]
+    pub async fn load(&self, currencies: &[Currency]) -> sqlx::Result<ExchangeRates> {
+        let mut codes: Vec<String> = currencies.iter().map(|c| c.code().to_string()).collect();
+        if !currencies.contains(&Currency::EUR) {
+            codes.push(Currency::EUR.code().to_string());
+        }
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT base_currency, quote_currency, rate_date, rate
+            FROM exchange_rates
+            WHERE base_currency = ANY($1) AND quote_currency = ANY($1)
+            "#,
+            &codes
+        )
+        .fetch_all(&self.database)
+        .await?;
+
+        let rates = rows.into_iter().filter_map(|row| {
+            Some(ExchangeRate {
+                base: *CurrencyCode::parse(&row.base_currency).ok()?,
+                quote: *CurrencyCode::parse(&row.quote_currency).ok()?,
+                date: row.rate_date,
+                rate: row.rate,
+            })
+        });
+
+        Ok(ExchangeRates::new(rates))
+    }
+
+    /// Loads the rates to convert amounts in any of the `sources` currencies into the `targets`,
+    /// e.g. the prices of the subscriptions into the preferred currency of the user
+    pub async fn load_for(
+        &self,
+        targets: impl IntoIterator<Item = Currency>,
+        sources: impl IntoIterator<Item = Currency>,
+    ) -> sqlx::Result<ExchangeRates> {
+        let mut currencies: Vec<Currency> = vec![];
+        for currency in targets.into_iter().chain(sources) {
+            if !currencies.contains(&currency) {
+                currencies.push(currency);
+            }
+        }
+        self.load(&currencies).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use iso_currency::Currency;
+    use rust_decimal::Decimal;
+
+    use crate::{
+        domain::{ExchangeRate, Money},
+        test_database::create_migrated_db_for_tests,
+    };
+
+    use super::ExchangeRateStore;
+
+    #[tokio::test]
+    async fn pairs_without_the_euro_are_converted_through_it() {
+        let store = ExchangeRateStore::new(create_migrated_db_for_tests("test_rates").await);
+        let date = NaiveDate::from_ymd_opt(2023, 10, 2).unwrap();
+        let euro_rate = |quote, rate| ExchangeRate {
+            base: Currency::EUR,
+            quote,
+            date,
+            rate,
+        };
+        store
+            .save(&[
+                euro_rate(Currency::USD, Decimal::new(110, 2)),
+                euro_rate(Currency::GBP, Decimal::new(88, 2)),
+            ])
+            .await
+            .unwrap();
+
+        let rates = store.load(&[Currency::USD, Currency::GBP]).await.unwrap();
+
+        assert_eq!(
+            rates.convert_on(Money::new(1100, Currency::USD), Currency::GBP, date),
+            Ok(Money::new(880, Currency::GBP))
+        );
+    }
+}
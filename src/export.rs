@@ -0,0 +1,643 @@
+//! Exports of the subscriptions, payments and attachments of a user as CSV, JSON or XLSX files.
+//! Records are encoded batch by batch so that long payment histories never have to be kept in
+//! memory. The files of the attachments are exported separately as a ZIP archive.
+use std::io::{Seek, Write};
+
+use chrono::NaiveDate;
+use iso_currency::Currency;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::domain::{
+    Attachment, BillingPeriodUnit, ExchangeRates, Subscription, SubscriptionStatus,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Xlsx,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Json => "application/json",
+            Self::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+            Self::Xlsx => "xlsx",
+        }
+    }
+}
+
+/// Value of a column in CSV and XLSX files
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Text(String),
+    Number(Decimal),
+    Empty,
+}
+
+impl From<Option<NaiveDate>> for Cell {
+    fn from(value: Option<NaiveDate>) -> Self {
+        value.map_or(Self::Empty, |date| Self::Text(date.to_string()))
+    }
+}
+
+impl From<Option<Decimal>> for Cell {
+    fn from(value: Option<Decimal>) -> Self {
+        value.map_or(Self::Empty, Self::Number)
+    }
+}
+
+/// Row of an export, serialized as is into JSON and split into [`Cell`]s for the other formats
+pub trait ExportRecord: serde::Serialize + Send + 'static {
+    /// Column headers, the same as the JSON keys
+    const HEADERS: &'static [&'static str];
+    /// Name of the file and of the worksheet
+    const NAME: &'static str;
+
+    /// Values in the order of the [`HEADERS`](ExportRecord::HEADERS)
+    fn cells(&self) -> Vec<Cell>;
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionRecord {
+    id: Uuid,
+    name: String,
+    service_id: Uuid,
+    category: String,
+    status: SubscriptionStatus,
+    amount: Decimal,
+    currency: &'static str,
+    share: u8,
+    billing_period: u8,
+    billing_period_unit: BillingPeriodUnit,
+    next_renewal_date: NaiveDate,
+    subscribed_at: Option<NaiveDate>,
+    cancelled_at: Option<NaiveDate>,
+    /// `amount` in the preferred currency of the user at the latest rate, missing without one
+    converted_amount: Option<Decimal>,
+    converted_currency: &'static str,
+}
+
+impl SubscriptionRecord {
+    pub(crate) fn new(subscription: &Subscription, rates: &ExchangeRates, to: Currency) -> Self {
+        Self {
+            id: subscription.id.into(),
+            name: subscription.name.clone(),
+            service_id: subscription.service_id.into(),
+            category: subscription.category.as_ref().to_owned(),
+            status: subscription.status(),
+            amount: subscription.price.to_decimal(),
+            currency: subscription.price.currency().code(),
+            share: subscription.share,
+            billing_period: subscription.billing_period,
+            billing_period_unit: subscription.billing_period_unit,
+            next_renewal_date: subscription.next_renewal_date,
+            subscribed_at: subscription.subscribed_at,
+            cancelled_at: subscription
+                .cancelled_at
+                .map(|cancelled_at| cancelled_at.date_naive()),
+            converted_amount: rates
+                .convert_latest(subscription.price, to)
+                .ok()
+                .map(|amount| amount.to_decimal()),
+            converted_currency: to.code(),
+        }
+    }
+}
+
+impl ExportRecord for SubscriptionRecord {
+    const HEADERS: &'static [&'static str] = &[
+        "id",
+        "name",
+        "serviceId",
+        "category",
+        "status",
+        "amount",
+        "currency",
+        "share",
+        "billingPeriod",
+        "billingPeriodUnit",
+        "nextRenewalDate",
+        "subscribedAt",
+        "cancelledAt",
+        "convertedAmount",
+        "convertedCurrency",
+    ];
+    const NAME: &'static str = "subscriptions";
+
+    fn cells(&self) -> Vec<Cell> {
+        let status = match self.status {
+            SubscriptionStatus::Active => "active",
+            SubscriptionStatus::Cancelled => "cancelled",
+            SubscriptionStatus::Deleted => "deleted",
+        };
+        vec![
+            Cell::Text(self.id.to_string()),
+            Cell::Text(self.name.clone()),
+            Cell::Text(self.service_id.to_string()),
+            Cell::Text(self.category.clone()),
+            Cell::Text(status.into()),
+            Cell::Number(self.amount),
+            Cell::Text(self.currency.into()),
+            Cell::Number(self.share.into()),
+            Cell::Number(self.billing_period.into()),
+            Cell::Text(self.billing_period_unit.as_ref().into()),
+            Cell::from(Some(self.next_renewal_date)),
+            Cell::from(self.subscribed_at),
+            Cell::from(self.cancelled_at),
+            Cell::from(self.converted_amount),
+            Cell::Text(self.converted_currency.into()),
+        ]
+    }
+}
+
+/// Charge of a subscription, see [`Subscription::charges_between`]
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentRecord {
+    date: NaiveDate,
+    subscription_id: Uuid,
+    name: String,
+    category: String,
+    /// Part of the price paid by the user
+    amount: Decimal,
+    currency: &'static str,
+    /// `amount` in the preferred currency of the user at the rate of the payment date
+    converted_amount: Option<Decimal>,
+    converted_currency: &'static str,
+}
+
+impl PaymentRecord {
+    /// Payments of the subscription within `[from, to)`
+    pub(crate) fn of(
+        subscription: &Subscription,
+        from: NaiveDate,
+        to: NaiveDate,
+        rates: &ExchangeRates,
+        currency: Currency,
+    ) -> Result<Vec<Self>, String> {
+        Ok(subscription
+            .charges_between(from, to)?
+            .into_iter()
+            .map(|(date, amount)| Self {
+                date,
+                subscription_id: subscription.id.into(),
+                name: subscription.name.clone(),
+                category: subscription.category.as_ref().to_owned(),
+                amount: amount.to_decimal(),
+                currency: amount.currency().code(),
+                converted_amount: rates
+                    .convert_on(amount, currency, date)
+                    .ok()
+                    .map(|amount| amount.to_decimal()),
+                converted_currency: currency.code(),
+            })
+            .collect())
+    }
+}
+
+impl ExportRecord for PaymentRecord {
+    const HEADERS: &'static [&'static str] = &[
+        "date",
+        "subscriptionId",
+        "name",
+        "category",
+        "amount",
+        "currency",
+        "convertedAmount",
+        "convertedCurrency",
+    ];
+    const NAME: &'static str = "payments";
+
+    fn cells(&self) -> Vec<Cell> {
+        vec![
+            Cell::from(Some(self.date)),
+            Cell::Text(self.subscription_id.to_string()),
+            Cell::Text(self.name.clone()),
+            Cell::Text(self.category.clone()),
+            Cell::Number(self.amount),
+            Cell::Text(self.currency.into()),
+            Cell::from(self.converted_amount),
+            Cell::Text(self.converted_currency.into()),
+        ]
+    }
+}
+
+/// Encodes records into consecutive chunks of a CSV or JSON file, XLSX files are written with
+/// [`XlsxWriter`] instead
+#[derive(Debug)]
+pub enum ChunkEncoder {
+    Csv,
+    Json { is_empty: bool },
+}
+
+impl ChunkEncoder {
+    /// Encoder with the start of the file, `None` for XLSX
+    pub fn start<R: ExportRecord>(format: ExportFormat) -> Option<(Self, Vec<u8>)> {
+        match format {
+            ExportFormat::Csv => {
+                let mut header = R::HEADERS.join(",");
+                header.push_str("\r\n");
+                Some((Self::Csv, header.into_bytes()))
+            }
+            ExportFormat::Json => Some((Self::Json { is_empty: true }, b"[".to_vec())),
+            ExportFormat::Xlsx => None,
+        }
+    }
+
+    pub fn encode<R: ExportRecord>(&mut self, records: &[R]) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Csv => {
+                let mut writer = csv::WriterBuilder::new()
+                    .terminator(csv::Terminator::CRLF)
+                    .from_writer(vec![]);
+                for record in records {
+                    writer
+                        .write_record(record.cells().iter().map(csv_field))
+                        .map_err(|e| e.to_string())?;
+                }
+                writer.into_inner().map_err(|e| e.to_string())
+            }
+            Self::Json { is_empty } => {
+                let mut chunk = vec![];
+                for record in records {
+                    if !*is_empty {
+                        chunk.push(b',');
+                    }
+                    *is_empty = false;
+                    serde_json::to_writer(&mut chunk, record).map_err(|e| e.to_string())?;
+                }
+                Ok(chunk)
+            }
+        }
+    }
+
+    /// End of the file
+    pub fn finish(self) -> Vec<u8> {
+        match self {
+            Self::Csv => vec![],
+            Self::Json { .. } => b"]".to_vec(),
+        }
+    }
+}
+
+/// Spreadsheet apps run text starting with these as formulas
+const FORMULA_PREFIXES: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+fn csv_field(cell: &Cell) -> String {
+    match cell {
+        Cell::Text(text) if text.starts_with(FORMULA_PREFIXES) => format!("'{}", text),
+        Cell::Text(text) => text.clone(),
+        Cell::Number(number) => number.to_string(),
+        Cell::Empty => String::new(),
+    }
+}
+
+/// Attachment of a subscription, its file is stored under `path` in the archive written by
+/// [`ArchiveWriter`]
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentRecord {
+    id: Uuid,
+    subscription_id: Uuid,
+    payment_date: Option<NaiveDate>,
+    file_name: String,
+    content_type: String,
+    size_bytes: i64,
+    created_at: String,
+    path: String,
+}
+
+impl AttachmentRecord {
+    pub(crate) fn new(attachment: &Attachment) -> Self {
+        Self {
+            id: attachment.id.into(),
+            subscription_id: attachment.subscription_id.into(),
+            payment_date: attachment.payment_date,
+            file_name: attachment.file_name.clone(),
+            content_type: attachment.content_type.clone(),
+            size_bytes: attachment.size_bytes,
+            created_at: attachment.created_at.to_rfc3339(),
+            path: archive_path(attachment),
+        }
+    }
+}
+
+impl ExportRecord for AttachmentRecord {
+    const HEADERS: &'static [&'static str] = &[
+        "id",
+        "subscriptionId",
+        "paymentDate",
+        "fileName",
+        "contentType",
+        "sizeBytes",
+        "createdAt",
+        "path",
+    ];
+    const NAME: &'static str = "attachments";
+
+    fn cells(&self) -> Vec<Cell> {
+        vec![
+            Cell::Text(self.id.to_string()),
+            Cell::Text(self.subscription_id.to_string()),
+            Cell::from(self.payment_date),
+            Cell::Text(self.file_name.clone()),
+            Cell::Text(self.content_type.clone()),
+            Cell::Number(self.size_bytes.into()),
+            Cell::Text(self.created_at.clone()),
+            Cell::Text(self.path.clone()),
+        ]
+    }
+}
+
+/// Files of different attachments can have the same name, each of them gets its own folder
+pub(crate) fn archive_path(attachment: &Attachment) -> String {
+    format!("{}/{}", Uuid::from(attachment.id), attachment.file_name)
+}
+
+/// ZIP archive of the attachment files. The files are compressed already (PDF, PNG, JPEG and
+/// WebP), so they are stored as they are.
+pub struct ArchiveWriter<W: Write + Seek> {
+    zip: ZipWriter<W>,
+}
+
+impl<W: Write + Seek> ArchiveWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            zip: ZipWriter::new(writer),
+        }
+    }
+
+    pub fn add_file(&mut self, path: &str, bytes: &[u8]) -> Result<(), String> {
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        self.zip
+            .start_file(path, options)
+            .map_err(|e| e.to_string())?;
+        self.zip.write_all(bytes).map_err(|e| e.to_string())
+    }
+
+    /// Completes the archive and returns the underlying writer
+    pub fn finish(mut self) -> Result<W, String> {
+        self.zip.finish().map_err(|e| e.to_string())
+    }
+}
+
+/// Workbook with a single worksheet written row by row. The ZIP archive needs to seek back to
+/// the start of each entry, so the workbook is written into a file rather than a response.
+pub struct XlsxWriter<W: Write + Seek> {
+    zip: ZipWriter<W>,
+    rows: u32,
+}
+
+impl<W: Write + Seek> XlsxWriter<W> {
+    pub fn new(writer: W, sheet_name: &str, headers: &[&str]) -> Result<Self, String> {
+        let mut zip = ZipWriter::new(writer);
+        let parts = [
+            ("[Content_Types].xml", CONTENT_TYPES.to_owned()),
+            ("_rels/.rels", ROOT_RELATIONSHIPS.to_owned()),
+            (
+                "xl/workbook.xml",
+                WORKBOOK.replace("{sheet}", &xml_text(sheet_name)),
+            ),
+            (
+                "xl/_rels/workbook.xml.rels",
+                WORKBOOK_RELATIONSHIPS.to_owned(),
+            ),
+        ];
+        for (name, content) in parts {
+            zip.start_file(name, FileOptions::default())
+                .map_err(|e| e.to_string())?;
+            zip.write_all(content.as_bytes())
+                .map_err(|e| e.to_string())?;
+        }
+        zip.start_file("xl/worksheets/sheet1.xml", FileOptions::default())
+            .map_err(|e| e.to_string())?;
+        zip.write_all(WORKSHEET_START.as_bytes())
+            .map_err(|e| e.to_string())?;
+
+        let mut writer = Self { zip, rows: 0 };
+        let headers: Vec<Cell> = headers
+            .iter()
+            .map(|header| Cell::Text(header.to_string()))
+            .collect();
+        writer.write_row(&headers)?;
+
+        Ok(writer)
+    }
+
+    pub fn write_row(&mut self, cells: &[Cell]) -> Result<(), String> {
+        self.rows += 1;
+        let mut row = format!("<row r=\"{}\">", self.rows);
+        for (index, cell) in cells.iter().enumerate() {
+            let reference = format!("{}{}", column_name(index), self.rows);
+            match cell {
+                Cell::Text(text) => row.push_str(&format!(
+                    "<c r=\"{}\" t=\"inlineStr\"><is><t xml:space=\"preserve\">{}</t></is></c>",
+                    reference,
+                    xml_text(text)
+                )),
+                Cell::Number(number) => {
+                    row.push_str(&format!("<c r=\"{}\"><v>{}</v></c>", reference, number))
+                }
+                Cell::Empty => {}
+            }
+        }
+        row.push_str("</row>");
+
+        self.zip
+            .write_all(row.as_bytes())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Completes the workbook and returns the underlying writer
+    pub fn finish(mut self) -> Result<W, String> {
+        self.zip
+            .write_all(b"</sheetData></worksheet>")
+            .map_err(|e| e.to_string())?;
+        self.zip.finish().map_err(|e| e.to_string())
+    }
+}
+
+/// Letters of the column, `A` to `Z`, then `AA` and so on
+fn column_name(index: usize) -> String {
+    let mut name = vec![];
+    let mut index = index + 1;
+    while index > 0 {
+        let remainder = (index - 1) % 26;
+        name.push(b'A' + remainder as u8);
+        index = (index - 1) / 26;
+    }
+    name.reverse();
+
+    String::from_utf8(name).unwrap_or_default()
+}
+
+/// Escaped text without the control characters XML does not allow
+fn xml_text(text: &str) -> String {
+    let text: String = text
+        .chars()
+        .filter(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r'))
+        .collect();
+
+    quick_xml::escape::escape(&text).into_owned()
+}
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/></Types>"#;
+
+const ROOT_RELATIONSHIPS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+
+const WORKBOOK: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="{sheet}" sheetId="1" r:id="rId1"/></sheets></workbook>"#;
+
+const WORKBOOK_RELATIONSHIPS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/></Relationships>"#;
+
+const WORKSHEET_START: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>"#;
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read};
+
+    use rust_decimal::Decimal;
+
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    use crate::domain::Attachment;
+
+    use super::{
+        archive_path, column_name, ArchiveWriter, Cell, ChunkEncoder, ExportFormat, ExportRecord,
+        XlsxWriter,
+    };
+
+    #[derive(serde::Serialize)]
+    struct Row {
+        name: String,
+        amount: Decimal,
+    }
+
+    impl ExportRecord for Row {
+        const HEADERS: &'static [&'static str] = &["name", "amount"];
+        const NAME: &'static str = "rows";
+
+        fn cells(&self) -> Vec<Cell> {
+            vec![Cell::Text(self.name.clone()), Cell::Number(self.amount)]
+        }
+    }
+
+    fn rows() -> Vec<Row> {
+        vec![
+            Row {
+                name: "Netflix, Premium".into(),
+                amount: Decimal::new(1799, 2),
+            },
+            Row {
+                name: "=HYPERLINK(\"x\")".into(),
+                amount: Decimal::new(5, 0),
+            },
+        ]
+    }
+
+    fn encode(format: ExportFormat) -> String {
+        let (mut encoder, mut file) = ChunkEncoder::start::<Row>(format).unwrap();
+        // Split into batches like the records of an export
+        for batch in rows().chunks(1) {
+            file.extend(encoder.encode(batch).unwrap());
+        }
+        file.extend(encoder.finish());
+
+        String::from_utf8(file).unwrap()
+    }
+
+    #[test]
+    fn chunks_make_up_the_whole_file() {
+        assert_eq!(
+            encode(ExportFormat::Csv),
+            "name,amount\r\n\"Netflix, Premium\",17.99\r\n\"'=HYPERLINK(\"\"x\"\")\",5\r\n"
+        );
+        let json: serde_json::Value = serde_json::from_str(&encode(ExportFormat::Json)).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), 2);
+        assert_eq!(json[0]["name"], "Netflix, Premium");
+    }
+
+    #[test]
+    fn workbooks_have_a_row_per_record() {
+        let mut writer = XlsxWriter::new(Cursor::new(vec![]), "Rows & more", Row::HEADERS).unwrap();
+        for row in rows() {
+            writer.write_row(&row.cells()).unwrap();
+        }
+        let file = writer.finish().unwrap();
+
+        let mut archive = zip::ZipArchive::new(file).unwrap();
+        let mut sheet = String::new();
+        archive
+            .by_name("xl/worksheets/sheet1.xml")
+            .unwrap()
+            .read_to_string(&mut sheet)
+            .unwrap();
+        assert!(sheet.contains("<row r=\"3\">"));
+        assert!(sheet.contains("<c r=\"B2\"><v>17.99</v></c>"));
+        assert!(sheet.contains("=HYPERLINK(&quot;x&quot;)"));
+        let mut workbook = String::new();
+        archive
+            .by_name("xl/workbook.xml")
+            .unwrap()
+            .read_to_string(&mut workbook)
+            .unwrap();
+        assert!(workbook.contains("name=\"Rows &amp; more\""));
+    }
+
+    #[test]
+    fn attachments_with_the_same_name_are_archived_apart() {
+        let attachment = || Attachment {
+            id: Uuid::new_v4().into(),
+            subscription_id: Uuid::new_v4().into(),
+            payment_date: None,
+            file_name: "invoice.pdf".into(),
+            content_type: "application/pdf".into(),
+            size_bytes: 9,
+            created_at: Utc::now(),
+        };
+        let (first, second) = (attachment(), attachment());
+        let mut writer = ArchiveWriter::new(Cursor::new(vec![]));
+        writer.add_file(&archive_path(&first), b"%PDF-1.7").unwrap();
+        writer
+            .add_file(&archive_path(&second), b"%PDF-1.4")
+            .unwrap();
+        let file = writer.finish().unwrap();
+
+        let mut archive = zip::ZipArchive::new(file).unwrap();
+        assert_eq!(archive.len(), 2);
+        let mut content = String::new();
+        archive
+            .by_name(&archive_path(&second))
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "%PDF-1.4");
+    }
+
+    #[test]
+    fn columns_are_named_by_letters() {
+        assert_eq!(column_name(0), "A");
+        assert_eq!(column_name(25), "Z");
+        assert_eq!(column_name(26), "AA");
+        assert_eq!(column_name(27), "AB");
+    }
+}
@@ -1,7 +1,21 @@
+mod audit_log;
 pub mod auth;
+pub mod blob_store;
+pub mod budget_alerts;
 pub mod configuration;
 pub mod domain;
+pub mod exchange_rate_store;
+pub mod export;
+mod notification_store;
+pub mod notifications;
+pub mod rate_providers;
+pub mod reminders;
 pub mod routes;
+pub mod service_catalog;
+pub mod service_logos;
 mod session_store;
 pub mod startup;
+pub mod subscription_import;
 pub mod telemetry;
+#[cfg(test)]
+mod test_database;
@@ -0,0 +1,72 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    domain::{Locale, UserId},
+    notifications::{Channel, Message, Notification},
+};
+
+/// Outbox of rendered notifications in the `notification_outbox` table, messages are picked up
+/// from there by the delivery of each channel
+#[derive(Debug, Clone)]
+pub(crate) struct NotificationStore {
+    database: PgPool,
+}
+
+impl NotificationStore {
+    pub fn new(database: PgPool) -> Self {
+        Self { database }
+    }
+
+    /// Renders the notification for every channel and queues it, see [`enqueue`](Self::enqueue)
+    pub async fn enqueue_notification(
+        &self,
+        user_id: UserId,
+        notification: &Notification,
+        locale: Locale,
+        channels: &[Channel],
+        dedup_key: &str,
+    ) -> sqlx::Result<bool> {
+        let messages: Vec<Message> = channels
+            .iter()
+            .map(|channel| notification.render(*channel, locale))
+            .collect();
+        self.enqueue(user_id, &messages, dedup_key).await
+    }
+
+    /// Queues the messages unless a notification with the same `dedup_key` was already queued
+    /// for the user, returns whether anything was queued
+    #[tracing::instrument(name = "Enqueue notification", skip(self, messages))]
+    pub async fn enqueue(
+        &self,
+        user_id: UserId,
+        messages: &[Message],
+        dedup_key: &str,
+    ) -> sqlx::Result<bool> {
+        let mut transaction = self.database.begin().await?;
+        let mut queued = false;
+
+        for message in messages {
+            let result = sqlx::query!(
+                r#"
+                INSERT INTO notification_outbox (id, user_id, channel, subject, body, dedup_key)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (user_id, channel, dedup_key) DO NOTHING
+                "#,
+                Uuid::new_v4(),
+                Into::<Uuid>::into(user_id),
+                message.channel.as_ref(),
+                message.subject,
+                message.body,
+                dedup_key
+            )
+            .execute(&mut *transaction)
+            .await?;
+
+            queued |= result.rows_affected() > 0;
+        }
+
+        transaction.commit().await?;
+        Ok(queued)
+    }
+}
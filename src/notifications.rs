@@ -0,0 +1,550 @@
+use chrono::NaiveDate;
+
+use crate::domain::{CancellationGuide, Category, Locale, Money};
+
+/// Way of reaching the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    Email,
+    Sms,
+    Push,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 3] = [Self::Email, Self::Sms, Self::Push];
+}
+
+impl AsRef<str> for Channel {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Email => "email",
+            Self::Sms => "sms",
+            Self::Push => "push",
+        }
+    }
+}
+
+/// Event the user gets notified about
+#[derive(Debug, Clone, PartialEq)]
+pub enum Notification {
+    /// Trial converts to the regular price soon
+    TrialEnding {
+        subscription: String,
+        converts_on: NaiveDate,
+        /// Regular price charged from the conversion on
+        price: Money,
+    },
+    /// Introductory price ends and a higher price will be charged
+    PriceIncrease {
+        subscription: String,
+        changes_on: NaiveDate,
+        previous: Money,
+        price: Money,
+    },
+    /// Price of the service plan changed, the user can apply it to the subscription
+    PlanPriceChanged {
+        subscription: String,
+        plan: String,
+        /// Price the subscription is charged now
+        previous: Money,
+        price: Money,
+    },
+    /// Last day to cancel before the contract renews for another term
+    CancellationDeadline {
+        subscription: String,
+        cancel_by: NaiveDate,
+        term_ends_on: NaiveDate,
+        /// Current guide of the service so that the user can act right away
+        guide: Option<CancellationGuide>,
+    },
+    /// Card expires soon and the listed subscriptions will fail to charge
+    CardExpiring {
+        payment_method: String,
+        last_four: Option<String>,
+        /// Last day the card is valid on
+        expires_on: NaiveDate,
+        subscriptions: Vec<String>,
+    },
+    /// Suggested service was published in the catalog
+    ServiceApproved { service: String },
+    /// Suggested service stays private
+    ServiceRejected {
+        service: String,
+        reason: Option<String>,
+    },
+    /// Suggested service duplicated a service in the catalog
+    ServiceMerged {
+        service: String,
+        /// Name of the service the subscriptions moved to
+        into: String,
+    },
+    /// Committed spend of the month reached a threshold of the budget
+    BudgetThresholdReached {
+        /// `None` for the overall budget
+        category: Option<Category>,
+        /// Percentage of the limit
+        threshold: u8,
+        committed: Money,
+        limit: Money,
+    },
+    /// A new subscription pushes the average monthly spend over the budget
+    BudgetForecastExceeded {
+        subscription: String,
+        category: Option<Category>,
+        forecast: Money,
+        limit: Money,
+    },
+    /// Weekly summary of the upcoming payments, the total is in the preferred currency
+    PaymentDigest {
+        starts_on: NaiveDate,
+        ends_on: NaiveDate,
+        subscriptions: Vec<String>,
+        total: Money,
+    },
+}
+
+/// Notification rendered for a specific channel
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub channel: Channel,
+    /// Email subject or push notification title, SMS has none
+    pub subject: Option<String>,
+    pub body: String,
+}
+
+/// Texts of a notification, channels pick the ones that fit them
+struct Content {
+    title: String,
+    /// One line, short enough for an SMS or a push notification
+    summary: String,
+    details: String,
+}
+
+impl Notification {
+    /// Renders the notification, amounts and dates are formatted for the `locale`
+    pub fn render(&self, channel: Channel, locale: Locale) -> Message {
+        let Content {
+            title,
+            summary,
+            details,
+        } = self.content(locale);
+
+        match channel {
+            Channel::Email => Message {
+                channel,
+                subject: Some(title),
+                body: details,
+            },
+            Channel::Sms => Message {
+                channel,
+                subject: None,
+                body: format!("Recurio: {}", summary),
+            },
+            Channel::Push => Message {
+                channel,
+                subject: Some(title),
+                body: summary,
+            },
+        }
+    }
+
+    fn content(&self, locale: Locale) -> Content {
+        match self {
+            Self::TrialEnding {
+                subscription,
+                converts_on,
+                price,
+            } => {
+                let date = locale.format_date(*converts_on);
+                let price = locale.format_money(*price);
+                Content {
+                    title: format!("Your {} trial ends soon", subscription),
+                    summary: format!(
+                        "{} trial converts on {} ({})",
+                        subscription, date, price
+                    ),
+                    details: format!(
+                        "Your {} trial converts to a paid subscription on {} and {} will be charged. Cancel before then if you do not want to keep it.",
+                        subscription, date, price
+                    ),
+                }
+            }
+            Self::PriceIncrease {
+                subscription,
+                changes_on,
+                previous,
+                price,
+            } => {
+                let date = locale.format_date(*changes_on);
+                let previous = locale.format_money(*previous);
+                let price = locale.format_money(*price);
+                Content {
+                    title: format!("{} gets more expensive", subscription),
+                    summary: format!(
+                        "{} costs {} instead of {} from {}",
+                        subscription, price, previous, date
+                    ),
+                    details: format!(
+                        "The price of your {} subscription goes up from {} to {} with the renewal on {}.",
+                        subscription, previous, price, date
+                    ),
+                }
+            }
+            Self::PlanPriceChanged {
+                subscription,
+                plan,
+                previous,
+                price,
+            } => {
+                let previous = locale.format_money(*previous);
+                let price = locale.format_money(*price);
+                Content {
+                    title: format!("New price for the {} plan", plan),
+                    summary: format!(
+                        "{} plan now costs {} instead of {}",
+                        plan, price, previous
+                    ),
+                    details: format!(
+                        "The {} plan of your {} subscription now costs {} instead of {}. Update the subscription to keep your spending accurate.",
+                        plan, subscription, price, previous
+                    ),
+                }
+            }
+            Self::CancellationDeadline {
+                subscription,
+                cancel_by,
+                term_ends_on,
+                guide,
+            } => {
+                let cancel_by = locale.format_date(*cancel_by);
+                let term_ends_on = locale.format_date(*term_ends_on);
+                let guide = match guide {
+                    Some(guide) => format!("\n\nHow to cancel:\n{}", guide.to_text()),
+                    None => String::new(),
+                };
+                Content {
+                    title: format!("Cancel {} by {}", subscription, cancel_by),
+                    summary: format!(
+                        "Cancel {} by {} or it renews after {}",
+                        subscription, cancel_by, term_ends_on
+                    ),
+                    details: format!(
+                        "Your {} contract renews for another term after {}. If you want to leave, give notice by {}.{}",
+                        subscription, term_ends_on, cancel_by, guide
+                    ),
+                }
+            }
+            Self::CardExpiring {
+                payment_method,
+                last_four,
+                expires_on,
+                subscriptions,
+            } => {
+                let card = match last_four {
+                    Some(last_four) => format!("{} ending in {}", payment_method, last_four),
+                    None => payment_method.clone(),
+                };
+                let date = locale.format_date(*expires_on);
+                let (summary, details) = match subscriptions.as_slice() {
+                    [] => (
+                        format!("{} expires on {}", card, date),
+                        format!(
+                            "Your card {} expires on {}. None of your subscriptions are charged to it after that.",
+                            card, date
+                        ),
+                    ),
+                    subscriptions => (
+                        format!(
+                            "{} expires on {}, {} subscriptions will fail to charge",
+                            card,
+                            date,
+                            subscriptions.len()
+                        ),
+                        format!(
+                            "Your card {} expires on {}. Update the payment details of these subscriptions to keep them running: {}.",
+                            card,
+                            date,
+                            subscriptions.join(", ")
+                        ),
+                    ),
+                };
+                Content {
+                    title: format!("Your card {} expires soon", payment_method),
+                    summary,
+                    details,
+                }
+            }
+            Self::ServiceApproved { service } => Content {
+                title: format!("{} was added to the catalog", service),
+                summary: format!("{} is now available to everyone", service),
+                details: format!(
+                    "Thanks for your suggestion! {} was approved and is now available to everyone.",
+                    service
+                ),
+            },
+            Self::ServiceRejected { service, reason } => {
+                let reason = match reason {
+                    Some(reason) => format!(" Reason: {}", reason),
+                    None => String::new(),
+                };
+                Content {
+                    title: format!("{} was not added to the catalog", service),
+                    summary: format!("{} was not added to the catalog", service),
+                    details: format!(
+                        "Your suggestion {} was not added to the catalog, it stays available to you only.{}",
+                        service, reason
+                    ),
+                }
+            }
+            Self::ServiceMerged { service, into } => Content {
+                title: format!("{} is already in the catalog", service),
+                summary: format!("{} was merged into {}", service, into),
+                details: format!(
+                    "Your suggestion {} is already in the catalog as {}. Your subscriptions were moved to {}.",
+                    service, into, into
+                ),
+            },
+            Self::BudgetThresholdReached {
+                category,
+                threshold,
+                committed,
+                limit,
+            } => {
+                let budget = budget_name(*category);
+                let committed = locale.format_money(*committed);
+                let limit = locale.format_money(*limit);
+                Content {
+                    title: format!("{}% of your {} used", threshold, budget),
+                    summary: format!("{} of your {} {} committed", committed, limit, budget),
+                    details: format!(
+                        "You have committed {} of your {} of {} this month.",
+                        committed, budget, limit
+                    ),
+                }
+            }
+            Self::BudgetForecastExceeded {
+                subscription,
+                category,
+                forecast,
+                limit,
+            } => {
+                let budget = budget_name(*category);
+                let forecast = locale.format_money(*forecast);
+                let limit = locale.format_money(*limit);
+                Content {
+                    title: format!("{} exceeds your {}", subscription, budget),
+                    summary: format!(
+                        "With {} you spend {} a month, over your {} {}",
+                        subscription, forecast, limit, budget
+                    ),
+                    details: format!(
+                        "With {} your spending is projected at {} a month, which is over your {} of {}.",
+                        subscription, forecast, budget, limit
+                    ),
+                }
+            }
+            Self::PaymentDigest {
+                starts_on,
+                ends_on,
+                subscriptions,
+                total,
+            } => {
+                let starts_on = locale.format_date(*starts_on);
+                let ends_on = locale.format_date(*ends_on);
+                let total = locale.format_money(*total);
+                Content {
+                    title: format!("{} due this week", total),
+                    summary: format!(
+                        "{} subscriptions renew by {} ({})",
+                        subscriptions.len(),
+                        ends_on,
+                        total
+                    ),
+                    details: format!(
+                        "From {} to {} you will be charged {} in total for {}.",
+                        starts_on,
+                        ends_on,
+                        total,
+                        subscriptions.join(", ")
+                    ),
+                }
+            }
+        }
+    }
+}
+
+fn budget_name(category: Option<Category>) -> String {
+    match category {
+        Some(category) => format!("{} budget", category.as_ref()),
+        None => "monthly budget".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, Utc};
+    use iso_currency::Currency;
+    use uuid::Uuid;
+
+    use super::{Channel, Notification};
+    use crate::domain::{CancellationGuide, Category, Locale, Money};
+
+    fn trial_ending() -> Notification {
+        Notification::TrialEnding {
+            subscription: "Netflix".into(),
+            converts_on: NaiveDate::from_ymd_opt(2023, 9, 15).unwrap(),
+            price: Money::new(1299, Currency::EUR),
+        }
+    }
+
+    #[test]
+    fn email_is_formatted_for_user_locale() {
+        let message = trial_ending().render(Channel::Email, Locale::DeDe);
+        assert_eq!(
+            message.subject.as_deref(),
+            Some("Your Netflix trial ends soon")
+        );
+        assert_eq!(
+            message.body,
+            "Your Netflix trial converts to a paid subscription on 15.09.2023 and 12,99 € will be charged. Cancel before then if you do not want to keep it."
+        );
+    }
+
+    #[test]
+    fn sms_has_no_subject() {
+        let message = trial_ending().render(Channel::Sms, Locale::EnUs);
+        assert_eq!(message.subject, None);
+        assert_eq!(
+            message.body,
+            "Recurio: Netflix trial converts on 09/15/2023 (€12.99)"
+        );
+    }
+
+    #[test]
+    fn push_uses_short_summary() {
+        let message = trial_ending().render(Channel::Push, Locale::NlNl);
+        assert_eq!(
+            message.body,
+            "Netflix trial converts on 15-09-2023 (€ 12,99)"
+        );
+    }
+
+    #[test]
+    fn price_increase_shows_both_prices() {
+        let notification = Notification::PriceIncrease {
+            subscription: "Spotify".into(),
+            changes_on: NaiveDate::from_ymd_opt(2023, 12, 1).unwrap(),
+            previous: Money::new(100, Currency::EUR),
+            price: Money::new(1099, Currency::EUR),
+        };
+        let message = notification.render(Channel::Push, Locale::EnGb);
+        assert_eq!(
+            message.body,
+            "Spotify costs €10.99 instead of €1.00 from 01/12/2023"
+        );
+    }
+
+    #[test]
+    fn plan_price_change_names_the_plan() {
+        let notification = Notification::PlanPriceChanged {
+            subscription: "Spotify".into(),
+            plan: "Duo".into(),
+            previous: Money::new(1299, Currency::EUR),
+            price: Money::new(1499, Currency::EUR),
+        };
+        let message = notification.render(Channel::Push, Locale::EnGb);
+        assert_eq!(
+            message.subject.as_deref(),
+            Some("New price for the Duo plan")
+        );
+        assert_eq!(message.body, "Duo plan now costs €14.99 instead of €12.99");
+    }
+
+    #[test]
+    fn rejection_includes_the_reason() {
+        let notification = Notification::ServiceRejected {
+            service: "Netflx".into(),
+            reason: Some("Misspelled.".into()),
+        };
+        let message = notification.render(Channel::Email, Locale::EnGb);
+        assert_eq!(
+            message.body,
+            "Your suggestion Netflx was not added to the catalog, it stays available to you only. Reason: Misspelled."
+        );
+    }
+
+    #[test]
+    fn cancellation_deadline_embeds_the_guide() {
+        let notification = Notification::CancellationDeadline {
+            subscription: "Gym".into(),
+            cancel_by: NaiveDate::from_ymd_opt(2023, 11, 30).unwrap(),
+            term_ends_on: NaiveDate::from_ymd_opt(2023, 12, 31).unwrap(),
+            guide: Some(CancellationGuide {
+                service_id: Uuid::new_v4().into(),
+                version: 1,
+                url: None,
+                steps: vec!["Write to the front desk".into()],
+                notice_days: Some(30),
+                contact_email: Some("members@example.com".into()),
+                contact_phone: None,
+                edited_by: None,
+                created_at: Utc::now(),
+            }),
+        };
+        let message = notification.render(Channel::Email, Locale::EnGb);
+        assert_eq!(
+            message.body,
+            "Your Gym contract renews for another term after 31/12/2023. If you want to leave, give notice by 30/11/2023.\n\nHow to cancel:\n1. Write to the front desk\nNotice required: 30 days before the end of the billing period\nContact: members@example.com"
+        );
+    }
+
+    #[test]
+    fn card_expiry_lists_affected_subscriptions() {
+        let notification = Notification::CardExpiring {
+            payment_method: "Visa".into(),
+            last_four: Some("4242".into()),
+            expires_on: NaiveDate::from_ymd_opt(2023, 10, 31).unwrap(),
+            subscriptions: vec!["Netflix".into(), "Spotify".into()],
+        };
+        let message = notification.render(Channel::Email, Locale::EnGb);
+        assert_eq!(
+            message.body,
+            "Your card Visa ending in 4242 expires on 31/10/2023. Update the payment details of these subscriptions to keep them running: Netflix, Spotify."
+        );
+    }
+
+    #[test]
+    fn payment_digest_shows_the_total_in_the_user_locale() {
+        let notification = Notification::PaymentDigest {
+            starts_on: NaiveDate::from_ymd_opt(2023, 9, 20).unwrap(),
+            ends_on: NaiveDate::from_ymd_opt(2023, 9, 26).unwrap(),
+            subscriptions: vec!["Netflix".into(), "Hulu".into()],
+            total: Money::new(1800, Currency::EUR),
+        };
+        let message = notification.render(Channel::Email, Locale::DeDe);
+        assert_eq!(message.subject.as_deref(), Some("18,00 € due this week"));
+        assert_eq!(
+            message.body,
+            "From 20.09.2023 to 26.09.2023 you will be charged 18,00 € in total for Netflix, Hulu."
+        );
+    }
+
+    #[test]
+    fn budget_alert_names_the_category() {
+        let notification = Notification::BudgetThresholdReached {
+            category: Some(Category::Streaming),
+            threshold: 80,
+            committed: Money::new(4250, Currency::EUR),
+            limit: Money::new(5000, Currency::EUR),
+        };
+        let message = notification.render(Channel::Email, Locale::EnGb);
+        assert_eq!(
+            message.subject.as_deref(),
+            Some("80% of your streaming budget used")
+        );
+        assert_eq!(
+            message.body,
+            "You have committed €42.50 of your streaming budget of €50.00 this month."
+        );
+    }
+}
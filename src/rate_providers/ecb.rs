@@ -0,0 +1,253 @@
+use std::{
+    fs::File,
+    io::{Read, Seek},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use iso_currency::Currency;
+use quick_xml::{events::Event, Reader};
+use rust_decimal::Decimal;
+
+use super::RateProvider;
+use crate::domain::{CurrencyCode, ExchangeRate};
+
+/// European Central Bank euro foreign exchange reference rates read from the files published at
+/// <https://www.ecb.europa.eu/stats/policy_and_exchange_rates/euro_reference_exchange_rates>
+///
+/// Supports the daily and historical XML files (`eurofxref-daily.xml`, `eurofxref-hist.xml`)
+/// and the zipped CSV files (`eurofxref.zip`, `eurofxref-hist.zip`). All rates use EUR as base.
+pub struct EcbFileProvider {
+    path: PathBuf,
+}
+
+impl EcbFileProvider {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl RateProvider for EcbFileProvider {
+    fn name(&self) -> &str {
+        "ECB reference rates"
+    }
+
+    #[tracing::instrument(name = "Read ECB reference rates", skip(self), fields(path = %self.path.display()))]
+    async fn fetch_rates(&self) -> Result<Vec<ExchangeRate>, String> {
+        let path = self.path.clone();
+        tokio::task::spawn_blocking(move || {
+            let file = File::open(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some("xml") => parse_xml(file),
+                Some("csv") => parse_csv(file),
+                Some("zip") => parse_zip(file),
+                _ => Err(format!(
+                    "{} is not a supported ECB rates file, expected .xml, .csv or .zip",
+                    path.display()
+                )),
+            }
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    }
+}
+
+/// Parses the `gesmes:Envelope` document, rates are nested in `<Cube time="...">` elements
+pub fn parse_xml(mut reader: impl Read) -> Result<Vec<ExchangeRate>, String> {
+    let mut content = String::new();
+    reader
+        .read_to_string(&mut content)
+        .map_err(|e| e.to_string())?;
+
+    let mut xml = Reader::from_str(&content);
+    let mut rates = vec![];
+    let mut date = None;
+
+    loop {
+        match xml.read_event().map_err(|e| e.to_string())? {
+            Event::Start(cube) | Event::Empty(cube) if cube.name().as_ref() == b"Cube" => {
+                let mut currency = None;
+                let mut rate = None;
+                for attribute in cube.attributes() {
+                    let attribute = attribute.map_err(|e| e.to_string())?;
+                    let value = attribute.unescape_value().map_err(|e| e.to_string())?;
+                    match attribute.key.as_ref() {
+                        b"time" => date = Some(parse_date(&value)?),
+                        b"currency" => currency = Some(value.into_owned()),
+                        b"rate" => rate = Some(value.into_owned()),
+                        _ => {}
+                    }
+                }
+
+                if let (Some(currency), Some(rate)) = (currency, rate) {
+                    let date = date.ok_or("rate found outside of a dated Cube element")?;
+                    rates.extend(euro_rate(&currency, &rate, date)?);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(rates)
+}
+
+/// Parses the CSV file, one row per day with a column per currency
+pub fn parse_csv(reader: impl Read) -> Result<Vec<ExchangeRate>, String> {
+    let mut csv = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(reader);
+
+    let currencies: Vec<String> = csv
+        .headers()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .skip(1)
+        .map(str::to_owned)
+        .collect();
+
+    let mut rates = vec![];
+    for record in csv.records() {
+        let record = record.map_err(|e| e.to_string())?;
+        let Some(date) = record.get(0).filter(|date| !date.is_empty()) else {
+            continue;
+        };
+        let date = parse_date(date)?;
+
+        for (currency, rate) in currencies.iter().zip(record.iter().skip(1)) {
+            rates.extend(euro_rate(currency, rate, date)?);
+        }
+    }
+
+    Ok(rates)
+}
+
+/// Parses every CSV file found in the archive
+pub fn parse_zip(reader: impl Read + Seek) -> Result<Vec<ExchangeRate>, String> {
+    let mut archive = zip::ZipArchive::new(reader).map_err(|e| e.to_string())?;
+    let mut rates = vec![];
+
+    for index in 0..archive.len() {
+        let file = archive.by_index(index).map_err(|e| e.to_string())?;
+        if file.name().ends_with(".csv") {
+            rates.extend(parse_csv(file)?);
+        }
+    }
+
+    Ok(rates)
+}
+
+/// Historical files use ISO dates, daily CSV uses `01 September 2023`
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(value, "%d %B %Y"))
+        .map_err(|_| format!("{} is not a valid ECB rate date", value))
+}
+
+/// `None` for empty cells, `N/A` values and currencies which are no longer in use
+fn euro_rate(currency: &str, rate: &str, date: NaiveDate) -> Result<Option<ExchangeRate>, String> {
+    if currency.is_empty() || rate.is_empty() || rate == "N/A" {
+        return Ok(None);
+    }
+
+    let Ok(quote) = CurrencyCode::parse(currency) else {
+        tracing::debug!("Skipping rate for unknown currency {}", currency);
+        return Ok(None);
+    };
+    let rate = Decimal::from_str(rate)
+        .map_err(|e| format!("{} is not a valid rate for {}: {}", rate, currency, e))?;
+
+    Ok(Some(ExchangeRate {
+        base: Currency::EUR,
+        quote: *quote,
+        date,
+        rate,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use claims::assert_err;
+    use iso_currency::Currency;
+    use rust_decimal::Decimal;
+
+    use super::{parse_csv, parse_xml, parse_zip};
+    use crate::domain::fixtures::date;
+
+    const DAILY_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gesmes:Envelope xmlns:gesmes="http://www.gesmes.org/xml/2002-08-01" xmlns="http://www.ecb.int/vocabulary/2002-08-01/eurofxref">
+    <gesmes:subject>Reference rates</gesmes:subject>
+    <gesmes:Sender>
+        <gesmes:name>European Central Bank</gesmes:name>
+    </gesmes:Sender>
+    <Cube>
+        <Cube time='2023-09-01'>
+            <Cube currency='USD' rate='1.0844'/>
+            <Cube currency='JPY' rate='158.61'/>
+        </Cube>
+        <Cube time='2023-08-31'>
+            <Cube currency='USD' rate='1.0871'/>
+        </Cube>
+    </Cube>
+</gesmes:Envelope>"#;
+
+    const HISTORICAL_CSV: &str = "Date,USD,JPY,CYP,\n\
+        2023-09-01,1.0844,158.61,N/A,\n\
+        2023-08-31,1.0871,158.40,N/A,\n";
+
+    #[test]
+    fn xml_rates_are_parsed_with_their_dates() {
+        let rates = parse_xml(DAILY_XML.as_bytes()).unwrap();
+
+        assert_eq!(rates.len(), 3);
+        assert!(rates.iter().all(|rate| rate.base == Currency::EUR));
+        assert_eq!(rates[0].quote, Currency::USD);
+        assert_eq!(rates[0].date, date(2023, 9, 1));
+        assert_eq!(rates[0].rate, Decimal::new(10844, 4));
+        assert_eq!(rates[2].date, date(2023, 8, 31));
+    }
+
+    #[test]
+    fn csv_skips_missing_values_and_trailing_columns() {
+        let rates = parse_csv(HISTORICAL_CSV.as_bytes()).unwrap();
+
+        assert_eq!(rates.len(), 4);
+        assert_eq!(rates[1].quote, Currency::JPY);
+        assert_eq!(rates[1].rate, Decimal::new(15861, 2));
+    }
+
+    #[test]
+    fn daily_csv_date_format_is_supported() {
+        let csv = "Date, USD, JPY, \n01 September 2023, 1.0844, 158.61, \n";
+        let rates = parse_csv(csv.as_bytes()).unwrap();
+
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates[0].date, date(2023, 9, 1));
+    }
+
+    #[test]
+    fn invalid_rate_is_rejected() {
+        let csv = "Date,USD\n2023-09-01,abc\n";
+        assert_err!(parse_csv(csv.as_bytes()));
+    }
+
+    #[test]
+    fn csv_files_are_read_from_zip_archive() {
+        let mut archive = zip::ZipWriter::new(Cursor::new(vec![]));
+        archive
+            .start_file("eurofxref-hist.csv", Default::default())
+            .unwrap();
+        archive.write_all(HISTORICAL_CSV.as_bytes()).unwrap();
+        let archive = archive.finish().unwrap();
+
+        let rates = parse_zip(Cursor::new(archive.into_inner())).unwrap();
+
+        assert_eq!(rates.len(), 4);
+    }
+}
@@ -0,0 +1,16 @@
+mod ecb;
+
+pub use ecb::*;
+
+use async_trait::async_trait;
+
+use crate::domain::ExchangeRate;
+
+/// Source of daily exchange rates
+#[async_trait]
+pub trait RateProvider {
+    /// Name of the source, used in logs
+    fn name(&self) -> &str;
+
+    async fn fetch_rates(&self) -> Result<Vec<ExchangeRate>, String>;
+}
@@ -0,0 +1,490 @@
+//! Reminders about upcoming changes of subscriptions queued in the notification outbox
+use chrono::{Datelike, Days, Months, NaiveDate};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    domain::{
+        CancellationDeadline, CardExpiry, CurrencyCode, Locale, PaymentDigest, UserId,
+        CANCEL_BY_REMINDER_DAYS, PRICE_CHANGE_REMINDER_DAYS,
+    },
+    exchange_rate_store::ExchangeRateStore,
+    notification_store::NotificationStore,
+    notifications::{Channel, Notification},
+    routes::{fetch_cancellation_guide, fetch_user_subscriptions},
+};
+
+const REMINDER_CHANNELS: [Channel; 2] = [Channel::Email, Channel::Push];
+
+/// Reminds users of trials converting within their chosen number of days, every trial is
+/// announced once. Returns the number of queued reminders.
+#[tracing::instrument(name = "Send trial reminders", skip(database))]
+pub async fn send_trial_reminders(database: &PgPool, today: NaiveDate) -> Result<usize, String> {
+    let users = sqlx::query!(
+        r#"
+        SELECT DISTINCT s.user_id, users.locale
+        FROM subscriptions s
+        JOIN users ON users.id = s.user_id
+        WHERE s.trial_ends_on > $1
+          AND s.trial_ends_on - s.trial_reminder_days::int <= $1
+          AND s.cancelled_at IS NULL
+          AND s.deleted_at IS NULL
+        "#,
+        today
+    )
+    .fetch_all(database)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let store = NotificationStore::new(database.clone());
+    let mut queued = 0;
+    for user in users {
+        match send_user_trial_reminders(&store, database, user.user_id.into(), &user.locale, today)
+            .await
+        {
+            Ok(count) => queued += count,
+            Err(e) => {
+                tracing::error!(
+                    "Failed to send trial reminders to user {}: {}",
+                    user.user_id,
+                    e
+                )
+            }
+        }
+    }
+
+    Ok(queued)
+}
+
+async fn send_user_trial_reminders(
+    store: &NotificationStore,
+    database: &PgPool,
+    user_id: UserId,
+    locale: &str,
+    today: NaiveDate,
+) -> Result<usize, String> {
+    let locale = Locale::parse(locale)?;
+    let subscriptions = fetch_user_subscriptions(database, user_id).await?;
+
+    let mut queued = 0;
+    for subscription in subscriptions {
+        if subscription.cancelled_at.is_some() {
+            continue;
+        }
+        let Some(trial) = &subscription.trial else {
+            continue;
+        };
+        let is_due = today < trial.ends_on
+            && trial
+                .reminder_date()
+                .is_some_and(|reminder_date| reminder_date <= today);
+        if !is_due {
+            continue;
+        }
+
+        // The conversion is the first paid renewal, introductory prices apply to it
+        let notification = Notification::TrialEnding {
+            subscription: subscription.name.clone(),
+            converts_on: trial.ends_on,
+            price: subscription.price_of_renewal(0).share(subscription.share)?,
+        };
+        let dedup_key = format!("trial:{}:{}", Uuid::from(subscription.id), trial.ends_on);
+
+        let is_queued = store
+            .enqueue_notification(
+                subscription.user_id,
+                &notification,
+                locale,
+                &REMINDER_CHANNELS,
+                &dedup_key,
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        queued += usize::from(is_queued);
+    }
+
+    Ok(queued)
+}
+
+/// Reminds users [`PRICE_CHANGE_REMINDER_DAYS`] before an introductory price phase ends and the
+/// price goes up. Returns the number of queued reminders.
+#[tracing::instrument(name = "Send price increase reminders", skip(database))]
+pub async fn send_price_increase_reminders(
+    database: &PgPool,
+    today: NaiveDate,
+) -> Result<usize, String> {
+    let users = sqlx::query!(
+        r#"
+        SELECT DISTINCT s.user_id, users.locale
+        FROM subscription_price_phases p
+        JOIN subscriptions s ON s.id = p.subscription_id
+        JOIN users ON users.id = s.user_id
+        WHERE s.cancelled_at IS NULL AND s.deleted_at IS NULL
+        "#
+    )
+    .fetch_all(database)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let store = NotificationStore::new(database.clone());
+    let mut queued = 0;
+    for user in users {
+        match send_user_price_increase_reminders(
+            &store,
+            database,
+            user.user_id.into(),
+            &user.locale,
+            today,
+        )
+        .await
+        {
+            Ok(count) => queued += count,
+            Err(e) => tracing::error!(
+                "Failed to send price increase reminders to user {}: {}",
+                user.user_id,
+                e
+            ),
+        }
+    }
+
+    Ok(queued)
+}
+
+async fn send_user_price_increase_reminders(
+    store: &NotificationStore,
+    database: &PgPool,
+    user_id: UserId,
+    locale: &str,
+    today: NaiveDate,
+) -> Result<usize, String> {
+    let locale = Locale::parse(locale)?;
+    let reminder_window = today
+        .checked_add_days(Days::new(PRICE_CHANGE_REMINDER_DAYS.into()))
+        .ok_or("date out of range")?;
+    let subscriptions = fetch_user_subscriptions(database, user_id).await?;
+
+    let mut queued = 0;
+    for subscription in subscriptions {
+        if subscription.cancelled_at.is_some() {
+            continue;
+        }
+        let Some(change) = subscription.next_price_change(today) else {
+            continue;
+        };
+        if !change.is_increase() || change.date > reminder_window {
+            continue;
+        }
+
+        let notification = Notification::PriceIncrease {
+            subscription: subscription.name.clone(),
+            changes_on: change.date,
+            previous: change.previous.share(subscription.share)?,
+            price: change.price.share(subscription.share)?,
+        };
+        let dedup_key = format!("price:{}:{}", Uuid::from(subscription.id), change.date);
+
+        let is_queued = store
+            .enqueue_notification(
+                subscription.user_id,
+                &notification,
+                locale,
+                &REMINDER_CHANNELS,
+                &dedup_key,
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        queued += usize::from(is_queued);
+    }
+
+    Ok(queued)
+}
+
+/// Reminds users [`CANCEL_BY_REMINDER_DAYS`] before the last day they can give notice on to
+/// leave an auto-renewing contract, together with the cancellation guide of the service. Returns
+/// the number of queued reminders.
+#[tracing::instrument(name = "Send cancellation deadline reminders", skip(database))]
+pub async fn send_cancellation_deadline_reminders(
+    database: &PgPool,
+    today: NaiveDate,
+) -> Result<usize, String> {
+    let users = sqlx::query!(
+        r#"
+        SELECT DISTINCT s.user_id, users.locale
+        FROM subscriptions s
+        JOIN users ON users.id = s.user_id
+        WHERE s.contract_auto_renew AND s.cancelled_at IS NULL AND s.deleted_at IS NULL
+        "#
+    )
+    .fetch_all(database)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let store = NotificationStore::new(database.clone());
+    let mut queued = 0;
+    for user in users {
+        match send_user_cancellation_deadline_reminders(
+            &store,
+            database,
+            user.user_id.into(),
+            &user.locale,
+            today,
+        )
+        .await
+        {
+            Ok(count) => queued += count,
+            Err(e) => tracing::error!(
+                "Failed to send cancellation deadline reminders to user {}: {}",
+                user.user_id,
+                e
+            ),
+        }
+    }
+
+    Ok(queued)
+}
+
+async fn send_user_cancellation_deadline_reminders(
+    store: &NotificationStore,
+    database: &PgPool,
+    user_id: UserId,
+    locale: &str,
+    today: NaiveDate,
+) -> Result<usize, String> {
+    let locale = Locale::parse(locale)?;
+    let reminder_window = today
+        .checked_add_days(Days::new(CANCEL_BY_REMINDER_DAYS.into()))
+        .ok_or("date out of range")?;
+    let subscriptions = fetch_user_subscriptions(database, user_id).await?;
+
+    let mut queued = 0;
+    for subscription in subscriptions {
+        if subscription.cancelled_at.is_some() {
+            continue;
+        }
+        let Some(CancellationDeadline {
+            cancel_by,
+            term_ends_on,
+        }) = subscription.cancellation_deadline(today)
+        else {
+            continue;
+        };
+        if cancel_by > reminder_window {
+            continue;
+        }
+
+        let guide = fetch_cancellation_guide(database, subscription.service_id).await?;
+        let notification = Notification::CancellationDeadline {
+            subscription: subscription.name.clone(),
+            cancel_by,
+            term_ends_on,
+            guide,
+        };
+        let dedup_key = format!("cancel_by:{}:{}", Uuid::from(subscription.id), cancel_by);
+
+        let is_queued = store
+            .enqueue_notification(
+                subscription.user_id,
+                &notification,
+                locale,
+                &REMINDER_CHANNELS,
+                &dedup_key,
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        queued += usize::from(is_queued);
+    }
+
+    Ok(queued)
+}
+
+/// Payment method with an expiry date together with the locale of its owner
+struct CardRow {
+    id: Uuid,
+    user_id: Uuid,
+    label: String,
+    last_four: Option<String>,
+    expiry_month: i16,
+    expiry_year: i16,
+    locale: String,
+}
+
+/// Alerts users of cards expiring within
+/// [`CARD_EXPIRY_ALERT_DAYS`](crate::domain::CARD_EXPIRY_ALERT_DAYS), listing the subscriptions
+/// charged to the card after it expires. Returns the number of queued alerts.
+#[tracing::instrument(name = "Send card expiry alerts", skip(database))]
+pub async fn send_card_expiry_alerts(database: &PgPool, today: NaiveDate) -> Result<usize, String> {
+    let cards = sqlx::query_as!(
+        CardRow,
+        r#"
+        SELECT
+            p.id, p.user_id, p.label, p.last_four,
+            p.expiry_month AS "expiry_month!", p.expiry_year AS "expiry_year!", users.locale
+        FROM payment_methods p
+        JOIN users ON users.id = p.user_id
+        WHERE p.expiry_month IS NOT NULL AND p.expiry_year IS NOT NULL
+        "#
+    )
+    .fetch_all(database)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let store = NotificationStore::new(database.clone());
+    let mut queued = 0;
+    for card in cards {
+        match send_card_expiry_alert(&store, database, &card, today).await {
+            Ok(is_queued) => queued += usize::from(is_queued),
+            Err(e) => tracing::error!("Failed to send the expiry alert of card {}: {}", card.id, e),
+        }
+    }
+
+    Ok(queued)
+}
+
+/// Whether an alert got queued, only cards expiring soon are announced
+async fn send_card_expiry_alert(
+    store: &NotificationStore,
+    database: &PgPool,
+    card: &CardRow,
+    today: NaiveDate,
+) -> Result<bool, String> {
+    let expiry = CardExpiry::parse(
+        card.expiry_month
+            .try_into()
+            .map_err(|_| "invalid expiry month")?,
+        card.expiry_year
+            .try_into()
+            .map_err(|_| "invalid expiry year")?,
+    )?;
+    if !expiry.is_expiring(today) {
+        return Ok(false);
+    }
+
+    let expired = expiry.last_day().succ_opt().ok_or("date out of range")?;
+    let year_after = expired
+        .checked_add_months(Months::new(12))
+        .ok_or("date out of range")?;
+    let mut subscriptions = vec![];
+    for subscription in fetch_user_subscriptions(database, card.user_id.into()).await? {
+        if subscription.payment_method_id == Some(card.id.into())
+            && !subscription
+                .charges_between(expired, year_after)?
+                .is_empty()
+        {
+            subscriptions.push(subscription.name);
+        }
+    }
+
+    let notification = Notification::CardExpiring {
+        payment_method: card.label.clone(),
+        last_four: card.last_four.clone(),
+        expires_on: expiry.last_day(),
+        subscriptions,
+    };
+    let dedup_key = format!(
+        "card_expiry:{}:{}-{:02}",
+        card.id, expiry.year, expiry.month
+    );
+
+    store
+        .enqueue_notification(
+            card.user_id.into(),
+            &notification,
+            Locale::parse(&card.locale)?,
+            &REMINDER_CHANNELS,
+            &dedup_key,
+        )
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Sends every user with active subscriptions a digest of the payments of the coming week, in
+/// their preferred currency. Users get one digest per calendar week, covering the week from the
+/// day it was sent on. Returns the number of queued digests.
+#[tracing::instrument(name = "Send payment digests", skip(database))]
+pub async fn send_payment_digests(database: &PgPool, today: NaiveDate) -> Result<usize, String> {
+    let users = sqlx::query!(
+        r#"
+        SELECT DISTINCT users.id, users.locale, users.preferred_currency
+        FROM users
+        JOIN subscriptions s ON s.user_id = users.id
+        WHERE s.cancelled_at IS NULL AND s.deleted_at IS NULL
+        "#
+    )
+    .fetch_all(database)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let store = NotificationStore::new(database.clone());
+    let rate_store = ExchangeRateStore::new(database.clone());
+    let mut queued = 0;
+    for user in users {
+        // Missing rates of one user should not hold back the digests of everyone else
+        match send_user_payment_digest(
+            &store,
+            &rate_store,
+            database,
+            user.id.into(),
+            &user.locale,
+            &user.preferred_currency,
+            today,
+        )
+        .await
+        {
+            Ok(is_queued) => queued += usize::from(is_queued),
+            Err(e) => tracing::error!(
+                "Failed to send the payment digest of user {}: {}",
+                user.id,
+                e
+            ),
+        }
+    }
+
+    Ok(queued)
+}
+
+/// Whether a digest got queued, nobody gets one for a week without payments
+async fn send_user_payment_digest(
+    store: &NotificationStore,
+    rate_store: &ExchangeRateStore,
+    database: &PgPool,
+    user_id: UserId,
+    locale: &str,
+    preferred_currency: &str,
+    today: NaiveDate,
+) -> Result<bool, String> {
+    let locale = Locale::parse(locale)?;
+    let currency = *CurrencyCode::parse(preferred_currency)?;
+    let subscriptions = fetch_user_subscriptions(database, user_id).await?;
+    let rates = rate_store
+        .load_for([currency], subscriptions.iter().map(|s| s.price.currency()))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let Some(digest) = PaymentDigest::compute(&subscriptions, &rates, currency, today)? else {
+        return Ok(false);
+    };
+    let notification = Notification::PaymentDigest {
+        starts_on: digest.starts_on,
+        ends_on: digest.ends_on,
+        subscriptions: digest.subscriptions,
+        total: digest.total,
+    };
+    let week = today.iso_week();
+    let dedup_key = format!(
+        "digest:{}:{}-W{:02}",
+        Uuid::from(user_id),
+        week.year(),
+        week.week()
+    );
+
+    store
+        .enqueue_notification(
+            user_id,
+            &notification,
+            locale,
+            &REMINDER_CHANNELS,
+            &dedup_key,
+        )
+        .await
+        .map_err(|e| e.to_string())
+}
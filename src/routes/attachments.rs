@@ -0,0 +1,337 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::NaiveDate;
+use hyper::StatusCode;
+use uuid::Uuid;
+
+use crate::{
+    auth::{current_user, AuthContext},
+    domain::{ensure_attachment_quota, Attachment, NewAttachment, SubscriptionId, UserId},
+    startup::AppState,
+};
+
+use super::fetch_subscription;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentUpload {
+    file_name: String,
+    /// Date of the payment the file belongs to, e.g. the invoice of a renewal
+    payment_date: Option<NaiveDate>,
+}
+
+struct AttachmentRow {
+    id: Uuid,
+    subscription_id: Uuid,
+    payment_date: Option<NaiveDate>,
+    file_name: String,
+    content_type: String,
+    size_bytes: i64,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<AttachmentRow> for Attachment {
+    fn from(row: AttachmentRow) -> Self {
+        Self {
+            id: row.id.into(),
+            subscription_id: row.subscription_id.into(),
+            payment_date: row.payment_date,
+            file_name: row.file_name,
+            content_type: row.content_type,
+            size_bytes: row.size_bytes,
+            created_at: row.created_at,
+        }
+    }
+}
+
+/// Files of the subscription, grouped by payment with the latest payment first
+#[tracing::instrument(name = "Attachments index", skip_all, fields(subscription_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn attachments_index(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<Attachment>>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    fetch_subscription(&database, user.id, id.into())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Subscription not found".to_string()))?;
+
+    let attachments = sqlx::query_as!(
+        AttachmentRow,
+        r#"
+        SELECT id, subscription_id, payment_date, file_name, content_type, size_bytes, created_at
+        FROM attachments
+        WHERE subscription_id = $1 AND user_id = $2
+        ORDER BY payment_date DESC NULLS LAST, created_at, id
+        "#,
+        id,
+        Uuid::from(user.id)
+    )
+    .fetch_all(&database)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(
+        attachments.into_iter().map(Attachment::from).collect(),
+    ))
+}
+
+/// Stores the file in the request body, its type is taken from the `Content-Type` header
+#[tracing::instrument(name = "Create attachment", skip_all, fields(subscription_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn create_attachment(
+    State(AppState { database, blobs }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+    Query(upload): Query<AttachmentUpload>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<Attachment>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let internal_error = |e: String| (StatusCode::INTERNAL_SERVER_ERROR, e);
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let attachment =
+        NewAttachment::parse(&upload.file_name, content_type, &body, upload.payment_date)
+            .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let subscription = fetch_subscription(&database, user.id, id.into())
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Subscription not found".to_string()))?;
+    attachment
+        .ensure_payment_of(&subscription)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let attachment_id = Uuid::new_v4();
+    let blob_key = attachment_key(user.id, attachment_id);
+    let size = body.len();
+    blobs
+        .put(&blob_key, body.into())
+        .await
+        .map_err(internal_error)?;
+
+    let saved = save_attachment(
+        &database,
+        user.id,
+        id.into(),
+        attachment_id,
+        &attachment,
+        size,
+        &blob_key,
+    )
+    .await;
+    if saved.is_err() {
+        if let Err(e) = blobs.delete(&blob_key).await {
+            tracing::warn!("Failed to delete an unsaved attachment: {}", e);
+        }
+    }
+
+    saved.map(Json)
+}
+
+/// Downloads the file with the name it was uploaded with
+#[tracing::instrument(name = "Download attachment", skip_all, fields(attachment_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn attachment_handler(
+    State(AppState { database, blobs }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+) -> Result<Response, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let not_found = || (StatusCode::NOT_FOUND, "Attachment not found".to_string());
+
+    let attachment = sqlx::query!(
+        r#"
+        SELECT file_name, content_type, blob_key FROM attachments
+        WHERE id = $1 AND user_id = $2
+        "#,
+        id,
+        Uuid::from(user.id)
+    )
+    .fetch_optional(&database)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or_else(not_found)?;
+    let bytes = blobs
+        .get(&attachment.blob_key)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .ok_or_else(not_found)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, attachment.content_type),
+            (
+                header::CONTENT_DISPOSITION,
+                content_disposition(&attachment.file_name),
+            ),
+            (header::CACHE_CONTROL, "private, no-cache".to_string()),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+        ],
+        bytes,
+    )
+        .into_response())
+}
+
+#[tracing::instrument(name = "Delete attachment", skip_all, fields(attachment_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn delete_attachment(
+    State(AppState { database, blobs }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let user = current_user(auth)?;
+
+    let blob_key = sqlx::query_scalar!(
+        "DELETE FROM attachments WHERE id = $1 AND user_id = $2 RETURNING blob_key",
+        id,
+        Uuid::from(user.id)
+    )
+    .fetch_optional(&database)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or_else(|| (StatusCode::NOT_FOUND, "Attachment not found".to_string()))?;
+    // The file is not referenced anymore, a leftover only takes up disk space
+    if let Err(e) = blobs.delete(&blob_key).await {
+        tracing::warn!("Failed to delete the file of attachment {}: {}", id, e);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// All attachments of the user with the keys of their files, oldest first
+pub(crate) async fn fetch_user_attachments(
+    database: &sqlx::PgPool,
+    user_id: UserId,
+) -> Result<Vec<(Attachment, String)>, String> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            id, subscription_id, payment_date, file_name, content_type, size_bytes, created_at,
+            blob_key
+        FROM attachments
+        WHERE user_id = $1
+        ORDER BY created_at, id
+        "#,
+        Uuid::from(user_id)
+    )
+    .fetch_all(database)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let attachment = AttachmentRow {
+                id: row.id,
+                subscription_id: row.subscription_id,
+                payment_date: row.payment_date,
+                file_name: row.file_name,
+                content_type: row.content_type,
+                size_bytes: row.size_bytes,
+                created_at: row.created_at,
+            };
+            (attachment.into(), row.blob_key)
+        })
+        .collect())
+}
+
+/// Files are grouped by user so that all of them can be removed together
+fn attachment_key(user_id: UserId, attachment_id: Uuid) -> String {
+    format!("attachments/{}/{}", Uuid::from(user_id), attachment_id)
+}
+
+/// Saves the attachment unless it exceeds the storage quota of the user
+async fn save_attachment(
+    database: &sqlx::PgPool,
+    user_id: UserId,
+    subscription_id: SubscriptionId,
+    attachment_id: Uuid,
+    attachment: &NewAttachment,
+    size: usize,
+    blob_key: &str,
+) -> Result<Attachment, (StatusCode, String)> {
+    let internal_error = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    let mut transaction = database.begin().await.map_err(internal_error)?;
+
+    // Concurrent uploads of the same user wait for each other so that both count
+    sqlx::query!(
+        "SELECT id FROM users WHERE id = $1 FOR UPDATE",
+        Uuid::from(user_id)
+    )
+    .fetch_one(&mut *transaction)
+    .await
+    .map_err(internal_error)?;
+    let used = sqlx::query_scalar!(
+        r#"SELECT COALESCE(SUM(size_bytes), 0)::bigint AS "used!" FROM attachments WHERE user_id = $1"#,
+        Uuid::from(user_id)
+    )
+    .fetch_one(&mut *transaction)
+    .await
+    .map_err(internal_error)?;
+    ensure_attachment_quota(used, size).map_err(|e| (StatusCode::PAYLOAD_TOO_LARGE, e))?;
+
+    let row = sqlx::query_as!(
+        AttachmentRow,
+        r#"
+        INSERT INTO attachments (
+            id, user_id, subscription_id, payment_date, file_name, content_type, size_bytes,
+            blob_key
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING id, subscription_id, payment_date, file_name, content_type, size_bytes, created_at
+        "#,
+        attachment_id,
+        Uuid::from(user_id),
+        Uuid::from(subscription_id),
+        attachment.payment_date,
+        attachment.file_name,
+        attachment.content_type,
+        size as i64,
+        blob_key
+    )
+    .fetch_one(&mut *transaction)
+    .await
+    .map_err(internal_error)?;
+    transaction.commit().await.map_err(internal_error)?;
+
+    Ok(row.into())
+}
+
+/// Offers the file for download under its name, non-ASCII names are percent-encoded as in
+/// RFC 6266 with an ASCII fallback for old clients
+fn content_disposition(file_name: &str) -> String {
+    let fallback: String = file_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii() && c != '"' && c != '\\' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let encoded: String = file_name
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'.' | b'-' | b'_' => {
+                (byte as char).to_string()
+            }
+            byte => format!("%{:02X}", byte),
+        })
+        .collect();
+
+    format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+        fallback, encoded
+    )
+}
@@ -5,7 +5,7 @@ use sqlx::PgPool;
 
 use crate::{
     auth::AuthContext,
-    domain::{Password, User},
+    domain::{CurrencyCode, Locale, Password, User},
     startup::AppState,
 };
 
@@ -17,7 +17,7 @@ pub struct LoginInput {
 
 #[axum::debug_handler(state = crate::startup::AppState)]
 pub(crate) async fn login_handler(
-    State(AppState { database }): State<AppState>,
+    State(AppState { database, .. }): State<AppState>,
     mut auth: AuthContext,
     Json(input): Json<LoginInput>,
 ) -> Result<Json<User>, StatusCode> {
@@ -71,7 +71,7 @@ impl TryFrom<RegisterInput> for NewUser {
 }
 
 pub(crate) async fn register_handler(
-    State(AppState { database }): State<AppState>,
+    State(AppState { database, .. }): State<AppState>,
     mut auth: AuthContext,
     Json(input): Json<RegisterInput>,
 ) -> Result<Json<User>, StatusCode> {
@@ -106,10 +106,20 @@ pub(crate) async fn register_handler(
 }
 
 async fn find_user_by_login(db_pool: &PgPool, login: &str) -> Result<Option<User>, String> {
-    sqlx::query_as!(User, "SELECT * FROM users WHERE login = $1", login)
-        .fetch_optional(db_pool)
-        .await
-        .map_err(|e| e.to_string())
+    sqlx::query_as!(
+        User,
+        r#"
+        SELECT
+            id, password_hash, role, login, updated_at, created_at,
+            preferred_currency AS "preferred_currency: CurrencyCode", locale AS "locale: Locale",
+            unused_after_cycles
+        FROM users WHERE login = $1
+        "#,
+        login
+    )
+    .fetch_optional(db_pool)
+    .await
+    .map_err(|e| e.to_string())
 }
 
 async fn insert_user(pg_pool: &PgPool, new_user: NewUser) -> Result<User, String> {
@@ -118,16 +128,21 @@ async fn insert_user(pg_pool: &PgPool, new_user: NewUser) -> Result<User, String
         User,
         r#"
         INSERT INTO users 
-            (id, login, password_hash, role, created_at, updated_at) 
-        VALUES ($1, $2, $3, $4, $5, $6) 
-        RETURNING *
+            (id, login, password_hash, role, created_at, updated_at, preferred_currency, locale) 
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8) 
+        RETURNING
+            id, password_hash, role, login, updated_at, created_at,
+            preferred_currency AS "preferred_currency: CurrencyCode", locale AS "locale: Locale",
+            unused_after_cycles
         "#,
         Into::<uuid::Uuid>::into(user.id),
         user.login,
         user.password_hash.as_ref(),
         user.role.as_ref(),
         user.created_at,
-        user.updated_at
+        user.updated_at,
+        user.preferred_currency.as_ref(),
+        user.locale.as_ref()
     )
     .fetch_one(pg_pool)
     .await
@@ -0,0 +1,235 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use hyper::StatusCode;
+use iso_currency::Currency;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    auth::{current_user, AuthContext},
+    budget_alerts::check_budgets,
+    domain::{
+        Budget, BudgetStatus, Category, CurrencyCode, Money, NewBudget, UserId,
+        DEFAULT_BUDGET_THRESHOLDS,
+    },
+    exchange_rate_store::ExchangeRateStore,
+    startup::AppState,
+};
+
+use super::fetch_user_subscriptions;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetBudget {
+    /// Budget of a single category, the overall budget when missing
+    category: Option<String>,
+    /// Monthly limit in major units of the preferred currency, e.g. `50.00`
+    amount: String,
+    /// Percentages of the limit to be alerted at, 80% and 100% by default
+    thresholds: Option<Vec<u8>>,
+}
+
+impl SetBudget {
+    fn parse(self, currency: Currency) -> Result<NewBudget, String> {
+        let category = self.category.as_deref().map(Category::parse).transpose()?;
+        let limit = Money::parse(&self.amount, currency)?;
+        if limit.is_zero() {
+            return Err("Budget has to be greater than 0.".into());
+        }
+        let thresholds = Budget::parse_thresholds(
+            self.thresholds
+                .unwrap_or_else(|| DEFAULT_BUDGET_THRESHOLDS.to_vec()),
+        )?;
+
+        Ok(NewBudget {
+            category,
+            limit,
+            thresholds,
+        })
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BudgetResponse {
+    #[serde(flatten)]
+    budget: Budget,
+    /// Committed and remaining spend in the current month
+    status: BudgetStatus,
+}
+
+/// Raw `budgets` row
+pub(crate) struct BudgetRow {
+    id: Uuid,
+    category: Option<String>,
+    amount: i64,
+    currency: String,
+    thresholds: Vec<i16>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl TryFrom<BudgetRow> for Budget {
+    type Error = String;
+
+    fn try_from(row: BudgetRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.id.into(),
+            category: row.category.as_deref().map(Category::parse).transpose()?,
+            limit: Money::new(row.amount, *CurrencyCode::parse(&row.currency)?),
+            thresholds: row
+                .thresholds
+                .into_iter()
+                .map(|threshold| threshold.try_into().map_err(|_| "invalid threshold"))
+                .collect::<Result<_, _>>()?,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        })
+    }
+}
+
+/// Budgets of the user with their status in the current month
+#[tracing::instrument(name = "Budgets index", skip_all)]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn budgets_index(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+) -> Result<Json<Vec<BudgetResponse>>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let internal_error = |e: String| (StatusCode::INTERNAL_SERVER_ERROR, e);
+
+    let budgets = fetch_user_budgets(&database, user.id)
+        .await
+        .map_err(internal_error)?;
+    let subscriptions = fetch_user_subscriptions(&database, user.id)
+        .await
+        .map_err(internal_error)?;
+
+    let rates = ExchangeRateStore::new(database)
+        .load_for(
+            budgets.iter().map(|budget| budget.limit.currency()),
+            subscriptions.iter().map(|s| s.price.currency()),
+        )
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    let today = Utc::now().date_naive();
+    let budgets = budgets
+        .into_iter()
+        .map(|budget| {
+            let status = BudgetStatus::compute(&budget, &subscriptions, &rates, today)?;
+            Ok(BudgetResponse { budget, status })
+        })
+        .collect::<Result<_, String>>()
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+
+    Ok(Json(budgets))
+}
+
+/// Sets the budget of a category (or the overall one), replacing the previous one
+#[tracing::instrument(name = "Set budget", skip_all)]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn set_budget(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Json(input): Json<SetBudget>,
+) -> Result<Json<Budget>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let new_budget = input
+        .parse(*user.preferred_currency)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let budget = upsert_budget(&database, user.id, new_budget)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    if let Err(e) = check_budgets(&database, user.id, user.locale, None).await {
+        tracing::error!("Failed to check budget alerts: {}", e);
+    }
+
+    Ok(Json(budget))
+}
+
+#[tracing::instrument(name = "Delete budget", skip_all, fields(budget_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn delete_budget(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let user = current_user(auth)?;
+
+    let result = sqlx::query!(
+        "DELETE FROM budgets WHERE id = $1 AND user_id = $2",
+        id,
+        Into::<Uuid>::into(user.id)
+    )
+    .execute(&database)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Budget not found".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[tracing::instrument(name = "Fetching user budgets", skip_all)]
+pub(crate) async fn fetch_user_budgets(
+    database: &PgPool,
+    user_id: UserId,
+) -> Result<Vec<Budget>, String> {
+    sqlx::query_as!(
+        BudgetRow,
+        r#"
+        SELECT id, category, amount, currency, thresholds, created_at, updated_at
+        FROM budgets
+        WHERE user_id = $1
+        ORDER BY category NULLS FIRST
+        "#,
+        Into::<Uuid>::into(user_id)
+    )
+    .fetch_all(database)
+    .await
+    .map_err(|e| e.to_string())?
+    .into_iter()
+    .map(Budget::try_from)
+    .collect()
+}
+
+#[tracing::instrument(name = "Save budget in the database", skip_all)]
+async fn upsert_budget(
+    database: &PgPool,
+    user_id: UserId,
+    budget: NewBudget,
+) -> Result<Budget, String> {
+    let thresholds: Vec<i16> = budget.thresholds.into_iter().map(i16::from).collect();
+
+    sqlx::query_as!(
+        BudgetRow,
+        r#"
+        INSERT INTO budgets (id, user_id, category, amount, currency, thresholds)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (user_id, (COALESCE(category, ''))) DO UPDATE SET
+          amount = EXCLUDED.amount,
+          currency = EXCLUDED.currency,
+          thresholds = EXCLUDED.thresholds,
+          updated_at = now()
+        RETURNING id, category, amount, currency, thresholds, created_at, updated_at
+        "#,
+        Uuid::new_v4(),
+        Into::<Uuid>::into(user_id),
+        budget.category.as_ref().map(AsRef::<str>::as_ref),
+        budget.limit.minor_units(),
+        budget.limit.currency().code(),
+        &thresholds
+    )
+    .fetch_one(database)
+    .await
+    .map_err(|e| e.to_string())?
+    .try_into()
+}
@@ -0,0 +1,206 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use hyper::StatusCode;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    auth::{current_user, AuthContext},
+    domain::{CancellationGuide, NewCancellationGuide, ServiceId, ServiceUrl},
+    startup::AppState,
+};
+
+use super::{ensure_editable_service, ensure_visible_service};
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SaveCancellationGuide {
+    /// Defaults to the cancellation URL of the service
+    url: Option<String>,
+    #[serde(default)]
+    steps: Vec<String>,
+    notice_days: Option<u16>,
+    contact_email: Option<String>,
+    contact_phone: Option<String>,
+}
+
+struct CancellationGuideRow {
+    service_id: Uuid,
+    version: i32,
+    url: Option<String>,
+    steps: Vec<String>,
+    notice_days: Option<i16>,
+    contact_email: Option<String>,
+    contact_phone: Option<String>,
+    edited_by: Option<Uuid>,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl TryFrom<CancellationGuideRow> for CancellationGuide {
+    type Error = String;
+
+    fn try_from(row: CancellationGuideRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            service_id: row.service_id.into(),
+            version: row.version,
+            url: row.url,
+            steps: row.steps,
+            notice_days: row
+                .notice_days
+                .map(u16::try_from)
+                .transpose()
+                .map_err(|_| "invalid notice days")?,
+            contact_email: row.contact_email,
+            contact_phone: row.contact_phone,
+            edited_by: row.edited_by,
+            created_at: row.created_at,
+        })
+    }
+}
+
+/// Current version of the guide
+#[tracing::instrument(name = "Cancellation guide", skip_all, fields(service_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn cancellation_guide_handler(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+) -> Result<Json<CancellationGuide>, (StatusCode, String)> {
+    let service = ensure_visible_service(&database, id, auth.current_user.as_ref()).await?;
+
+    fetch_cancellation_guide(&database, service.id.into())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .map(Json)
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                "Service has no cancellation guide".to_string(),
+            )
+        })
+}
+
+/// All versions of the guide, newest first
+#[tracing::instrument(name = "Cancellation guide versions", skip_all, fields(service_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn cancellation_guide_versions(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<CancellationGuide>>, (StatusCode, String)> {
+    let service = ensure_visible_service(&database, id, auth.current_user.as_ref()).await?;
+
+    let guides = sqlx::query_as!(
+        CancellationGuideRow,
+        r#"
+        SELECT
+            service_id, version, url, steps, notice_days, contact_email, contact_phone,
+            edited_by, created_at
+        FROM service_cancellation_guides
+        WHERE service_id = $1
+        ORDER BY version DESC
+        "#,
+        service.id
+    )
+    .fetch_all(&database)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .into_iter()
+    .map(CancellationGuide::try_from)
+    .collect::<Result<_, _>>()
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok(Json(guides))
+}
+
+/// Saves the guide as a new version, earlier versions are kept
+#[tracing::instrument(name = "Save cancellation guide", skip_all, fields(service_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn save_cancellation_guide(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+    Json(input): Json<SaveCancellationGuide>,
+) -> Result<Json<CancellationGuide>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let bad_request = |e: String| (StatusCode::BAD_REQUEST, e);
+    let service = ensure_editable_service(&database, id, &user).await?;
+    let url = input
+        .url
+        .or(service.cancellation_url)
+        .as_deref()
+        .map(ServiceUrl::parse)
+        .transpose()
+        .map_err(bad_request)?;
+    let guide = NewCancellationGuide::parse(
+        url,
+        input.steps,
+        input.notice_days,
+        input.contact_email.as_deref(),
+        input.contact_phone.as_deref(),
+    )
+    .map_err(bad_request)?;
+
+    // The primary key rejects a concurrent edit of the same version
+    let row = sqlx::query_as!(
+        CancellationGuideRow,
+        r#"
+        INSERT INTO service_cancellation_guides (
+            service_id, version, url, steps, notice_days, contact_email, contact_phone, edited_by
+        )
+        SELECT $1, COALESCE(MAX(version), 0) + 1, $2, $3, $4, $5, $6, $7
+        FROM service_cancellation_guides
+        WHERE service_id = $1
+        RETURNING
+            service_id, version, url, steps, notice_days, contact_email, contact_phone,
+            edited_by, created_at
+        "#,
+        id,
+        guide.url.as_ref().map(AsRef::<str>::as_ref),
+        &guide.steps,
+        guide.notice_days.map(|days| days as i16),
+        guide.contact_email,
+        guide.contact_phone,
+        Uuid::from(user.id)
+    )
+    .fetch_one(&database)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(e) if e.is_unique_violation() => (
+            StatusCode::CONFLICT,
+            "Guide was changed in the meantime, try again".to_string(),
+        ),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    })?;
+
+    row.try_into()
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+/// Current version of the guide of the service, if it has one
+pub(crate) async fn fetch_cancellation_guide(
+    database: &PgPool,
+    service_id: ServiceId,
+) -> Result<Option<CancellationGuide>, String> {
+    sqlx::query_as!(
+        CancellationGuideRow,
+        r#"
+        SELECT
+            service_id, version, url, steps, notice_days, contact_email, contact_phone,
+            edited_by, created_at
+        FROM service_cancellation_guides
+        WHERE service_id = $1
+        ORDER BY version DESC
+        LIMIT 1
+        "#,
+        Uuid::from(service_id)
+    )
+    .fetch_optional(database)
+    .await
+    .map_err(|e| e.to_string())?
+    .map(CancellationGuide::try_from)
+    .transpose()
+}
@@ -0,0 +1,418 @@
+use axum::{
+    body::{boxed, Body, Bytes},
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use chrono::{Months, NaiveDate, Utc};
+use hyper::{body::Sender, StatusCode};
+use iso_currency::Currency;
+use sqlx::PgPool;
+use tokio::{io::AsyncReadExt, sync::mpsc};
+use uuid::Uuid;
+
+use crate::{
+    auth::{current_user, AuthContext},
+    domain::{ExchangeRates, Subscription, SubscriptionId, User, UserId},
+    exchange_rate_store::ExchangeRateStore,
+    export::{
+        archive_path, ArchiveWriter, AttachmentRecord, ChunkEncoder, ExportFormat, ExportRecord,
+        PaymentRecord, SubscriptionRecord, XlsxWriter,
+    },
+    startup::AppState,
+};
+
+use super::{fetch_user_attachments, fetch_user_subscriptions_after};
+
+/// Subscriptions loaded from the database at a time
+const EXPORT_BATCH_SIZE: i64 = 100;
+/// Payments are forecast at most this far into the future
+const MAX_FORECAST_MONTHS: u32 = 10 * 12;
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ExportQuery {
+    format: ExportFormat,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct PaymentRange {
+    /// First day of the payments, from the start of every subscription when missing
+    from: Option<NaiveDate>,
+    /// Last day of the payments, today when missing
+    to: Option<NaiveDate>,
+}
+
+type Batch<R> = Result<Vec<R>, String>;
+/// Path of a file in the archive with its content
+type ArchiveFile = Result<(String, Vec<u8>), String>;
+type TempFile = std::io::BufWriter<std::fs::File>;
+
+/// All subscriptions of the user, amounts are converted at the latest rate
+#[tracing::instrument(name = "Export subscriptions", skip_all, fields(format = ?query.format))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn export_subscriptions(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let currency = *user.preferred_currency;
+    let rates = load_export_rates(&database, &user).await?;
+
+    let (sender, receiver) = mpsc::channel(2);
+    tokio::spawn(send_batches(
+        database,
+        user.id,
+        sender,
+        move |subscription| {
+            Ok(vec![SubscriptionRecord::new(
+                subscription,
+                &rates,
+                currency,
+            )])
+        },
+    ));
+
+    Ok(export_response(query.format, receiver))
+}
+
+/// Charges of all subscriptions within the range, amounts are converted at the rate of the day
+/// they were paid on
+#[tracing::instrument(name = "Export payments", skip_all, fields(format = ?query.format))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn export_payments(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Query(query): Query<ExportQuery>,
+    Query(range): Query<PaymentRange>,
+) -> Result<Response, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let currency = *user.preferred_currency;
+    let today = Utc::now().date_naive();
+    let from = range.from.unwrap_or(NaiveDate::MIN);
+    let to = range.to.unwrap_or(today);
+    if to < from {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "The range cannot end before it starts.".to_string(),
+        ));
+    }
+    if today
+        .checked_add_months(Months::new(MAX_FORECAST_MONTHS))
+        .is_some_and(|max| to > max)
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Payments can be exported at most {} years ahead.",
+                MAX_FORECAST_MONTHS / 12
+            ),
+        ));
+    }
+    // Charges are looked up within `[from, to)`, the last day is included
+    let to = to.succ_opt().unwrap_or(to);
+    let rates = load_export_rates(&database, &user).await?;
+
+    let (sender, receiver) = mpsc::channel(2);
+    tokio::spawn(send_batches(
+        database,
+        user.id,
+        sender,
+        move |subscription| PaymentRecord::of(subscription, from, to, &rates, currency),
+    ));
+
+    Ok(export_response(query.format, receiver))
+}
+
+/// Details of all attachments of the user, the files themselves are exported by
+/// [`export_attachment_files`] under the listed paths
+#[tracing::instrument(name = "Export attachments", skip_all, fields(format = ?query.format))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn export_attachments(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let attachments = fetch_user_attachments(&database, user.id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let records = attachments
+        .iter()
+        .map(|(attachment, _)| AttachmentRecord::new(attachment))
+        .collect();
+
+    // The details are small enough to be sent as a single batch
+    let (sender, receiver) = mpsc::channel(1);
+    sender
+        .send(Ok(records))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(export_response(query.format, receiver))
+}
+
+/// Files of all attachments of the user in a ZIP archive
+#[tracing::instrument(name = "Export attachment files", skip_all)]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn export_attachment_files(
+    State(AppState { database, blobs }): State<AppState>,
+    auth: AuthContext,
+) -> Result<Response, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let attachments = fetch_user_attachments(&database, user.id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    let (files_sender, files) = mpsc::channel::<ArchiveFile>(2);
+    tokio::spawn(async move {
+        for (attachment, blob_key) in attachments {
+            let file = match blobs.get(&blob_key).await {
+                Ok(Some(bytes)) => Ok((archive_path(&attachment), bytes)),
+                Ok(None) => {
+                    tracing::warn!(
+                        "File of attachment {} is missing",
+                        Uuid::from(attachment.id)
+                    );
+                    continue;
+                }
+                Err(e) => Err(e),
+            };
+            let is_failed = file.is_err();
+            if files_sender.send(file).await.is_err() || is_failed {
+                return;
+            }
+        }
+    });
+
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        if let Err(e) = send_archive(files, &mut sender).await {
+            tracing::error!("Failed to export attachment files: {}", e);
+            sender.abort();
+        }
+    });
+
+    Ok(file_response(
+        "application/zip",
+        &format!("attachments-{}.zip", Utc::now().date_naive()),
+        body,
+    ))
+}
+
+/// Rates between the currencies of the subscriptions and the preferred currency of the user
+async fn load_export_rates(
+    database: &PgPool,
+    user: &User,
+) -> Result<ExchangeRates, (StatusCode, String)> {
+    let codes = sqlx::query_scalar!(
+        "SELECT DISTINCT currency FROM subscriptions WHERE user_id = $1 AND deleted_at IS NULL",
+        Uuid::from(user.id)
+    )
+    .fetch_all(database)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    ExchangeRateStore::new(database.clone())
+        .load_for(
+            [*user.preferred_currency],
+            codes.iter().filter_map(|code| Currency::from_code(code)),
+        )
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Loads the subscriptions in batches and sends their records until all are sent or the client
+/// is gone
+async fn send_batches<R: ExportRecord>(
+    database: PgPool,
+    user_id: UserId,
+    sender: mpsc::Sender<Batch<R>>,
+    records: impl Fn(&Subscription) -> Result<Vec<R>, String>,
+) {
+    let mut after: Option<SubscriptionId> = None;
+    loop {
+        let subscriptions = match fetch_user_subscriptions_after(
+            &database,
+            user_id,
+            after,
+            EXPORT_BATCH_SIZE,
+        )
+        .await
+        {
+            Ok(subscriptions) => subscriptions,
+            Err(e) => {
+                let _ = sender.send(Err(e)).await;
+                return;
+            }
+        };
+        let Some(last) = subscriptions.last() else {
+            return;
+        };
+        after = Some(last.id);
+        let batch = subscriptions
+            .iter()
+            .map(&records)
+            .collect::<Result<Vec<_>, _>>()
+            .map(|records| records.into_iter().flatten().collect());
+        let failed = batch.is_err();
+        if sender.send(batch).await.is_err()
+            || failed
+            || subscriptions.len() < EXPORT_BATCH_SIZE as usize
+        {
+            return;
+        }
+    }
+}
+
+/// Streams the file while the records come in, a failure midway aborts the response so that
+/// clients do not take a partial export for a complete one
+fn export_response<R: ExportRecord>(
+    format: ExportFormat,
+    receiver: mpsc::Receiver<Batch<R>>,
+) -> Response {
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        let result = match ChunkEncoder::start::<R>(format) {
+            Some((encoder, start)) => send_chunks(encoder, start, receiver, &mut sender).await,
+            None => send_xlsx(receiver, &mut sender).await,
+        };
+        if let Err(e) = result {
+            tracing::error!("Failed to export {}: {}", R::NAME, e);
+            sender.abort();
+        }
+    });
+
+    let file_name = format!(
+        "{}-{}.{}",
+        R::NAME,
+        Utc::now().date_naive(),
+        format.extension()
+    );
+    file_response(format.content_type(), &file_name, body)
+}
+
+fn file_response(content_type: &str, file_name: &str, body: Body) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", file_name),
+            ),
+        ],
+        boxed(body),
+    )
+        .into_response()
+}
+
+async fn send_chunks<R: ExportRecord>(
+    mut encoder: ChunkEncoder,
+    start: Vec<u8>,
+    mut receiver: mpsc::Receiver<Batch<R>>,
+    sender: &mut Sender,
+) -> Result<(), String> {
+    send(sender, start).await?;
+    while let Some(records) = receiver.recv().await {
+        send(sender, encoder.encode(&records?)?).await?;
+    }
+
+    send(sender, encoder.finish()).await
+}
+
+/// Writes the workbook into a temporary file first, see [`XlsxWriter`]
+async fn send_xlsx<R: ExportRecord>(
+    mut receiver: mpsc::Receiver<Batch<R>>,
+    sender: &mut Sender,
+) -> Result<(), String> {
+    let write = move |file| {
+        let mut writer = XlsxWriter::new(file, R::NAME, R::HEADERS)?;
+        while let Some(records) = receiver.blocking_recv() {
+            for record in records? {
+                writer.write_row(&record.cells())?;
+            }
+        }
+        writer.finish()
+    };
+
+    send_temp_file(write, sender).await
+}
+
+/// Writes the archive into a temporary file first, see [`ArchiveWriter`]
+async fn send_archive(
+    mut files: mpsc::Receiver<ArchiveFile>,
+    sender: &mut Sender,
+) -> Result<(), String> {
+    let write = move |file| {
+        let mut writer = ArchiveWriter::new(file);
+        while let Some(file) = files.blocking_recv() {
+            let (path, bytes) = file?;
+            writer.add_file(&path, &bytes)?;
+        }
+        writer.finish()
+    };
+
+    send_temp_file(write, sender).await
+}
+
+/// Sends the file once `write` completed it, ZIP based formats seek back while being written so
+/// they cannot be streamed directly
+async fn send_temp_file(
+    write: impl FnOnce(TempFile) -> Result<TempFile, String> + Send + 'static,
+    sender: &mut Sender,
+) -> Result<(), String> {
+    const CHUNK_SIZE: usize = 64 * 1024;
+
+    let path = std::env::temp_dir().join(format!("recurio-export-{}", Uuid::new_v4()));
+    let file_path = path.clone();
+    let written = tokio::task::spawn_blocking(move || -> Result<(), String> {
+        let file = std::fs::File::create(&file_path).map_err(|e| e.to_string())?;
+        write(std::io::BufWriter::new(file))?
+            .into_inner()
+            .map_err(|e| e.to_string())?
+            .sync_all()
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|written| written);
+
+    let sent = match written {
+        Ok(()) => send_file(&path, CHUNK_SIZE, sender).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = tokio::fs::remove_file(&path).await {
+        tracing::warn!("Failed to remove the export file {}: {}", path.display(), e);
+    }
+
+    sent
+}
+
+async fn send_file(
+    path: &std::path::Path,
+    chunk_size: usize,
+    sender: &mut Sender,
+) -> Result<(), String> {
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut buffer = vec![0; chunk_size];
+    loop {
+        let read = file.read(&mut buffer).await.map_err(|e| e.to_string())?;
+        if read == 0 {
+            return Ok(());
+        }
+        send(sender, buffer[..read].to_vec()).await?;
+    }
+}
+
+async fn send(sender: &mut Sender, chunk: Vec<u8>) -> Result<(), String> {
+    if chunk.is_empty() {
+        return Ok(());
+    }
+
+    sender
+        .send_data(Bytes::from(chunk))
+        .await
+        .map_err(|e| e.to_string())
+}
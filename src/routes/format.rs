@@ -0,0 +1,6 @@
+/// `?formatted=true` adds amounts and dates formatted for the user's locale next to the raw values
+#[derive(Debug, Default, serde::Deserialize)]
+pub(crate) struct FormatOptions {
+    #[serde(default)]
+    pub formatted: bool,
+}
@@ -0,0 +1,362 @@
+use std::fmt;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::{Postgres, QueryBuilder};
+use uuid::Uuid;
+
+/// Number of items returned when the request does not ask for a specific `limit`
+pub(crate) const DEFAULT_PAGE_SIZE: u32 = 50;
+pub(crate) const MAX_PAGE_SIZE: u32 = 200;
+
+/// Column a list can be sorted by. Every key maps to a non-nullable SQL expression, ties are
+/// broken by the ID of the row so that pagination stays stable.
+pub(crate) trait SortKey: Copy + PartialEq + Sized {
+    fn parse(name: &str) -> Result<Self, String>;
+    /// Name of the key as used in the `sort` query parameter
+    fn name(&self) -> &'static str;
+    /// SQL expression sorted by, never built from user input
+    fn column(&self) -> &'static str;
+    fn value_type(&self) -> SortValueType;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SortValueType {
+    Text,
+    Integer,
+    Date,
+    Timestamp,
+}
+
+/// Value of the sort key of the last item on a page
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) enum SortValue {
+    Text(String),
+    Integer(i64),
+    Date(NaiveDate),
+    Timestamp(DateTime<Utc>),
+}
+
+impl SortValue {
+    fn value_type(&self) -> SortValueType {
+        match self {
+            Self::Text(_) => SortValueType::Text,
+            Self::Integer(_) => SortValueType::Integer,
+            Self::Date(_) => SortValueType::Date,
+            Self::Timestamp(_) => SortValueType::Timestamp,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SortDirection {
+    Asc,
+    Desc,
+}
+
+/// Sort order given as `?sort=amount` (ascending) or `?sort=-amount` (descending)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Sort<K> {
+    pub key: K,
+    pub direction: SortDirection,
+}
+
+impl<K: SortKey> Sort<K> {
+    pub fn asc(key: K) -> Self {
+        Self {
+            key,
+            direction: SortDirection::Asc,
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        match value.strip_prefix('-') {
+            Some(key) => Ok(Self {
+                key: K::parse(key)?,
+                direction: SortDirection::Desc,
+            }),
+            None => Ok(Self::asc(K::parse(value)?)),
+        }
+    }
+}
+
+impl<K: SortKey> fmt::Display for Sort<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.direction {
+            SortDirection::Asc => write!(f, "{}", self.key.name()),
+            SortDirection::Desc => write!(f, "-{}", self.key.name()),
+        }
+    }
+}
+
+/// Position after which the next page starts, opaque to clients
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct Cursor {
+    /// Sort order of the list the cursor was created for
+    sort: String,
+    value: SortValue,
+    id: Uuid,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).expect("cursor is always serializable");
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    pub fn decode(value: &str) -> Result<Self, String> {
+        let invalid = || "Invalid cursor.".to_string();
+        let json = URL_SAFE_NO_PAD.decode(value).map_err(|_| invalid())?;
+        serde_json::from_slice(&json).map_err(|_| invalid())
+    }
+}
+
+/// Pagination parameters shared by list endpoints
+#[derive(Debug, Default, serde::Deserialize)]
+pub(crate) struct PageParams {
+    limit: Option<u32>,
+    cursor: Option<String>,
+    sort: Option<String>,
+}
+
+/// Validated [`PageParams`]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ListQuery<K> {
+    pub sort: Sort<K>,
+    pub limit: u32,
+    pub after: Option<(SortValue, Uuid)>,
+}
+
+impl<K: SortKey> ListQuery<K> {
+    pub fn parse(params: PageParams, default_sort: Sort<K>) -> Result<Self, String> {
+        let sort = params
+            .sort
+            .as_deref()
+            .map(Sort::parse)
+            .transpose()?
+            .unwrap_or(default_sort);
+
+        let limit = params.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+        if limit == 0 || limit > MAX_PAGE_SIZE {
+            return Err(format!("Limit has to be between 1 and {}.", MAX_PAGE_SIZE));
+        }
+
+        let after = match params.cursor.as_deref().map(Cursor::decode).transpose()? {
+            None => None,
+            Some(cursor) if cursor.sort != sort.to_string() => {
+                return Err("Cursor belongs to a list with a different sort order.".into())
+            }
+            Some(cursor) if cursor.value.value_type() != sort.key.value_type() => {
+                return Err("Invalid cursor.".into())
+            }
+            Some(cursor) => Some((cursor.value, cursor.id)),
+        };
+
+        Ok(Self { sort, limit, after })
+    }
+
+    /// Appends `AND (<sort column>, <id>) > (<cursor>)` (or `<` when descending) when continuing
+    /// after a cursor, the query has to have a `WHERE` clause already
+    pub fn push_keyset(&self, builder: &mut QueryBuilder<'_, Postgres>, id_column: &str) {
+        let Some((value, id)) = &self.after else {
+            return;
+        };
+        let operator = match self.sort.direction {
+            SortDirection::Asc => ">",
+            SortDirection::Desc => "<",
+        };
+
+        builder.push(format_args!(
+            " AND ({}, {}) {} (",
+            self.sort.key.column(),
+            id_column,
+            operator
+        ));
+        match value.clone() {
+            SortValue::Text(value) => builder.push_bind(value),
+            SortValue::Integer(value) => builder.push_bind(value),
+            SortValue::Date(value) => builder.push_bind(value),
+            SortValue::Timestamp(value) => builder.push_bind(value),
+        };
+        builder.push(", ").push_bind(*id).push(")");
+    }
+
+    /// Appends `ORDER BY` and `LIMIT`, one extra row is fetched to find out whether there is
+    /// a next page
+    pub fn push_order_and_limit(&self, builder: &mut QueryBuilder<'_, Postgres>, id_column: &str) {
+        let direction = match self.sort.direction {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        };
+
+        builder
+            .push(format_args!(
+                " ORDER BY {} {}, {} {}",
+                self.sort.key.column(),
+                direction,
+                id_column,
+                direction
+            ))
+            .push(" LIMIT ")
+            .push_bind(i64::from(self.limit) + 1);
+    }
+
+    /// Trims the rows fetched with [`push_order_and_limit`](ListQuery::push_order_and_limit)
+    /// to a page, `position` returns the sort value and ID of an item
+    pub fn page<T>(
+        &self,
+        mut items: Vec<T>,
+        position: impl Fn(&T) -> (SortValue, Uuid),
+    ) -> Page<T> {
+        let has_more = items.len() > self.limit as usize;
+        items.truncate(self.limit as usize);
+
+        let next_cursor = has_more.then(|| items.last()).flatten().map(|item| {
+            let (value, id) = position(item);
+            Cursor {
+                sort: self.sort.to_string(),
+                value,
+                id,
+            }
+            .encode()
+        });
+
+        Page { items, next_cursor }
+    }
+}
+
+/// One page of a list, `nextCursor` is passed as `?cursor=` to get the following page
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    pub fn try_map<U, E>(self, f: impl FnMut(T) -> Result<U, E>) -> Result<Page<U>, E> {
+        Ok(Page {
+            items: self.items.into_iter().map(f).collect::<Result<_, _>>()?,
+            next_cursor: self.next_cursor,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+    use uuid::Uuid;
+
+    use super::{
+        Cursor, ListQuery, PageParams, Sort, SortDirection, SortKey, SortValue, SortValueType,
+    };
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum TestKey {
+        Name,
+        Amount,
+    }
+
+    impl SortKey for TestKey {
+        fn parse(name: &str) -> Result<Self, String> {
+            match name {
+                "name" => Ok(Self::Name),
+                "amount" => Ok(Self::Amount),
+                other => Err(format!("Cannot sort by {}.", other)),
+            }
+        }
+
+        fn name(&self) -> &'static str {
+            match self {
+                Self::Name => "name",
+                Self::Amount => "amount",
+            }
+        }
+
+        fn column(&self) -> &'static str {
+            self.name()
+        }
+
+        fn value_type(&self) -> SortValueType {
+            match self {
+                Self::Name => SortValueType::Text,
+                Self::Amount => SortValueType::Integer,
+            }
+        }
+    }
+
+    fn params(sort: Option<&str>, limit: Option<u32>, cursor: Option<String>) -> PageParams {
+        PageParams {
+            limit,
+            cursor,
+            sort: sort.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn leading_dash_sorts_descending() {
+        let sort = Sort::<TestKey>::parse("-amount").unwrap();
+        assert_eq!(sort.key, TestKey::Amount);
+        assert_eq!(sort.direction, SortDirection::Desc);
+        assert_eq!(sort.to_string(), "-amount");
+        assert_err!(Sort::<TestKey>::parse("color"));
+    }
+
+    #[test]
+    fn limit_is_bounded() {
+        let default = Sort::asc(TestKey::Name);
+        assert_err!(ListQuery::parse(params(None, Some(0), None), default));
+        assert_err!(ListQuery::parse(params(None, Some(1000), None), default));
+        assert_ok!(ListQuery::parse(params(None, Some(200), None), default));
+    }
+
+    #[test]
+    fn next_cursor_points_at_the_last_item_of_a_full_page() {
+        let query = ListQuery::parse(
+            params(Some("-amount"), Some(2), None),
+            Sort::asc(TestKey::Name),
+        )
+        .unwrap();
+        let ids: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+        let items = vec![(300, ids[0]), (200, ids[1]), (100, ids[2])];
+
+        let page = query.page(items, |(amount, id)| (SortValue::Integer(*amount), *id));
+
+        assert_eq!(page.items.len(), 2);
+        let cursor = Cursor::decode(&page.next_cursor.unwrap()).unwrap();
+        let next = ListQuery::parse(
+            params(Some("-amount"), Some(2), Some(cursor.encode())),
+            Sort::asc(TestKey::Name),
+        )
+        .unwrap();
+        assert_eq!(next.after, Some((SortValue::Integer(200), ids[1])));
+    }
+
+    #[test]
+    fn last_page_has_no_cursor() {
+        let query =
+            ListQuery::parse(params(None, Some(2), None), Sort::asc(TestKey::Amount)).unwrap();
+        let page = query.page(vec![(1, Uuid::new_v4())], |(amount, id)| {
+            (SortValue::Integer(*amount), *id)
+        });
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn cursor_of_a_different_sort_order_is_rejected() {
+        let cursor = Cursor {
+            sort: "name".into(),
+            value: SortValue::Text("Netflix".into()),
+            id: Uuid::new_v4(),
+        };
+        assert_err!(ListQuery::parse(
+            params(Some("amount"), None, Some(cursor.encode())),
+            Sort::asc(TestKey::Name)
+        ));
+        assert_err!(ListQuery::parse(
+            params(None, None, Some("not a cursor".into())),
+            Sort::asc(TestKey::Name)
+        ));
+    }
+}
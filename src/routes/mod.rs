@@ -1,7 +1,37 @@
+mod attachments;
 mod auth;
+mod budgets;
+mod cancellation_guides;
+mod exports;
+mod format;
 mod health_check;
+mod list_query;
+mod payment_methods;
+mod profile;
+mod service_logos;
+mod service_reviews;
 mod services;
+mod stats;
+mod subscription_imports;
+mod subscriptions;
+mod tags;
+mod usages;
 
+pub use attachments::*;
 pub use auth::*;
+pub use budgets::*;
+pub use cancellation_guides::*;
+pub use exports::*;
+pub use format::*;
 pub use health_check::*;
+pub use list_query::*;
+pub use payment_methods::*;
+pub use profile::*;
+pub use service_logos::*;
+pub use service_reviews::*;
 pub use services::*;
+pub use stats::*;
+pub use subscription_imports::*;
+pub use subscriptions::*;
+pub use tags::*;
+pub use usages::*;
@@ -0,0 +1,248 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use hyper::StatusCode;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    auth::{current_user, AuthContext},
+    domain::{
+        CardExpiry, LastFour, NewPaymentMethod, PaymentMethod, PaymentMethodId, PaymentMethodKind,
+        UserId,
+    },
+    startup::AppState,
+};
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentMethodInput {
+    label: String,
+    kind: PaymentMethodKind,
+    /// Last four digits of the card or account number
+    last_four: Option<String>,
+    expiry_month: Option<u8>,
+    expiry_year: Option<u16>,
+}
+
+impl TryFrom<PaymentMethodInput> for NewPaymentMethod {
+    type Error = String;
+
+    fn try_from(value: PaymentMethodInput) -> Result<Self, Self::Error> {
+        let expiry = match (value.expiry_month, value.expiry_year) {
+            (Some(month), Some(year)) => Some(CardExpiry::parse(month, year)?),
+            (None, None) => None,
+            _ => return Err("Expiry requires both a month and a year.".into()),
+        };
+        NewPaymentMethod::parse(&value.label, value.kind, value.last_four.as_deref(), expiry)
+    }
+}
+
+struct PaymentMethodRow {
+    id: Uuid,
+    label: String,
+    kind: String,
+    last_four: Option<String>,
+    expiry_month: Option<i16>,
+    expiry_year: Option<i16>,
+    created_at: DateTime<Utc>,
+}
+
+impl TryFrom<PaymentMethodRow> for PaymentMethod {
+    type Error = String;
+
+    fn try_from(row: PaymentMethodRow) -> Result<Self, Self::Error> {
+        let expiry = match (row.expiry_month, row.expiry_year) {
+            (Some(month), Some(year)) => Some(CardExpiry {
+                month: month.try_into().map_err(|_| "invalid expiry month")?,
+                year: year.try_into().map_err(|_| "invalid expiry year")?,
+            }),
+            _ => None,
+        };
+
+        Ok(Self {
+            id: row.id.into(),
+            label: row.label,
+            kind: row.kind.try_into()?,
+            last_four: row.last_four.map(LastFour::from),
+            expiry,
+            created_at: row.created_at,
+        })
+    }
+}
+
+#[tracing::instrument(name = "Payment methods index", skip_all)]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn payment_methods_index(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+) -> Result<Json<Vec<PaymentMethod>>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+
+    let payment_methods = fetch_user_payment_methods(&database, user.id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok(Json(payment_methods))
+}
+
+#[tracing::instrument(name = "Create payment method", skip_all, fields(label = %input.label))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn create_payment_method(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Json(input): Json<PaymentMethodInput>,
+) -> Result<Json<PaymentMethod>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let payment_method: NewPaymentMethod =
+        input.try_into().map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let row = sqlx::query_as!(
+        PaymentMethodRow,
+        r#"
+        INSERT INTO payment_methods (id, user_id, label, kind, last_four, expiry_month, expiry_year)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id, label, kind, last_four, expiry_month, expiry_year, created_at
+        "#,
+        Uuid::new_v4(),
+        Into::<Uuid>::into(user.id),
+        payment_method.label,
+        payment_method.kind.as_ref(),
+        payment_method.last_four.as_ref().map(AsRef::<str>::as_ref),
+        payment_method.expiry.map(|expiry| i16::from(expiry.month)),
+        payment_method.expiry.map(|expiry| expiry.year as i16)
+    )
+    .fetch_one(&database)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    row.try_into()
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+/// Replaces the details of the payment method, e.g. after a card got renewed
+#[tracing::instrument(name = "Update payment method", skip_all, fields(payment_method_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn update_payment_method(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+    Json(input): Json<PaymentMethodInput>,
+) -> Result<Json<PaymentMethod>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let payment_method: NewPaymentMethod =
+        input.try_into().map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let row = sqlx::query_as!(
+        PaymentMethodRow,
+        r#"
+        UPDATE payment_methods
+        SET label = $3, kind = $4, last_four = $5, expiry_month = $6, expiry_year = $7
+        WHERE id = $1 AND user_id = $2
+        RETURNING id, label, kind, last_four, expiry_month, expiry_year, created_at
+        "#,
+        id,
+        Into::<Uuid>::into(user.id),
+        payment_method.label,
+        payment_method.kind.as_ref(),
+        payment_method.last_four.as_ref().map(AsRef::<str>::as_ref),
+        payment_method.expiry.map(|expiry| i16::from(expiry.month)),
+        payment_method.expiry.map(|expiry| expiry.year as i16)
+    )
+    .fetch_optional(&database)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            "Payment method not found".to_string(),
+        )
+    })?;
+
+    row.try_into()
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+/// Deletes the payment method, subscriptions paid with it are left without one
+#[tracing::instrument(name = "Delete payment method", skip_all, fields(payment_method_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn delete_payment_method(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let user = current_user(auth)?;
+
+    let result = sqlx::query!(
+        "DELETE FROM payment_methods WHERE id = $1 AND user_id = $2",
+        id,
+        Into::<Uuid>::into(user.id)
+    )
+    .execute(&database)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if result.rows_affected() == 0 {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Payment method not found".to_string(),
+        ));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[tracing::instrument(name = "Fetching user payment methods", skip_all)]
+pub(crate) async fn fetch_user_payment_methods(
+    database: &PgPool,
+    user_id: UserId,
+) -> Result<Vec<PaymentMethod>, String> {
+    sqlx::query_as!(
+        PaymentMethodRow,
+        r#"
+        SELECT id, label, kind, last_four, expiry_month, expiry_year, created_at
+        FROM payment_methods
+        WHERE user_id = $1
+        ORDER BY lower(label), id
+        "#,
+        Into::<Uuid>::into(user_id)
+    )
+    .fetch_all(database)
+    .await
+    .map_err(|e| e.to_string())?
+    .into_iter()
+    .map(PaymentMethod::try_from)
+    .collect()
+}
+
+/// Checks that the payment method belongs to the user
+pub(crate) async fn ensure_user_payment_method(
+    database: &PgPool,
+    user_id: UserId,
+    payment_method_id: Option<PaymentMethodId>,
+) -> Result<(), (StatusCode, String)> {
+    let Some(payment_method_id) = payment_method_id else {
+        return Ok(());
+    };
+    let payment_methods = fetch_user_payment_methods(database, user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    match payment_methods
+        .iter()
+        .any(|payment_method| payment_method.id == payment_method_id)
+    {
+        true => Ok(()),
+        false => Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Payment method {} does not exist.",
+                Uuid::from(payment_method_id)
+            ),
+        )),
+    }
+}
@@ -0,0 +1,153 @@
+use axum::{extract::State, Json};
+use hyper::StatusCode;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    auth::{current_user, AuthContext},
+    domain::{CurrencyCode, Locale, User, UserId},
+    startup::AppState,
+};
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct UpdateProfile {
+    preferred_currency: Option<String>,
+    locale: Option<String>,
+    /// Billing cycles without use after which a subscription is flagged as unused
+    unused_after_cycles: Option<u8>,
+}
+
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn profile_handler(auth: AuthContext) -> Result<Json<User>, (StatusCode, String)> {
+    Ok(Json(current_user(auth)?))
+}
+
+#[tracing::instrument(name = "Update profile", skip_all)]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn update_profile(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Json(input): Json<UpdateProfile>,
+) -> Result<Json<User>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+
+    let preferred_currency = input
+        .preferred_currency
+        .as_deref()
+        .map(CurrencyCode::parse)
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?
+        .unwrap_or(user.preferred_currency);
+    let locale = input
+        .locale
+        .as_deref()
+        .map(Locale::parse)
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?
+        .unwrap_or(user.locale);
+    let unused_after_cycles = match input.unused_after_cycles {
+        Some(0) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Unused subscriptions have to be flagged after at least one cycle.".into(),
+            ))
+        }
+        Some(cycles) => cycles.into(),
+        None => user.unused_after_cycles,
+    };
+
+    let user = update_user_preferences(
+        &database,
+        user.id,
+        preferred_currency,
+        locale,
+        unused_after_cycles,
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok(Json(user))
+}
+
+/// Deletes the account with all its data. The files of the attachments are removed once nothing
+/// refers to them anymore.
+#[tracing::instrument(name = "Delete profile", skip_all)]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn delete_profile(
+    State(AppState { database, blobs }): State<AppState>,
+    mut auth: AuthContext,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let user_id = auth
+        .current_user
+        .as_ref()
+        .map(|user| user.id)
+        .ok_or_else(|| (StatusCode::UNAUTHORIZED, "Login required".to_string()))?;
+
+    let blob_keys = delete_user(&database, user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    auth.logout().await;
+    // Leftover files are not reachable anymore, they only take up disk space
+    for blob_key in blob_keys {
+        if let Err(e) = blobs.delete(&blob_key).await {
+            tracing::warn!("Failed to delete the attachment file {}: {}", blob_key, e);
+        }
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Deletes the user with everything they own, returns the keys of their attachment files
+#[tracing::instrument(name = "Delete user from the database", skip_all)]
+async fn delete_user(database: &PgPool, user_id: UserId) -> sqlx::Result<Vec<String>> {
+    let mut transaction = database.begin().await?;
+    // Uploads lock the user as well, files saved meanwhile are included in the keys
+    sqlx::query!(
+        "SELECT id FROM users WHERE id = $1 FOR UPDATE",
+        Uuid::from(user_id)
+    )
+    .fetch_one(&mut *transaction)
+    .await?;
+    let blob_keys = sqlx::query_scalar!(
+        "SELECT blob_key FROM attachments WHERE user_id = $1",
+        Uuid::from(user_id)
+    )
+    .fetch_all(&mut *transaction)
+    .await?;
+    sqlx::query!("DELETE FROM users WHERE id = $1", Uuid::from(user_id))
+        .execute(&mut *transaction)
+        .await?;
+    transaction.commit().await?;
+
+    Ok(blob_keys)
+}
+
+#[tracing::instrument(name = "Save user preferences in the database", skip_all)]
+async fn update_user_preferences(
+    database: &PgPool,
+    user_id: UserId,
+    preferred_currency: CurrencyCode,
+    locale: Locale,
+    unused_after_cycles: i16,
+) -> Result<User, String> {
+    sqlx::query_as!(
+        User,
+        r#"
+        UPDATE users
+        SET preferred_currency = $2, locale = $3, unused_after_cycles = $4, updated_at = now()
+        WHERE id = $1
+        RETURNING
+            id, password_hash, role, login, updated_at, created_at,
+            preferred_currency AS "preferred_currency: CurrencyCode", locale AS "locale: Locale",
+            unused_after_cycles
+        "#,
+        Into::<Uuid>::into(user_id),
+        preferred_currency.as_ref(),
+        locale.as_ref(),
+        unused_after_cycles
+    )
+    .fetch_one(database)
+    .await
+    .map_err(|e| e.to_string())
+}
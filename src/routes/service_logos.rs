@@ -0,0 +1,203 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+    Json,
+};
+use hyper::StatusCode;
+use uuid::Uuid;
+
+use crate::{
+    auth::{current_admin, AuthContext},
+    blob_store::BlobStore,
+    domain::{Service, ServiceStatus},
+    service_logos::{logo_key, normalize_logo, LOGO_SIZES, MAX_LOGO_BYTES},
+    startup::AppState,
+};
+
+use super::{ensure_editable_service, ensure_visible_service, fetch_service};
+
+#[derive(Debug, serde::Deserialize)]
+pub struct LogoQuery {
+    /// One of [`LOGO_SIZES`], the largest one when missing
+    size: Option<u32>,
+    /// Logo version the client expects, only URLs with the current one are cached for long
+    v: Option<i32>,
+}
+
+/// Serves the logo as PNG, see [`crate::service_logos`]
+#[tracing::instrument(name = "Service logo", skip_all, fields(service_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn service_logo_handler(
+    State(AppState { database, blobs }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+    Query(query): Query<LogoQuery>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+    const REVALIDATE: &str = "public, max-age=300";
+    // Logos of suggestions are only visible to their suggester and admins
+    const PRIVATE: &str = "private, max-age=300";
+
+    let size = query.size.unwrap_or(LOGO_SIZES[LOGO_SIZES.len() - 1]);
+    if !LOGO_SIZES.contains(&size) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Logos are available in the sizes {:?}", LOGO_SIZES),
+        ));
+    }
+    let service = ensure_visible_service(&database, id, auth.current_user.as_ref()).await?;
+    let not_found = || (StatusCode::NOT_FOUND, "Service has no logo".to_string());
+    let version = service.logo_version.ok_or_else(not_found)?;
+
+    let etag = format!("\"{}-{}-{}\"", service.id, version, size);
+    let cache_control = if service.status != ServiceStatus::Approved {
+        PRIVATE
+    } else if query.v == Some(version) {
+        IMMUTABLE
+    } else {
+        REVALIDATE
+    };
+    let is_cached = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.split(',').any(|tag| tag.trim() == etag));
+    if is_cached {
+        return Ok((
+            StatusCode::NOT_MODIFIED,
+            [
+                (header::ETAG, etag),
+                (header::CACHE_CONTROL, cache_control.into()),
+            ],
+        )
+            .into_response());
+    }
+
+    let logo = blobs
+        .get(&logo_key(service.id, version, size))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .ok_or_else(not_found)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "image/png".to_string()),
+            (header::ETAG, etag),
+            (header::CACHE_CONTROL, cache_control.into()),
+        ],
+        logo,
+    )
+        .into_response())
+}
+
+/// Replaces the logo with the PNG, JPEG or WebP image in the request body
+#[tracing::instrument(name = "Upload service logo", skip_all, fields(service_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn upload_service_logo(
+    State(AppState { database, blobs }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<Service>, (StatusCode, String)> {
+    let admin = current_admin(auth)?;
+    let internal_error = |e: String| (StatusCode::INTERNAL_SERVER_ERROR, e);
+    if body.len() > MAX_LOGO_BYTES {
+        return Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("Logos can be at most {} KiB.", MAX_LOGO_BYTES / 1024),
+        ));
+    }
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_owned();
+    let service = ensure_editable_service(&database, id, &admin).await?;
+
+    let logos = tokio::task::spawn_blocking(move || normalize_logo(&body, &content_type))
+        .await
+        .map_err(|e| internal_error(e.to_string()))?
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let version = service.logo_version.unwrap_or_default() + 1;
+    for (logo, size) in logos.into_iter().zip(LOGO_SIZES) {
+        blobs
+            .put(&logo_key(id, version, size), logo)
+            .await
+            .map_err(internal_error)?;
+    }
+
+    let result = sqlx::query!(
+        r#"
+        UPDATE services SET logo_version = $2
+        WHERE id = $1 AND logo_version IS NOT DISTINCT FROM $3
+        "#,
+        id,
+        version,
+        service.logo_version
+    )
+    .execute(&database)
+    .await;
+    match result {
+        Ok(result) if result.rows_affected() == 1 => {}
+        result => {
+            delete_logo_blobs(blobs.as_ref(), id, version).await;
+            return Err(match result {
+                Ok(_) => (
+                    StatusCode::CONFLICT,
+                    "Logo was changed in the meantime, try again".to_string(),
+                ),
+                Err(e) => internal_error(e.to_string()),
+            });
+        }
+    }
+    if let Some(previous) = service.logo_version {
+        delete_logo_blobs(blobs.as_ref(), id, previous).await;
+    }
+
+    fetch_service(&database, id)
+        .await
+        .map_err(internal_error)?
+        .map(Json)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Service not found".to_string()))
+}
+
+#[tracing::instrument(name = "Delete service logo", skip_all, fields(service_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn delete_service_logo(
+    State(AppState { database, blobs }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let admin = current_admin(auth)?;
+    ensure_editable_service(&database, id, &admin).await?;
+
+    let previous = sqlx::query_scalar!(
+        r#"
+        UPDATE services s SET logo_version = NULL
+        FROM services previous
+        WHERE s.id = $1 AND previous.id = s.id AND previous.logo_version IS NOT NULL
+        RETURNING previous.logo_version AS "logo_version!"
+        "#,
+        id
+    )
+    .fetch_optional(&database)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or_else(|| (StatusCode::NOT_FOUND, "Service has no logo".to_string()))?;
+    delete_logo_blobs(blobs.as_ref(), id, previous).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Removes all sizes of a logo version, failures are only logged as the logo is not referenced
+/// anymore
+pub(crate) async fn delete_logo_blobs(blobs: &dyn BlobStore, service_id: Uuid, version: i32) {
+    for size in LOGO_SIZES {
+        if let Err(e) = blobs.delete(&logo_key(service_id, version, size)).await {
+            tracing::warn!("Failed to delete a logo of service {}: {}", service_id, e);
+        }
+    }
+}
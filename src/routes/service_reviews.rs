@@ -0,0 +1,390 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use hyper::StatusCode;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    audit_log::{record_audit_event, AuditAction},
+    auth::{current_admin, AuthContext},
+    domain::{Locale, NewServiceAlias, Service, ServiceStatus},
+    notification_store::NotificationStore,
+    notifications::{Channel, Notification},
+    startup::AppState,
+};
+
+use super::{attach_plans, fetch_service, insert_service_aliases};
+
+#[derive(Debug, serde::Deserialize)]
+pub struct RejectService {
+    /// Explanation shown to the user who suggested the service
+    reason: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeService {
+    /// Approved service taking over the subscriptions, plans and aliases
+    into_service_id: Uuid,
+}
+
+/// Suggestions waiting for a review, oldest first
+#[tracing::instrument(name = "Service suggestions index", skip_all)]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn service_suggestions_index(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+) -> Result<Json<Vec<Service>>, (StatusCode, String)> {
+    current_admin(auth)?;
+    let internal_error = |e: String| (StatusCode::INTERNAL_SERVER_ERROR, e);
+
+    let mut services: Vec<Service> =
+        sqlx::query_as("SELECT * FROM services WHERE status = $1 ORDER BY created_at, id")
+            .bind(ServiceStatus::Pending.as_ref())
+            .fetch_all(&database)
+            .await
+            .map_err(|e| internal_error(e.to_string()))?;
+    attach_plans(&database, &mut services)
+        .await
+        .map_err(internal_error)?;
+
+    Ok(Json(services))
+}
+
+/// Publishes the suggestion in the catalog
+#[tracing::instrument(name = "Approve service", skip_all, fields(service_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn approve_service(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Service>, (StatusCode, String)> {
+    current_admin(auth)?;
+
+    let reviewed = sqlx::query!(
+        r#"
+        UPDATE services SET status = $2, reviewed_at = now()
+        WHERE id = $1 AND status = $3
+        RETURNING name, suggested_by
+        "#,
+        id,
+        ServiceStatus::Approved.as_ref(),
+        ServiceStatus::Pending.as_ref()
+    )
+    .fetch_optional(&database)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let Some(reviewed) = reviewed else {
+        return Err(not_pending(&database, id).await);
+    };
+
+    let notification = Notification::ServiceApproved {
+        service: reviewed.name,
+    };
+    notify_suggester(&database, reviewed.suggested_by, id, &notification).await;
+
+    reviewed_service(&database, id).await
+}
+
+/// Keeps the suggestion private to the user who made it
+#[tracing::instrument(name = "Reject service", skip_all, fields(service_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn reject_service(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+    Json(input): Json<RejectService>,
+) -> Result<Json<Service>, (StatusCode, String)> {
+    current_admin(auth)?;
+    let reason = input
+        .reason
+        .map(|reason| reason.trim().to_owned())
+        .filter(|reason| !reason.is_empty());
+
+    let reviewed = sqlx::query!(
+        r#"
+        UPDATE services SET status = $2, reviewed_at = now(), rejection_reason = $4
+        WHERE id = $1 AND status = $3
+        RETURNING name, suggested_by
+        "#,
+        id,
+        ServiceStatus::Rejected.as_ref(),
+        ServiceStatus::Pending.as_ref(),
+        reason
+    )
+    .fetch_optional(&database)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let Some(reviewed) = reviewed else {
+        return Err(not_pending(&database, id).await);
+    };
+
+    let notification = Notification::ServiceRejected {
+        service: reviewed.name,
+        reason,
+    };
+    notify_suggester(&database, reviewed.suggested_by, id, &notification).await;
+
+    reviewed_service(&database, id).await
+}
+
+/// Merges a duplicate (e.g. a suggestion) into an approved service which takes over its
+/// subscriptions, plans and aliases. The duplicate keeps pointing to the surviving service so
+/// that its ID still resolves, the surviving service is returned.
+#[tracing::instrument(name = "Merge service", skip_all, fields(service_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn merge_service(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+    Json(input): Json<MergeService>,
+) -> Result<Json<Service>, (StatusCode, String)> {
+    let admin = current_admin(auth)?;
+    let internal_error = |e: String| (StatusCode::INTERNAL_SERVER_ERROR, e);
+    let target = fetch_service(&database, input.into_service_id)
+        .await
+        .map_err(internal_error)?
+        .filter(|target| target.id != id && target.status == ServiceStatus::Approved)
+        .ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                "Services can only be merged into another approved service".to_string(),
+            )
+        })?;
+
+    let mut transaction = database
+        .begin()
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+    let merged = match merge_services(&mut transaction, id, target.id)
+        .await
+        .map_err(internal_error)?
+    {
+        MergeOutcome::Merged(merged) => merged,
+        MergeOutcome::SourceUnavailable => {
+            return Err(match fetch_service(&database, id).await {
+                Ok(Some(_)) => (
+                    StatusCode::CONFLICT,
+                    "Service was already merged".to_string(),
+                ),
+                Ok(None) => (StatusCode::NOT_FOUND, "Service not found".to_string()),
+                Err(e) => internal_error(e),
+            })
+        }
+        MergeOutcome::TargetNotApproved => {
+            return Err((
+                StatusCode::CONFLICT,
+                "Services can only be merged into another approved service".to_string(),
+            ))
+        }
+    };
+    record_audit_event(
+        &mut transaction,
+        admin.id,
+        AuditAction::ServiceMerged,
+        target.id,
+        serde_json::json!({
+            "mergedServiceId": id,
+            "mergedServiceName": merged.name,
+            "subscriptions": merged.subscriptions,
+            "plans": merged.plans,
+            "aliases": merged.aliases,
+        }),
+    )
+    .await
+    .map_err(|e| internal_error(e.to_string()))?;
+    transaction
+        .commit()
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    if merged.status == ServiceStatus::Pending {
+        let notification = Notification::ServiceMerged {
+            service: merged.name,
+            into: target.name.as_ref().to_owned(),
+        };
+        notify_suggester(&database, merged.suggested_by, id, &notification).await;
+    }
+
+    reviewed_service(&database, target.id).await
+}
+
+/// Duplicate service as it was before [`merge_services`] and the number of rows moved from it
+struct MergedService {
+    name: String,
+    status: ServiceStatus,
+    suggested_by: Option<Uuid>,
+    subscriptions: u64,
+    plans: u64,
+    aliases: u64,
+}
+
+/// Result of [`merge_services`], nothing is changed unless both services are still mergeable
+enum MergeOutcome {
+    Merged(MergedService),
+    /// Source does not exist or was merged already
+    SourceUnavailable,
+    /// Target was merged or otherwise left the approved state in the meantime
+    TargetNotApproved,
+}
+
+/// Moves everything of the `source` service to the `target` service, the name of the source
+/// becomes an alias of the target.
+async fn merge_services(
+    transaction: &mut Transaction<'_, Postgres>,
+    source: Uuid,
+    target: Uuid,
+) -> Result<MergeOutcome, String> {
+    // Both rows are locked in the same order by every merge so that concurrent merges of the
+    // same pair in opposite directions cannot deadlock
+    let rows = sqlx::query!(
+        r#"
+        SELECT id, name, status, suggested_by FROM services
+        WHERE id = ANY($1)
+        ORDER BY id
+        FOR UPDATE
+        "#,
+        &[source, target][..]
+    )
+    .fetch_all(&mut **transaction)
+    .await
+    .map_err(|e| e.to_string())?;
+    let Some(previous) = rows
+        .iter()
+        .find(|row| row.id == source && row.status != ServiceStatus::Merged.as_ref())
+    else {
+        return Ok(MergeOutcome::SourceUnavailable);
+    };
+    if !rows
+        .iter()
+        .any(|row| row.id == target && row.status == ServiceStatus::Approved.as_ref())
+    {
+        return Ok(MergeOutcome::TargetNotApproved);
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE services SET status = $3, merged_into = $2, reviewed_at = now()
+        WHERE id = $1
+        "#,
+        source,
+        target,
+        ServiceStatus::Merged.as_ref()
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|e| e.to_string())?;
+    // Keep redirects a single hop
+    sqlx::query!(
+        "UPDATE services SET merged_into = $2 WHERE merged_into = $1",
+        source,
+        target
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let subscriptions = sqlx::query!(
+        "UPDATE subscriptions SET service_id = $2, updated_at = now() WHERE service_id = $1",
+        source,
+        target
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|e| e.to_string())?
+    .rows_affected();
+    let plans = sqlx::query!(
+        "UPDATE service_plans SET service_id = $2, updated_at = now() WHERE service_id = $1",
+        source,
+        target
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|e| e.to_string())?
+    .rows_affected();
+
+    let mut names = sqlx::query_scalar!(
+        "DELETE FROM service_aliases WHERE service_id = $1 RETURNING alias",
+        source
+    )
+    .fetch_all(&mut **transaction)
+    .await
+    .map_err(|e| e.to_string())?;
+    names.insert(0, previous.name.clone());
+    // Names without any letter or digit cannot be searched for anyway
+    let aliases = names
+        .iter()
+        .filter_map(|name| NewServiceAlias::parse(name).ok())
+        .collect();
+    let aliases = insert_service_aliases(transaction, target, aliases)
+        .await?
+        .len() as u64;
+
+    Ok(MergeOutcome::Merged(MergedService {
+        name: previous.name.clone(),
+        status: previous.status.clone().try_into()?,
+        suggested_by: previous.suggested_by,
+        subscriptions,
+        plans,
+        aliases,
+    }))
+}
+
+async fn reviewed_service(
+    database: &PgPool,
+    id: Uuid,
+) -> Result<Json<Service>, (StatusCode, String)> {
+    fetch_service(database, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .map(Json)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Service not found".to_string()))
+}
+
+/// Error for a review of a service which does not exist or was reviewed already
+async fn not_pending(database: &PgPool, id: Uuid) -> (StatusCode, String) {
+    match fetch_service(database, id).await {
+        Ok(Some(_)) => (
+            StatusCode::CONFLICT,
+            "Service was already reviewed".to_string(),
+        ),
+        Ok(None) => (StatusCode::NOT_FOUND, "Service not found".to_string()),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+/// Tells the user who suggested the service about the review, failures are only logged as the
+/// review itself is saved already
+async fn notify_suggester(
+    database: &PgPool,
+    suggested_by: Option<Uuid>,
+    service_id: Uuid,
+    notification: &Notification,
+) {
+    let Some(user_id) = suggested_by else {
+        return;
+    };
+    let result = async {
+        let locale = sqlx::query_scalar!("SELECT locale FROM users WHERE id = $1", user_id)
+            .fetch_one(database)
+            .await
+            .map_err(|e| e.to_string())?;
+        NotificationStore::new(database.clone())
+            .enqueue_notification(
+                user_id.into(),
+                notification,
+                Locale::parse(&locale)?,
+                &[Channel::Email, Channel::Push],
+                &format!("service_review:{}", service_id),
+            )
+            .await
+            .map_err(|e| e.to_string())
+    }
+    .await;
+
+    if let Err(e) = result {
+        tracing::error!("Failed to notify about the service review: {}", e);
+    }
+}
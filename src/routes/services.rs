@@ -1,16 +1,105 @@
-use axum::{extract::State, Json};
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
 use hyper::StatusCode;
-use sqlx::PgPool;
+use sqlx::{PgPool, Postgres, QueryBuilder, Transaction};
 
 use crate::{
-    domain::{NewService, Service, ServiceName},
+    audit_log::{record_audit_event, AuditAction},
+    auth::{current_admin, current_user, AuthContext},
+    domain::{
+        normalize_name, rank_matches, BillingPeriodUnit, CancellationGuide, Category, CurrencyCode,
+        Locale, Money, NewService, NewServiceAlias, NewServicePlan, Region, SearchCandidate,
+        Service, ServiceAlias, ServiceId, ServiceMatch, ServiceName, ServicePlan, ServicePlanId,
+        ServiceStatus, ServiceUrl, User, UserId, UserRole, DEFAULT_SEARCH_LIMIT, MAX_SEARCH_LIMIT,
+    },
+    notification_store::NotificationStore,
+    notifications::{Channel, Notification},
     startup::AppState,
 };
 use uuid::Uuid;
 
+use super::{
+    delete_logo_blobs, fetch_cancellation_guide, ListQuery, Page, PageParams, Sort, SortKey,
+    SortValue, SortValueType,
+};
+
 #[derive(Debug, serde::Deserialize)]
 pub struct CreateService {
     name: String,
+    category: Option<String>,
+    #[serde(default)]
+    plans: Vec<CreateServicePlan>,
+    /// Other names of the service, e.g. `netflix.com`
+    #[serde(default)]
+    aliases: Vec<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateServicePlan {
+    name: String,
+    /// Price in major units
+    amount: String,
+    currency: String,
+    /// Country code the price applies to, everywhere when missing
+    region: Option<String>,
+    billing_period: u8,
+    billing_period_unit: BillingPeriodUnit,
+    /// Number of people who can use the plan, one when missing
+    seats: Option<u16>,
+}
+
+impl TryFrom<CreateServicePlan> for NewServicePlan {
+    type Error = String;
+
+    fn try_from(value: CreateServicePlan) -> Result<Self, Self::Error> {
+        let currency = CurrencyCode::parse(&value.currency)?;
+        let price = Money::parse(&value.amount, *currency)?;
+        let region = value.region.as_deref().map(Region::parse).transpose()?;
+
+        NewServicePlan::parse(
+            &value.name,
+            price,
+            region,
+            value.billing_period,
+            value.billing_period_unit,
+            value.seats.unwrap_or(1),
+        )
+    }
+}
+
+struct ServicePlanRow {
+    id: Uuid,
+    service_id: Uuid,
+    name: String,
+    amount: i64,
+    currency: String,
+    region: Option<String>,
+    billing_period: i16,
+    billing_period_unit: String,
+    seats: i16,
+}
+
+impl TryFrom<ServicePlanRow> for ServicePlan {
+    type Error = String;
+
+    fn try_from(row: ServicePlanRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.id.into(),
+            service_id: row.service_id.into(),
+            name: row.name,
+            price: Money::new(row.amount, *CurrencyCode::parse(&row.currency)?),
+            region: row.region.map(Region::from),
+            billing_period: row
+                .billing_period
+                .try_into()
+                .map_err(|_| "invalid billing period")?,
+            billing_period_unit: row.billing_period_unit.try_into()?,
+            seats: row.seats.try_into().map_err(|_| "invalid seats")?,
+        })
+    }
 }
 
 impl TryFrom<CreateService> for NewService {
@@ -18,57 +107,1034 @@ impl TryFrom<CreateService> for NewService {
 
     fn try_from(value: CreateService) -> Result<Self, Self::Error> {
         let name = ServiceName::parse(value.name)?;
+        let category = value
+            .category
+            .as_deref()
+            .map(Category::parse)
+            .transpose()?
+            .unwrap_or_default();
+        let plans = value
+            .plans
+            .into_iter()
+            .map(NewServicePlan::try_from)
+            .collect::<Result<_, _>>()?;
+        let aliases = value
+            .aliases
+            .iter()
+            .map(|alias| NewServiceAlias::parse(alias))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            name,
+            category,
+            plans,
+            aliases,
+        })
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ServiceSearch {
+    q: String,
+    /// Number of results, [`DEFAULT_SEARCH_LIMIT`] when missing
+    limit: Option<u8>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct CreateServiceAlias {
+    alias: String,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct ServiceFilter {
+    category: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ServiceSortKey {
+    Name,
+    Category,
+    CreatedAt,
+}
+
+impl SortKey for ServiceSortKey {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "name" => Ok(Self::Name),
+            "category" => Ok(Self::Category),
+            "createdAt" => Ok(Self::CreatedAt),
+            other => Err(format!("Services cannot be sorted by {}.", other)),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Category => "category",
+            Self::CreatedAt => "createdAt",
+        }
+    }
+
+    fn column(&self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Category => "category",
+            Self::CreatedAt => "created_at",
+        }
+    }
+
+    fn value_type(&self) -> SortValueType {
+        match self {
+            Self::Name | Self::Category => SortValueType::Text,
+            Self::CreatedAt => SortValueType::Timestamp,
+        }
+    }
+}
 
-        Ok(Self { name })
+impl ServiceSortKey {
+    fn value(&self, service: &Service) -> SortValue {
+        match self {
+            Self::Name => SortValue::Text(service.name.as_ref().to_owned()),
+            Self::Category => SortValue::Text(service.category.as_ref().to_owned()),
+            Self::CreatedAt => SortValue::Timestamp(service.created_at),
+        }
     }
 }
 
+/// Approved services together with the suggestions of the logged in user
 #[tracing::instrument(name = "services index", skip_all)]
 #[axum::debug_handler(state = crate::startup::AppState)]
-pub async fn services_index(
-    State(AppState { database }): State<AppState>,
-) -> Result<Json<Vec<Service>>, String> {
-    let services = fetch_all_services(database).await?;
+pub(crate) async fn services_index(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Query(filter): Query<ServiceFilter>,
+    Query(page): Query<PageParams>,
+) -> Result<Json<Page<Service>>, (StatusCode, String)> {
+    let category = filter
+        .category
+        .as_deref()
+        .map(Category::parse)
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let query = ListQuery::parse(page, Sort::asc(ServiceSortKey::Name))
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let viewer = auth.current_user.map(|user| user.id);
+    let services = list_services(&database, viewer, category, &query)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
     Ok(Json(services))
 }
 
+/// Services created by users are private suggestions until an admin reviews them, services
+/// created by admins are approved right away
 #[tracing::instrument(name = "Create service", skip_all, fields(service_name = %input.name))]
 #[axum::debug_handler(state = crate::startup::AppState)]
-pub async fn create_service(
-    State(AppState { database }): State<AppState>,
+pub(crate) async fn create_service(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
     Json(input): Json<CreateService>,
 ) -> Result<Json<Service>, (StatusCode, String)> {
+    let user = current_user(auth)?;
     let new_service: NewService = input.try_into().map_err(|e| (StatusCode::BAD_REQUEST, e))?;
 
-    let service = insert_service(database, new_service)
+    let service = insert_service(database, &user, new_service)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     Ok(Json(service))
 }
 
-#[tracing::instrument(name = "Fetching all services", skip_all)]
-async fn fetch_all_services(database: PgPool) -> Result<Vec<Service>, String> {
-    sqlx::query_as!(Service, "SELECT * FROM services")
-        .fetch_all(&database)
+/// Service together with the number of users subscribed to it and how to cancel
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceDetails {
+    #[serde(flatten)]
+    service: Service,
+    /// Users with an active subscription of the service
+    subscribers: i64,
+    cancellation_guide: Option<CancellationGuide>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateService {
+    name: Option<String>,
+    category: Option<String>,
+    /// An empty string removes the link
+    website_url: Option<String>,
+    cancellation_url: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteService {
+    /// Service taking over the subscriptions of the deleted one
+    reassign_to: Option<Uuid>,
+}
+
+/// Merged services resolve to the service they were merged into
+#[tracing::instrument(name = "Service details", skip_all, fields(service_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn service_handler(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ServiceDetails>, (StatusCode, String)> {
+    let service = ensure_visible_service(&database, id, auth.current_user.as_ref()).await?;
+
+    service_details(&database, service).await
+}
+
+/// Changes the given fields, admins edit any service and users their pending suggestions
+#[tracing::instrument(name = "Update service", skip_all, fields(service_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn update_service(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+    Json(input): Json<UpdateService>,
+) -> Result<Json<ServiceDetails>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let bad_request = |e: String| (StatusCode::BAD_REQUEST, e);
+    let name = input
+        .name
+        .map(ServiceName::parse)
+        .transpose()
+        .map_err(bad_request)?;
+    let category = input
+        .category
+        .as_deref()
+        .map(Category::parse)
+        .transpose()
+        .map_err(bad_request)?;
+    let website_url = parse_service_url(input.website_url).map_err(bad_request)?;
+    let cancellation_url = parse_service_url(input.cancellation_url).map_err(bad_request)?;
+    let service = ensure_editable_service(&database, id, &user).await?;
+
+    let name = name.unwrap_or(service.name);
+    let category = category.unwrap_or(service.category);
+    let website_url = match website_url {
+        Some(url) => url.map(|url| url.as_ref().to_owned()),
+        None => service.website_url,
+    };
+    let cancellation_url = match cancellation_url {
+        Some(url) => url.map(|url| url.as_ref().to_owned()),
+        None => service.cancellation_url,
+    };
+    sqlx::query!(
+        r#"
+        UPDATE services SET
+          name = $2, normalized_name = $3, category = $4, website_url = $5,
+          cancellation_url = $6, edited_at = now()
+        WHERE id = $1
+        "#,
+        id,
+        name.as_ref(),
+        normalize_name(name.as_ref()),
+        category.as_ref(),
+        website_url,
+        cancellation_url
+    )
+    .execute(&database)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let service = ensure_visible_service(&database, id, Some(&user)).await?;
+    service_details(&database, service).await
+}
+
+/// Deletes the service with its plans and aliases. Services still used by subscriptions or merged
+/// services can only be deleted when these are reassigned to another service.
+#[tracing::instrument(name = "Delete service", skip_all, fields(service_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn delete_service(
+    State(AppState { database, blobs }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+    Query(input): Query<DeleteService>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let internal_error = |e: String| (StatusCode::INTERNAL_SERVER_ERROR, e);
+    let service = ensure_editable_service(&database, id, &user).await?;
+    let target = match input.reassign_to {
+        Some(target_id) => {
+            let target = ensure_visible_service(&database, target_id, Some(&user)).await?;
+            if target.id == id || target.status != ServiceStatus::Approved {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "Subscriptions can only be reassigned to another approved service".to_string(),
+                ));
+            }
+            Some(target.id)
+        }
+        None => None,
+    };
+
+    let mut transaction = database
+        .begin()
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+    // Subscriptions created meanwhile wait for the row lock, so they are either counted below or
+    // fail on the removed service
+    sqlx::query!("SELECT id FROM services WHERE id = $1 FOR UPDATE", id)
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+    let reassigned = match target {
+        Some(target) => {
+            sqlx::query!(
+                "UPDATE services SET merged_into = $2 WHERE merged_into = $1",
+                id,
+                target
+            )
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| internal_error(e.to_string()))?;
+            sqlx::query!(
+                r#"
+                UPDATE subscriptions SET service_id = $2, service_plan_id = NULL, updated_at = now()
+                WHERE service_id = $1
+                "#,
+                id,
+                target
+            )
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| internal_error(e.to_string()))?
+            .rows_affected()
+        }
+        None => {
+            let subscriptions = sqlx::query_scalar!(
+                r#"SELECT COUNT(*) AS "count!" FROM subscriptions WHERE service_id = $1"#,
+                id
+            )
+            .fetch_one(&mut *transaction)
+            .await
+            .map_err(|e| internal_error(e.to_string()))?;
+            if subscriptions > 0 {
+                return Err((
+                    StatusCode::CONFLICT,
+                    format!(
+                        "Service is used by {} subscriptions, reassign them to another service first",
+                        subscriptions
+                    ),
+                ));
+            }
+            // Searches for merged services are redirected to this one, they would be lost
+            let merged = sqlx::query_scalar!(
+                r#"SELECT COUNT(*) AS "count!" FROM services WHERE merged_into = $1"#,
+                id
+            )
+            .fetch_one(&mut *transaction)
+            .await
+            .map_err(|e| internal_error(e.to_string()))?;
+            if merged > 0 {
+                return Err((
+                    StatusCode::CONFLICT,
+                    format!(
+                        "{} services were merged into this one, reassign them to another service first",
+                        merged
+                    ),
+                ));
+            }
+            0
+        }
+    };
+
+    sqlx::query!("DELETE FROM services WHERE id = $1", id)
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| match e.as_database_error() {
+            Some(error) if error.is_foreign_key_violation() => (
+                StatusCode::CONFLICT,
+                "Service is still in use, reassign its subscriptions to another service first"
+                    .to_string(),
+            ),
+            _ => internal_error(e.to_string()),
+        })?;
+    record_audit_event(
+        &mut transaction,
+        user.id,
+        AuditAction::ServiceDeleted,
+        id,
+        serde_json::json!({
+            "serviceName": service.name.as_ref(),
+            "reassignedTo": target,
+            "subscriptions": reassigned,
+        }),
+    )
+    .await
+    .map_err(|e| internal_error(e.to_string()))?;
+    transaction
+        .commit()
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| internal_error(e.to_string()))?;
+    if let Some(version) = service.logo_version {
+        delete_logo_blobs(blobs.as_ref(), id, version).await;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
 }
 
-#[tracing::instrument(name = "Save service in the database", skip_all)]
-async fn insert_service(database: PgPool, service: NewService) -> Result<Service, String> {
+/// `None` keeps the current URL, an empty string removes it
+fn parse_service_url(url: Option<String>) -> Result<Option<Option<ServiceUrl>>, String> {
+    url.map(|url| match url.trim() {
+        "" => Ok(None),
+        url => ServiceUrl::parse(url).map(Some),
+    })
+    .transpose()
+}
+
+async fn service_details(
+    database: &PgPool,
+    service: Service,
+) -> Result<Json<ServiceDetails>, (StatusCode, String)> {
+    let subscribers = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(DISTINCT user_id) AS "count!" FROM subscriptions
+        WHERE service_id = $1 AND cancelled_at IS NULL AND deleted_at IS NULL
+        "#,
+        service.id
+    )
+    .fetch_one(database)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let cancellation_guide = fetch_cancellation_guide(database, service.id.into())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok(Json(ServiceDetails {
+        service,
+        subscribers,
+        cancellation_guide,
+    }))
+}
+
+/// Services whose name or alias is similar to the query, for autocompletion while the user types
+#[tracing::instrument(name = "Search services", skip_all, fields(q = %search.q))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn search_services(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Query(search): Query<ServiceSearch>,
+) -> Result<Json<Vec<ServiceMatch>>, (StatusCode, String)> {
+    let limit = search.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+    if limit == 0 || limit > MAX_SEARCH_LIMIT {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Limit has to be between 1 and {}.", MAX_SEARCH_LIMIT),
+        ));
+    }
+    let query = normalize_name(&search.q);
+    if query.is_empty() {
+        return Ok(Json(vec![]));
+    }
+    let viewer = auth.current_user.map(|user| Uuid::from(user.id));
+
+    let candidates = find_search_candidates(&database, &query, viewer)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok(Json(rank_matches(candidates, limit.into())))
+}
+
+#[tracing::instrument(name = "Service aliases index", skip_all, fields(service_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn service_aliases_index(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<ServiceAlias>>, (StatusCode, String)> {
+    ensure_visible_service(&database, id, auth.current_user.as_ref()).await?;
+
+    let aliases = sqlx::query_as!(
+        ServiceAlias,
+        "SELECT id, service_id, alias FROM service_aliases WHERE service_id = $1 ORDER BY alias",
+        id
+    )
+    .fetch_all(&database)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(aliases))
+}
+
+/// Adds another name the service can be found by
+#[tracing::instrument(name = "Create service alias", skip_all, fields(service_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn create_service_alias(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+    Json(input): Json<CreateServiceAlias>,
+) -> Result<Json<ServiceAlias>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let alias = NewServiceAlias::parse(&input.alias).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    ensure_editable_service(&database, id, &user).await?;
+
+    let internal_error = |e: String| (StatusCode::INTERNAL_SERVER_ERROR, e);
+    let mut transaction = database
+        .begin()
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+    let mut aliases = insert_service_aliases(&mut transaction, id, vec![alias])
+        .await
+        .map_err(internal_error)?;
+    mark_service_edited(&mut *transaction, id)
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+    transaction
+        .commit()
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    aliases
+        .pop()
+        .map(Json)
+        .ok_or_else(|| (StatusCode::CONFLICT, "Alias already exists".to_string()))
+}
+
+#[tracing::instrument(name = "Delete service alias", skip_all, fields(alias_id = %alias_id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn delete_service_alias(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path((id, alias_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    ensure_editable_service(&database, id, &user).await?;
+
+    let result = sqlx::query!(
+        "DELETE FROM service_aliases WHERE id = $1 AND service_id = $2",
+        alias_id,
+        id
+    )
+    .execute(&database)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Alias not found".to_string()));
+    }
+    mark_service_edited(&database, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[tracing::instrument(name = "Service plans index", skip_all, fields(service_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn service_plans_index(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<ServicePlan>>, (StatusCode, String)> {
+    let service = ensure_visible_service(&database, id, auth.current_user.as_ref()).await?;
+
+    Ok(Json(service.plans))
+}
+
+/// Adds a known plan to the service, users can only add plans to their pending suggestions
+#[tracing::instrument(name = "Create service plan", skip_all, fields(service_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn create_service_plan(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+    Json(input): Json<CreateServicePlan>,
+) -> Result<Json<ServicePlan>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let new_plan: NewServicePlan = input.try_into().map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    ensure_editable_service(&database, id, &user).await?;
+
+    let internal_error = |e: String| (StatusCode::INTERNAL_SERVER_ERROR, e);
+    let mut transaction = database
+        .begin()
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+    let mut plans = insert_service_plans(&mut transaction, id.into(), vec![new_plan])
+        .await
+        .map_err(internal_error)?;
+    mark_service_edited(&mut *transaction, id)
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+    transaction
+        .commit()
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    plans
+        .pop()
+        .map(Json)
+        .ok_or_else(|| internal_error("Saved plan not found".into()))
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct UpdateServicePlan {
+    /// New price in major units of the plan currency
+    amount: String,
+}
+
+/// Changes the price of a plan, users on the plan paying a different price are offered the new
+/// one
+#[tracing::instrument(name = "Update service plan", skip_all, fields(plan_id = %plan_id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn update_service_plan(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path((id, plan_id)): Path<(Uuid, Uuid)>,
+    Json(input): Json<UpdateServicePlan>,
+) -> Result<Json<ServicePlan>, (StatusCode, String)> {
+    current_admin(auth)?;
+    let not_found = || (StatusCode::NOT_FOUND, "Plan not found".to_string());
+    let plan = fetch_service_plan(&database, plan_id.into())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .filter(|plan| Uuid::from(plan.service_id) == id)
+        .ok_or_else(not_found)?;
+    let price = Money::parse(&input.amount, plan.price.currency())
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    sqlx::query!(
+        "UPDATE service_plans SET amount = $2, updated_at = now() WHERE id = $1",
+        plan_id,
+        price.minor_units()
+    )
+    .execute(&database)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let plan = ServicePlan { price, ..plan };
+
+    match offer_plan_price(&database, &plan).await {
+        Ok(offered) => tracing::info!("Offered the new price to {} subscriptions", offered),
+        Err(e) => tracing::error!("Failed to offer the new plan price: {}", e),
+    }
+
+    Ok(Json(plan))
+}
+
+/// Known plans of the services, ordered by name within each service
+#[tracing::instrument(name = "Fetching service plans", skip(database))]
+pub(crate) async fn fetch_service_plans(
+    database: &PgPool,
+    service_ids: &[Uuid],
+) -> Result<Vec<ServicePlan>, String> {
+    sqlx::query_as!(
+        ServicePlanRow,
+        r#"
+        SELECT
+            id, service_id, name, amount, currency, region, billing_period, billing_period_unit,
+            seats
+        FROM service_plans
+        WHERE service_id = ANY($1)
+        ORDER BY service_id, name, currency, region NULLS LAST, created_at
+        "#,
+        service_ids
+    )
+    .fetch_all(database)
+    .await
+    .map_err(|e| e.to_string())?
+    .into_iter()
+    .map(ServicePlan::try_from)
+    .collect()
+}
+
+#[tracing::instrument(name = "Fetching service plan", skip(database))]
+pub(crate) async fn fetch_service_plan(
+    database: &PgPool,
+    id: ServicePlanId,
+) -> Result<Option<ServicePlan>, String> {
     sqlx::query_as!(
-        Service,
+        ServicePlanRow,
+        r#"
+        SELECT
+            id, service_id, name, amount, currency, region, billing_period, billing_period_unit,
+            seats
+        FROM service_plans
+        WHERE id = $1
+        "#,
+        Uuid::from(id)
+    )
+    .fetch_optional(database)
+    .await
+    .map_err(|e| e.to_string())?
+    .map(ServicePlan::try_from)
+    .transpose()
+}
+
+/// Notifies owners of active subscriptions on the plan which are charged a different price, every
+/// price is offered once. Returns the number of queued notifications.
+async fn offer_plan_price(database: &PgPool, plan: &ServicePlan) -> Result<usize, String> {
+    let subscriptions = sqlx::query!(
         r#"
-        INSERT INTO services(id, name)
-        VALUES ($1, $2)
-        RETURNING *
+        SELECT s.id, s.user_id, s.name, s.amount, users.locale
+        FROM subscriptions s
+        JOIN users ON users.id = s.user_id
+        WHERE s.service_plan_id = $1
+          AND s.currency = $2
+          AND s.amount <> $3
+          AND s.cancelled_at IS NULL
+          AND s.deleted_at IS NULL
+        "#,
+        Uuid::from(plan.id),
+        plan.price.currency().code(),
+        plan.price.minor_units()
+    )
+    .fetch_all(database)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let store = NotificationStore::new(database.clone());
+    let mut queued = 0;
+    for subscription in subscriptions {
+        let notification = Notification::PlanPriceChanged {
+            subscription: subscription.name,
+            plan: plan.name.clone(),
+            previous: Money::new(subscription.amount, plan.price.currency()),
+            price: plan.price,
+        };
+        let dedup_key = format!(
+            "plan_price:{}:{}",
+            subscription.id,
+            plan.price.minor_units()
+        );
+
+        let is_queued = store
+            .enqueue_notification(
+                subscription.user_id.into(),
+                &notification,
+                Locale::parse(&subscription.locale)?,
+                &[Channel::Email, Channel::Push],
+                &dedup_key,
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        queued += usize::from(is_queued);
+    }
+
+    Ok(queued)
+}
+
+/// Service with its plans regardless of its review status
+#[tracing::instrument(name = "Fetching service", skip(database))]
+pub(crate) async fn fetch_service(database: &PgPool, id: Uuid) -> Result<Option<Service>, String> {
+    let service: Option<Service> = sqlx::query_as("SELECT * FROM services WHERE id = $1")
+        .bind(id)
+        .fetch_optional(database)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let Some(mut service) = service else {
+        return Ok(None);
+    };
+    service.plans = fetch_service_plans(database, &[id]).await?;
+
+    Ok(Some(service))
+}
+
+/// Service the `user` is allowed to see, private suggestions of others are not found. IDs of
+/// merged services resolve to the service they were merged into.
+pub(crate) async fn ensure_visible_service(
+    database: &PgPool,
+    id: Uuid,
+    user: Option<&User>,
+) -> Result<Service, (StatusCode, String)> {
+    let internal_error = |e: String| (StatusCode::INTERNAL_SERVER_ERROR, e);
+    let mut service = fetch_service(database, id).await.map_err(internal_error)?;
+    if let Some(merged_into) = service.as_ref().and_then(|service| service.merged_into) {
+        service = fetch_service(database, merged_into)
+            .await
+            .map_err(internal_error)?;
+    }
+
+    service
+        .filter(|service| service.is_visible_to(user))
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Service not found".to_string()))
+}
+
+/// Service the `user` is allowed to change, see [`Service::is_editable_by`]. Unlike
+/// [`ensure_visible_service`] merged services are not resolved, they cannot be changed anymore.
+pub(crate) async fn ensure_editable_service(
+    database: &PgPool,
+    id: Uuid,
+    user: &User,
+) -> Result<Service, (StatusCode, String)> {
+    let service = fetch_service(database, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .filter(|service| service.is_visible_to(Some(user)))
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Service not found".to_string()))?;
+    if service.status == ServiceStatus::Merged {
+        return Err((
+            StatusCode::CONFLICT,
+            "Service was merged into another one".to_string(),
+        ));
+    }
+    if !service.is_editable_by(user) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Only admins can change reviewed services".to_string(),
+        ));
+    }
+
+    Ok(service)
+}
+
+/// Names and aliases of the services visible to the `viewer` which are similar to the normalized
+/// `query`
+#[tracing::instrument(name = "Finding search candidates", skip(database))]
+async fn find_search_candidates(
+    database: &PgPool,
+    query: &str,
+    viewer: Option<Uuid>,
+) -> Result<Vec<SearchCandidate>, String> {
+    const CANDIDATES_LIMIT: i64 = 100;
+
+    let rows = sqlx::query!(
+        r#"
+        WITH visible AS (
+            SELECT id, name, category, normalized_name FROM services
+            WHERE status = $3 OR (status = $4 AND suggested_by = $2)
+        ),
+        terms AS (
+            SELECT id AS service_id, normalized_name AS term, NULL AS alias FROM visible
+            UNION ALL
+            SELECT a.service_id, a.normalized, a.alias
+            FROM service_aliases a JOIN visible ON visible.id = a.service_id
+        )
+        SELECT
+            terms.service_id AS "service_id!", visible.name, visible.category, terms.alias,
+            word_similarity($1, terms.term) AS "similarity!",
+            starts_with(terms.term, $1) AS "is_prefix!",
+            (
+                SELECT COUNT(*) FROM subscriptions
+                WHERE service_id = terms.service_id
+                  AND cancelled_at IS NULL
+                  AND deleted_at IS NULL
+            ) AS "popularity!"
+        FROM terms
+        JOIN visible ON visible.id = terms.service_id
+        WHERE $1 <% terms.term OR starts_with(terms.term, $1)
+        ORDER BY 5 DESC
+        LIMIT $5
+        "#,
+        query,
+        viewer,
+        ServiceStatus::Approved.as_ref(),
+        ServiceStatus::Pending.as_ref(),
+        CANDIDATES_LIMIT
+    )
+    .fetch_all(database)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    rows.into_iter()
+        .map(|row| {
+            Ok(SearchCandidate {
+                service_id: row.service_id,
+                name: row.name,
+                category: row.category.try_into()?,
+                alias: row.alias,
+                similarity: row.similarity,
+                is_prefix: row.is_prefix,
+                popularity: row.popularity,
+            })
+        })
+        .collect()
+}
+
+/// Loads the plans of all `services` at once
+pub(crate) async fn attach_plans(
+    database: &PgPool,
+    services: &mut [Service],
+) -> Result<(), String> {
+    let ids: Vec<Uuid> = services.iter().map(|service| service.id).collect();
+    let mut plans = fetch_service_plans(database, &ids).await?;
+    for service in services {
+        let service_id = ServiceId::from(service.id);
+        let (own, rest) = plans
+            .into_iter()
+            .partition(|plan| plan.service_id == service_id);
+        service.plans = own;
+        plans = rest;
+    }
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Listing services", skip(database))]
+async fn list_services(
+    database: &PgPool,
+    viewer: Option<UserId>,
+    category: Option<Category>,
+    query: &ListQuery<ServiceSortKey>,
+) -> Result<Page<Service>, String> {
+    let mut builder = QueryBuilder::<Postgres>::new("SELECT * FROM services WHERE (status = ");
+    builder.push_bind(ServiceStatus::Approved.as_ref());
+    match viewer {
+        Some(viewer) => {
+            builder
+                .push(" OR (status = ")
+                .push_bind(ServiceStatus::Pending.as_ref())
+                .push(" AND suggested_by = ")
+                .push_bind(Uuid::from(viewer))
+                .push("))");
+        }
+        None => {
+            builder.push(")");
+        }
+    }
+    if let Some(category) = category {
+        builder
+            .push(" AND category = ")
+            .push_bind(category.as_ref().to_owned());
+    }
+    query.push_keyset(&mut builder, "id");
+    query.push_order_and_limit(&mut builder, "id");
+
+    let mut services: Vec<Service> = builder
+        .build_query_as()
+        .fetch_all(database)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    attach_plans(database, &mut services).await?;
+
+    Ok(query.page(services, |service| {
+        (query.sort.key.value(service), service.id)
+    }))
+}
+
+#[tracing::instrument(name = "Save service in the database", skip_all)]
+async fn insert_service(
+    database: PgPool,
+    user: &User,
+    service: NewService,
+) -> Result<Service, String> {
+    let (status, suggested_by) = match user.role {
+        UserRole::Admin => (ServiceStatus::Approved, None),
+        UserRole::User => (ServiceStatus::Pending, Some(Uuid::from(user.id))),
+    };
+    let mut transaction = database.begin().await.map_err(|e| e.to_string())?;
+
+    let row = sqlx::query!(
+        r#"
+        INSERT INTO services(id, name, category, status, suggested_by, normalized_name)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, name, category, created_at
         "#,
         Uuid::new_v4(),
-        service.name.as_ref()
+        service.name.as_ref(),
+        service.category.as_ref(),
+        status.as_ref(),
+        suggested_by,
+        normalize_name(service.name.as_ref())
     )
-    .fetch_one(&database)
+    .fetch_one(&mut *transaction)
     .await
-    .map_err(|e| e.to_string())
+    .map_err(|e| e.to_string())?;
+    let category = Category::try_from(row.category)?;
+
+    let plans = insert_service_plans(&mut transaction, row.id.into(), service.plans).await?;
+    insert_service_aliases(&mut transaction, row.id, service.aliases).await?;
+    transaction.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(Service {
+        id: row.id,
+        name: row.name.into(),
+        category,
+        created_at: row.created_at,
+        status,
+        suggested_by,
+        reviewed_at: None,
+        rejection_reason: None,
+        merged_into: None,
+        website_url: None,
+        cancellation_url: None,
+        logo_version: None,
+        plans,
+    })
+}
+
+/// Stops the seed catalog from updating the service, see [`crate::service_catalog`]
+async fn mark_service_edited<'e, E>(executor: E, id: Uuid) -> sqlx::Result<()>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    sqlx::query!("UPDATE services SET edited_at = now() WHERE id = $1", id)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+/// Skips aliases the service has already, only the inserted ones are returned
+pub(crate) async fn insert_service_aliases(
+    transaction: &mut Transaction<'_, Postgres>,
+    service_id: Uuid,
+    aliases: Vec<NewServiceAlias>,
+) -> Result<Vec<ServiceAlias>, String> {
+    let mut saved = Vec::with_capacity(aliases.len());
+    for alias in aliases {
+        let inserted = sqlx::query_as!(
+            ServiceAlias,
+            r#"
+            INSERT INTO service_aliases (id, service_id, alias, normalized)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (service_id, normalized) DO NOTHING
+            RETURNING id, service_id, alias
+            "#,
+            Uuid::new_v4(),
+            service_id,
+            alias.alias,
+            alias.normalized
+        )
+        .fetch_optional(&mut **transaction)
+        .await
+        .map_err(|e| e.to_string())?;
+        saved.extend(inserted);
+    }
+
+    Ok(saved)
+}
+
+async fn insert_service_plans(
+    transaction: &mut Transaction<'_, Postgres>,
+    service_id: ServiceId,
+    plans: Vec<NewServicePlan>,
+) -> Result<Vec<ServicePlan>, String> {
+    let mut saved = Vec::with_capacity(plans.len());
+    for plan in plans {
+        let row = sqlx::query_as!(
+            ServicePlanRow,
+            r#"
+            INSERT INTO service_plans (
+                id, service_id, name, amount, currency, region, billing_period,
+                billing_period_unit, seats
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING
+                id, service_id, name, amount, currency, region, billing_period,
+                billing_period_unit, seats
+            "#,
+            Uuid::new_v4(),
+            Into::<Uuid>::into(service_id),
+            plan.name,
+            plan.price.minor_units(),
+            plan.price.currency().code(),
+            plan.region.as_ref().map(AsRef::<str>::as_ref),
+            i16::from(plan.billing_period),
+            plan.billing_period_unit.as_ref(),
+            plan.seats as i16
+        )
+        .fetch_one(&mut **transaction)
+        .await
+        .map_err(|e| e.to_string())?;
+        saved.push(row.try_into()?);
+    }
+
+    Ok(saved)
 }
@@ -0,0 +1,313 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use chrono::Utc;
+use hyper::StatusCode;
+use uuid::Uuid;
+
+use crate::{
+    auth::{current_user, AuthContext},
+    domain::{
+        BillingPeriodUnit, Category, Locale, Money, PaymentDigest, ScenarioChange, Simulation,
+        SpendingSummary, Subscription, SubscriptionId, Tag, UsageReport,
+    },
+    exchange_rate_store::ExchangeRateStore,
+    startup::AppState,
+};
+
+use super::{fetch_usage_summaries, fetch_user_subscriptions, fetch_user_tags, FormatOptions};
+
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GroupBy {
+    Category,
+    Tag,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsOptions {
+    group_by: Option<GroupBy>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub(crate) struct ScenarioInput {
+    changes: Vec<ScenarioChangeInput>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub(crate) enum ScenarioChangeInput {
+    #[serde(rename_all = "camelCase")]
+    Cancel { subscription_id: Uuid },
+    #[serde(rename_all = "camelCase")]
+    SwitchPlan {
+        subscription_id: Uuid,
+        billing_period: u8,
+        billing_period_unit: BillingPeriodUnit,
+        /// Price of the new plan in major units of the subscription currency
+        amount: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    ChangeShare { subscription_id: Uuid, share: u8 },
+}
+
+impl ScenarioChangeInput {
+    fn parse(self, subscriptions: &[Subscription]) -> Result<ScenarioChange, String> {
+        let find = |id: Uuid| {
+            let id = SubscriptionId::from(id);
+            subscriptions
+                .iter()
+                .find(|subscription| subscription.id == id)
+                .ok_or_else(|| format!("Subscription {} does not exist.", Uuid::from(id)))
+        };
+
+        match self {
+            Self::Cancel { subscription_id } => {
+                Ok(ScenarioChange::Cancel(find(subscription_id)?.id))
+            }
+            Self::SwitchPlan {
+                subscription_id,
+                billing_period,
+                billing_period_unit,
+                amount,
+            } => {
+                let subscription = find(subscription_id)?;
+                if billing_period == 0 {
+                    return Err("Billing period has to be greater than 0.".into());
+                }
+                Ok(ScenarioChange::SwitchPlan {
+                    subscription_id: subscription.id,
+                    billing_period,
+                    billing_period_unit,
+                    price: Money::parse(&amount, subscription.price.currency())?,
+                })
+            }
+            Self::ChangeShare {
+                subscription_id,
+                share,
+            } => {
+                if share > 100 {
+                    return Err("Share has to be a percentage between 0 and 100.".into());
+                }
+                Ok(ScenarioChange::ChangeShare {
+                    subscription_id: find(subscription_id)?.id,
+                    share,
+                })
+            }
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StatsResponse {
+    #[serde(flatten)]
+    summary: SpendingSummary,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    formatted: Option<FormattedStats>,
+    /// Spending per category or tag when requested with `groupBy`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    groups: Option<Vec<SpendingGroup>>,
+    /// Unused subscriptions and cost per use of the last 12 months
+    #[serde(skip_serializing_if = "Option::is_none")]
+    usage: Option<UsageReport>,
+}
+
+/// Spending of subscriptions in a category or with a tag, subscriptions without tags are grouped
+/// together with neither `category` nor `tag` set
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SpendingGroup {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    category: Option<Category>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tag: Option<Tag>,
+    #[serde(flatten)]
+    summary: SpendingSummary,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FormattedStats {
+    spent_to_date: String,
+    next_12_months: String,
+    monthly_equivalent: String,
+}
+
+impl StatsResponse {
+    fn new(summary: SpendingSummary, locale: Option<Locale>) -> Self {
+        let formatted = locale.map(|locale| FormattedStats {
+            spent_to_date: locale.format_money(summary.spent_to_date),
+            next_12_months: locale.format_money(summary.next_12_months),
+            monthly_equivalent: locale.format_money(summary.monthly_equivalent),
+        });
+
+        Self {
+            summary,
+            formatted,
+            groups: None,
+            usage: None,
+        }
+    }
+}
+
+#[tracing::instrument(name = "Spending statistics", skip_all)]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn stats_handler(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Query(options): Query<FormatOptions>,
+    Query(stats_options): Query<StatsOptions>,
+) -> Result<Json<StatsResponse>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let locale = options.formatted.then_some(user.locale);
+    let currency = *user.preferred_currency;
+
+    let subscriptions = fetch_user_subscriptions(&database, user.id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    let rates = ExchangeRateStore::new(database.clone())
+        .load_for([currency], subscriptions.iter().map(|s| s.price.currency()))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let today = Utc::now().date_naive();
+    let summary = SpendingSummary::compute(&subscriptions, &rates, currency, today)
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    let mut response = StatsResponse::new(summary, locale);
+
+    let usages = fetch_usage_summaries(
+        &database,
+        user.id,
+        UsageReport::period_start(today).map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?,
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let unused_after_cycles = user.unused_after_cycles.try_into().unwrap_or(u8::MAX);
+    response.usage = Some(
+        UsageReport::compute(
+            &subscriptions,
+            &usages,
+            &rates,
+            currency,
+            today,
+            unused_after_cycles,
+        )
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?,
+    );
+
+    response.groups = match stats_options.group_by {
+        None => None,
+        Some(GroupBy::Category) => {
+            let groups = SpendingSummary::compute_grouped(
+                &subscriptions,
+                |subscription| vec![subscription.category],
+                &rates,
+                currency,
+                today,
+            )
+            .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+
+            Some(
+                groups
+                    .into_iter()
+                    .map(|(category, summary)| SpendingGroup {
+                        category: Some(category),
+                        tag: None,
+                        summary,
+                    })
+                    .collect(),
+            )
+        }
+        Some(GroupBy::Tag) => {
+            let tags = fetch_user_tags(&database, user.id)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+            let groups = SpendingSummary::compute_grouped(
+                &subscriptions,
+                |subscription| match subscription.tag_ids.as_slice() {
+                    [] => vec![None],
+                    tag_ids => tag_ids.iter().copied().map(Some).collect(),
+                },
+                &rates,
+                currency,
+                today,
+            )
+            .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+
+            Some(
+                groups
+                    .into_iter()
+                    .map(|(tag_id, summary)| SpendingGroup {
+                        category: None,
+                        tag: tag_id.and_then(|id| tags.iter().find(|tag| tag.id == id).cloned()),
+                        summary,
+                    })
+                    .collect(),
+            )
+        }
+    };
+
+    Ok(Json(response))
+}
+
+/// Forecast with hypothetical changes applied, nothing gets saved
+#[tracing::instrument(name = "Simulate scenario", skip_all)]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn simulate_handler(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Json(input): Json<ScenarioInput>,
+) -> Result<Json<Simulation>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let currency = *user.preferred_currency;
+
+    let subscriptions = fetch_user_subscriptions(&database, user.id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let changes = input
+        .changes
+        .into_iter()
+        .map(|change| change.parse(&subscriptions))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let rates = ExchangeRateStore::new(database.clone())
+        .load_for([currency], subscriptions.iter().map(|s| s.price.currency()))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let today = Utc::now().date_naive();
+    let simulation = Simulation::run(&subscriptions, &changes, &rates, currency, today)
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+
+    Ok(Json(simulation))
+}
+
+/// Payments of the coming week in the preferred currency, `null` when nothing is charged
+#[tracing::instrument(name = "Payment digest", skip_all)]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn digest_handler(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+) -> Result<Json<Option<PaymentDigest>>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let currency = *user.preferred_currency;
+
+    let subscriptions = fetch_user_subscriptions(&database, user.id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    let rates = ExchangeRateStore::new(database)
+        .load_for([currency], subscriptions.iter().map(|s| s.price.currency()))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let digest = PaymentDigest::compute(&subscriptions, &rates, currency, Utc::now().date_naive())
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+
+    Ok(Json(digest))
+}
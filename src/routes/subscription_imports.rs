@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+
+use axum::{extract::State, Json};
+use hyper::StatusCode;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    auth::{current_user, AuthContext},
+    budget_alerts::check_budgets,
+    domain::{normalize_name, NewSubscription, ServiceStatus, User},
+    startup::AppState,
+    subscription_import::{parse_import, ImportOptions},
+};
+
+use super::{ensure_visible_service, insert_subscription_with};
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSubscriptions {
+    /// Content of the CSV file, the first line holds the column headers
+    csv: String,
+    #[serde(flatten)]
+    options: ImportOptions,
+    /// Only checks the rows without saving them
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ImportReport {
+    dry_run: bool,
+    /// Number of subscriptions saved, 0 for dry runs and files with invalid rows
+    imported: usize,
+    rows: Vec<ImportRowReport>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ImportRowReport {
+    line: u64,
+    name: String,
+    service_id: Option<Uuid>,
+    /// Empty when the row can be imported
+    errors: Vec<String>,
+}
+
+/// Checks the rows of the CSV file and saves them unless it is a dry run. Either all rows are
+/// saved or none, files with invalid rows are answered with the report and status 422.
+#[tracing::instrument(name = "Import subscriptions", skip_all, fields(dry_run = %input.dry_run))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn import_subscriptions(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Json(input): Json<ImportSubscriptions>,
+) -> Result<(StatusCode, Json<ImportReport>), (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let internal_error = |e: String| (StatusCode::INTERNAL_SERVER_ERROR, e);
+    let rows = parse_import(&input.csv, &input.options, *user.preferred_currency)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    // Files usually repeat a few services, each of them is looked up once
+    let mut services: HashMap<String, Option<Uuid>> = HashMap::new();
+    let mut reports = vec![];
+    let mut subscriptions = vec![];
+    for row in rows {
+        let service_id = match services.get(&row.service) {
+            Some(service_id) => *service_id,
+            None => {
+                let service_id = find_import_service(&database, &row.service, &user).await?;
+                services.insert(row.service.clone(), service_id);
+                service_id
+            }
+        };
+        let mut errors = match row.subscription {
+            Ok(subscription) => {
+                if let Some(service_id) = service_id {
+                    subscriptions.push(NewSubscription {
+                        service_id: service_id.into(),
+                        plan_id: None,
+                        name: subscription.name,
+                        description: None,
+                        price: subscription.price,
+                        share: 100,
+                        next_renewal_date: subscription.next_renewal_date,
+                        billing_period: subscription.billing_period,
+                        billing_period_unit: subscription.billing_period_unit,
+                        subscribed_at: None,
+                        category: None,
+                        tag_ids: vec![],
+                        trial: None,
+                        price_phases: vec![],
+                        contract: None,
+                        payment_method_id: None,
+                        usage_target: None,
+                    });
+                }
+                vec![]
+            }
+            Err(errors) => errors,
+        };
+        if service_id.is_none() {
+            errors.push(format!("No service matches {}.", row.service));
+        }
+        reports.push(ImportRowReport {
+            line: row.line,
+            name: row.name,
+            service_id,
+            errors,
+        });
+    }
+
+    let is_valid = reports.iter().all(|report| report.errors.is_empty());
+    if input.dry_run || !is_valid {
+        let status = match is_valid {
+            true => StatusCode::OK,
+            false => StatusCode::UNPROCESSABLE_ENTITY,
+        };
+        return Ok((
+            status,
+            Json(ImportReport {
+                dry_run: input.dry_run,
+                imported: 0,
+                rows: reports,
+            }),
+        ));
+    }
+
+    let mut transaction = database
+        .begin()
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+    for subscription in &subscriptions {
+        insert_subscription_with(&mut transaction, user.id, subscription)
+            .await
+            .map_err(|e| internal_error(e.to_string()))?;
+    }
+    transaction
+        .commit()
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    if let Err(e) = check_budgets(&database, user.id, user.locale, None).await {
+        tracing::error!("Failed to check budget alerts: {}", e);
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(ImportReport {
+            dry_run: false,
+            imported: subscriptions.len(),
+            rows: reports,
+        }),
+    ))
+}
+
+/// Service the `user` can subscribe to by its ID, name or one of its aliases
+async fn find_import_service(
+    database: &PgPool,
+    service: &str,
+    user: &User,
+) -> Result<Option<Uuid>, (StatusCode, String)> {
+    if let Ok(id) = Uuid::parse_str(service.trim()) {
+        return match ensure_visible_service(database, id, Some(user)).await {
+            Ok(service) => Ok(Some(service.id)),
+            Err((StatusCode::NOT_FOUND, _)) => Ok(None),
+            Err(e) => Err(e),
+        };
+    }
+    let normalized = normalize_name(service);
+    if normalized.is_empty() {
+        return Ok(None);
+    }
+
+    // Names win over aliases and reviewed services over suggestions
+    sqlx::query_scalar!(
+        r#"
+        SELECT s.id FROM services s
+        WHERE (s.status = $2 OR (s.status = $3 AND s.suggested_by = $4))
+          AND (
+            s.normalized_name = $1
+            OR EXISTS (
+              SELECT 1 FROM service_aliases a WHERE a.service_id = s.id AND a.normalized = $1
+            )
+          )
+        ORDER BY s.normalized_name = $1 DESC, s.status = $2 DESC, s.created_at
+        LIMIT 1
+        "#,
+        normalized,
+        ServiceStatus::Approved.as_ref(),
+        ServiceStatus::Pending.as_ref(),
+        Uuid::from(user.id)
+    )
+    .fetch_optional(database)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
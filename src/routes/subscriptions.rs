@@ -0,0 +1,1284 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use hyper::StatusCode;
+use iso_currency::Currency;
+use sqlx::{PgPool, Postgres, QueryBuilder, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    auth::{current_user, AuthContext},
+    budget_alerts::check_budgets,
+    domain::{
+        BillingPeriodUnit, Category, Contract, CurrencyCode, Locale, Money, NewSubscription,
+        PaymentMethodId, PlanComparison, PricePhase, ServiceId, ServicePlan, ServicePlanId,
+        Subscription, SubscriptionId, SubscriptionStatus, TagId, Trial, UsageTarget, UserId,
+        DEFAULT_TRIAL_REMINDER_DAYS,
+    },
+    startup::AppState,
+};
+
+use super::{
+    ensure_user_payment_method, ensure_user_tags, ensure_visible_service, fetch_service_plan,
+    fetch_service_plans, FormatOptions, ListQuery, Page, PageParams, Sort, SortKey, SortValue,
+    SortValueType,
+};
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateSubscription {
+    service_id: Uuid,
+    /// Plan of the service filling in the price and billing period when they are missing
+    plan_id: Option<Uuid>,
+    name: String,
+    description: Option<String>,
+    /// Price in major units, e.g. `9.99`
+    amount: Option<String>,
+    currency: Option<String>,
+    share: Option<u8>,
+    next_renewal_date: NaiveDate,
+    billing_period: Option<u8>,
+    billing_period_unit: Option<BillingPeriodUnit>,
+    subscribed_at: Option<NaiveDate>,
+    /// Overrides the category of the service
+    category: Option<String>,
+    #[serde(default)]
+    tag_ids: Vec<Uuid>,
+    trial: Option<CreateTrial>,
+    /// Introductory prices charged before `amount`
+    #[serde(default)]
+    price_phases: Vec<CreatePricePhase>,
+    contract: Option<CreateContract>,
+    payment_method_id: Option<Uuid>,
+    usage_target: Option<UsageTarget>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateTrial {
+    /// Day of the conversion to the regular price
+    ends_on: NaiveDate,
+    /// Price of the trial in major units, free when missing
+    amount: Option<String>,
+    /// Days before the conversion to remind the user at
+    reminder_days: Option<u8>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatePricePhase {
+    /// Number of renewals charged at `amount`
+    cycles: u16,
+    /// Price in major units
+    amount: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateContract {
+    /// Last day of the minimum term
+    ends_on: NaiveDate,
+    /// Renews by another billing period unless cancelled in time, `true` when missing
+    auto_renew: Option<bool>,
+    notice_period: u8,
+    notice_period_unit: BillingPeriodUnit,
+}
+
+impl CreateSubscription {
+    /// Fills in the price and billing period the user left out from the chosen `plan`
+    fn prefill(&mut self, plan: &ServicePlan) -> Result<(), String> {
+        if Uuid::from(plan.service_id) != self.service_id {
+            return Err("Plan does not belong to the service.".into());
+        }
+        let currency = plan.price.currency().code();
+        match &self.currency {
+            Some(chosen) if !chosen.trim().eq_ignore_ascii_case(currency) => {
+                return Err(format!("Plan is priced in {}, not {}.", currency, chosen));
+            }
+            _ => self.currency = Some(currency.to_owned()),
+        }
+        self.amount
+            .get_or_insert_with(|| plan.price.format_amount());
+        self.billing_period.get_or_insert(plan.billing_period);
+        self.billing_period_unit
+            .get_or_insert(plan.billing_period_unit);
+
+        Ok(())
+    }
+}
+
+impl TryFrom<CreateSubscription> for NewSubscription {
+    type Error = String;
+
+    fn try_from(value: CreateSubscription) -> Result<Self, Self::Error> {
+        let name = value.name.trim().to_owned();
+        if name.is_empty() {
+            return Err("Subscription name cannot be empty.".into());
+        }
+        let (Some(amount), Some(currency), Some(billing_period), Some(billing_period_unit)) = (
+            value.amount,
+            value.currency,
+            value.billing_period,
+            value.billing_period_unit,
+        ) else {
+            return Err(
+                "Amount, currency and billing period are required unless a plan is chosen.".into(),
+            );
+        };
+        if billing_period == 0 {
+            return Err("Billing period has to be greater than 0.".into());
+        }
+        let share = value.share.unwrap_or(100);
+        if share > 100 {
+            return Err("Share has to be a percentage between 0 and 100.".into());
+        }
+        let currency = CurrencyCode::parse(&currency)?;
+        let price = Money::parse(&amount, *currency)?;
+        let category = value.category.as_deref().map(Category::parse).transpose()?;
+        let trial = value
+            .trial
+            .map(|trial| -> Result<Trial, String> {
+                if value
+                    .subscribed_at
+                    .is_some_and(|subscribed_at| trial.ends_on < subscribed_at)
+                {
+                    return Err("Trial cannot end before the subscription starts.".into());
+                }
+                let price = match trial.amount.as_deref() {
+                    Some(amount) => Money::parse(amount, *currency)?,
+                    None => Money::zero(*currency),
+                };
+                Ok(Trial {
+                    ends_on: trial.ends_on,
+                    price,
+                    reminder_days: trial.reminder_days.unwrap_or(DEFAULT_TRIAL_REMINDER_DAYS),
+                })
+            })
+            .transpose()?;
+        let price_phases = value
+            .price_phases
+            .into_iter()
+            .map(|phase| -> Result<PricePhase, String> {
+                if phase.cycles == 0 || phase.cycles > i16::MAX as u16 {
+                    return Err("Price phase has to last at least one cycle.".into());
+                }
+                Ok(PricePhase {
+                    cycles: phase.cycles,
+                    price: Money::parse(&phase.amount, *currency)?,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        if value
+            .usage_target
+            .is_some_and(|target| target.times == 0 || target.times > i16::MAX as u16)
+        {
+            return Err("Usage target has to be a positive number of times.".into());
+        }
+        let contract = value.contract.map(|contract| Contract {
+            ends_on: contract.ends_on,
+            auto_renew: contract.auto_renew.unwrap_or(true),
+            notice_period: contract.notice_period,
+            notice_period_unit: contract.notice_period_unit,
+        });
+
+        Ok(Self {
+            service_id: value.service_id.into(),
+            plan_id: value.plan_id.map(ServicePlanId::from),
+            name,
+            description: value.description,
+            price,
+            share,
+            next_renewal_date: value.next_renewal_date,
+            billing_period,
+            billing_period_unit,
+            subscribed_at: value.subscribed_at,
+            category,
+            tag_ids: value.tag_ids.into_iter().map(TagId::from).collect(),
+            trial,
+            price_phases,
+            contract,
+            payment_method_id: value.payment_method_id.map(PaymentMethodId::from),
+            usage_target: value.usage_target,
+        })
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SubscriptionResponse {
+    #[serde(flatten)]
+    subscription: Subscription,
+    status: SubscriptionStatus,
+    /// Latest day to cancel on to avoid another contract term
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cancel_by: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    formatted: Option<FormattedSubscription>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FormattedSubscription {
+    price: String,
+    user_price: String,
+    next_renewal_date: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cancel_by: Option<String>,
+}
+
+impl SubscriptionResponse {
+    fn new(subscription: Subscription, locale: Option<Locale>) -> Result<Self, String> {
+        let cancel_by = match subscription.status() {
+            SubscriptionStatus::Active => subscription
+                .cancellation_deadline(Utc::now().date_naive())
+                .map(|deadline| deadline.cancel_by),
+            _ => None,
+        };
+        let formatted = match locale {
+            Some(locale) => Some(FormattedSubscription {
+                price: locale.format_money(subscription.price),
+                user_price: locale.format_money(subscription.user_price()?),
+                next_renewal_date: locale.format_date(subscription.next_renewal_date),
+                cancel_by: cancel_by.map(|date| locale.format_date(date)),
+            }),
+            None => None,
+        };
+
+        Ok(Self {
+            status: subscription.status(),
+            cancel_by,
+            subscription,
+            formatted,
+        })
+    }
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionFilter {
+    /// Active and cancelled subscriptions when missing
+    status: Option<SubscriptionStatus>,
+    service: Option<Uuid>,
+    tag: Option<Uuid>,
+    currency: Option<String>,
+    /// Minimal price in major units of `currency`, requires `currency`
+    min_amount: Option<String>,
+    /// Maximal price in major units of `currency`, requires `currency`
+    max_amount: Option<String>,
+    /// Earliest next renewal date, inclusive
+    renews_from: Option<NaiveDate>,
+    /// Latest next renewal date, inclusive
+    renews_to: Option<NaiveDate>,
+}
+
+/// Validated [`SubscriptionFilter`]
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct SubscriptionConditions {
+    status: Option<SubscriptionStatus>,
+    service: Option<ServiceId>,
+    tag: Option<TagId>,
+    currency: Option<Currency>,
+    min_amount: Option<Money>,
+    max_amount: Option<Money>,
+    renews_from: Option<NaiveDate>,
+    renews_to: Option<NaiveDate>,
+}
+
+impl TryFrom<SubscriptionFilter> for SubscriptionConditions {
+    type Error = String;
+
+    fn try_from(value: SubscriptionFilter) -> Result<Self, Self::Error> {
+        let currency = value
+            .currency
+            .as_deref()
+            .map(CurrencyCode::parse)
+            .transpose()?
+            .map(Currency::from);
+        let amount = |amount: Option<String>| -> Result<Option<Money>, String> {
+            match (amount, currency) {
+                (None, _) => Ok(None),
+                (Some(amount), Some(currency)) => Money::parse(&amount, currency).map(Some),
+                (Some(_), None) => Err("Filtering by amount requires a currency.".into()),
+            }
+        };
+        let min_amount = amount(value.min_amount)?;
+        let max_amount = amount(value.max_amount)?;
+
+        if let (Some(min), Some(max)) = (min_amount, max_amount) {
+            if min.minor_units() > max.minor_units() {
+                return Err("Minimal amount cannot be greater than the maximal one.".into());
+            }
+        }
+        if let (Some(from), Some(to)) = (value.renews_from, value.renews_to) {
+            if from > to {
+                return Err("Renewal date range cannot end before it starts.".into());
+            }
+        }
+
+        Ok(Self {
+            status: value.status,
+            service: value.service.map(ServiceId::from),
+            tag: value.tag.map(TagId::from),
+            currency,
+            min_amount,
+            max_amount,
+            renews_from: value.renews_from,
+            renews_to: value.renews_to,
+        })
+    }
+}
+
+impl SubscriptionConditions {
+    /// Amounts are stored in minor units of their currency, they can only be compared within
+    /// one currency
+    fn ensure_sortable_by(&self, key: SubscriptionSortKey) -> Result<(), String> {
+        match (key, self.currency) {
+            (SubscriptionSortKey::Amount, None) => {
+                Err("Sorting by amount requires a currency.".into())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SubscriptionSortKey {
+    Name,
+    Service,
+    Currency,
+    Amount,
+    NextRenewalDate,
+    CreatedAt,
+}
+
+impl SortKey for SubscriptionSortKey {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "name" => Ok(Self::Name),
+            "service" => Ok(Self::Service),
+            "currency" => Ok(Self::Currency),
+            "amount" => Ok(Self::Amount),
+            "nextRenewalDate" => Ok(Self::NextRenewalDate),
+            "createdAt" => Ok(Self::CreatedAt),
+            other => Err(format!("Subscriptions cannot be sorted by {}.", other)),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Service => "service",
+            Self::Currency => "currency",
+            Self::Amount => "amount",
+            Self::NextRenewalDate => "nextRenewalDate",
+            Self::CreatedAt => "createdAt",
+        }
+    }
+
+    fn column(&self) -> &'static str {
+        match self {
+            Self::Name => "s.name",
+            Self::Service => "services.name",
+            Self::Currency => "s.currency",
+            Self::Amount => "s.amount",
+            Self::NextRenewalDate => "s.next_renewal_date",
+            Self::CreatedAt => "s.created_at",
+        }
+    }
+
+    fn value_type(&self) -> SortValueType {
+        match self {
+            Self::Name | Self::Service | Self::Currency => SortValueType::Text,
+            Self::Amount => SortValueType::Integer,
+            Self::NextRenewalDate => SortValueType::Date,
+            Self::CreatedAt => SortValueType::Timestamp,
+        }
+    }
+}
+
+impl SubscriptionSortKey {
+    fn value(&self, row: &SubscriptionRow) -> SortValue {
+        match self {
+            Self::Name => SortValue::Text(row.name.clone()),
+            Self::Service => SortValue::Text(row.service_name.clone()),
+            Self::Currency => SortValue::Text(row.currency.clone()),
+            Self::Amount => SortValue::Integer(row.amount),
+            Self::NextRenewalDate => SortValue::Date(row.next_renewal_date),
+            Self::CreatedAt => SortValue::Timestamp(row.created_at),
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionTags {
+    tag_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionUsageTarget {
+    /// Removes the target when `null`
+    usage_target: Option<UsageTarget>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionPaymentMethod {
+    /// Unlinks the payment method when `null`
+    payment_method_id: Option<Uuid>,
+}
+
+/// Raw `subscriptions` row joined with its service and tags, see [`Subscription`] for the meaning
+/// of the columns
+#[derive(sqlx::FromRow)]
+pub(crate) struct SubscriptionRow {
+    id: Uuid,
+    user_id: Uuid,
+    service_id: Uuid,
+    name: String,
+    description: Option<String>,
+    amount: i64,
+    currency: String,
+    share: i16,
+    next_renewal_date: NaiveDate,
+    billing_period: i16,
+    billing_period_unit: String,
+    subscribed_at: Option<NaiveDate>,
+    cancelled_at: Option<DateTime<Utc>>,
+    cancel_reason: Option<String>,
+    deleted_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    /// Category of the subscription falling back to the one of the service
+    category: String,
+    tag_ids: Vec<Uuid>,
+    service_name: String,
+    trial_ends_on: Option<NaiveDate>,
+    trial_amount: Option<i64>,
+    trial_reminder_days: Option<i16>,
+    phase_cycles: Vec<i16>,
+    phase_amounts: Vec<i64>,
+    contract_ends_on: Option<NaiveDate>,
+    contract_auto_renew: Option<bool>,
+    notice_period: Option<i16>,
+    notice_period_unit: Option<String>,
+    payment_method_id: Option<Uuid>,
+    usage_target_times: Option<i16>,
+    usage_target_unit: Option<String>,
+    service_plan_id: Option<Uuid>,
+}
+
+impl TryFrom<SubscriptionRow> for Subscription {
+    type Error = String;
+
+    fn try_from(row: SubscriptionRow) -> Result<Self, Self::Error> {
+        let currency = *CurrencyCode::parse(&row.currency)?;
+        let trial = match (row.trial_ends_on, row.trial_amount, row.trial_reminder_days) {
+            (Some(ends_on), Some(amount), Some(reminder_days)) => Some(Trial {
+                ends_on,
+                price: Money::new(amount, currency),
+                reminder_days: reminder_days
+                    .try_into()
+                    .map_err(|_| "invalid trial reminder days")?,
+            }),
+            _ => None,
+        };
+        let price_phases = row
+            .phase_cycles
+            .into_iter()
+            .zip(row.phase_amounts)
+            .map(|(cycles, amount)| -> Result<PricePhase, String> {
+                Ok(PricePhase {
+                    cycles: cycles
+                        .try_into()
+                        .map_err(|_| "invalid price phase cycles")?,
+                    price: Money::new(amount, currency),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        let contract = match (
+            row.contract_ends_on,
+            row.contract_auto_renew,
+            row.notice_period,
+            row.notice_period_unit,
+        ) {
+            (Some(ends_on), Some(auto_renew), Some(notice_period), Some(notice_period_unit)) => {
+                Some(Contract {
+                    ends_on,
+                    auto_renew,
+                    notice_period: notice_period
+                        .try_into()
+                        .map_err(|_| "invalid notice period")?,
+                    notice_period_unit: notice_period_unit.try_into()?,
+                })
+            }
+            _ => None,
+        };
+
+        let usage_target = match (row.usage_target_times, row.usage_target_unit) {
+            (Some(times), Some(per)) => Some(UsageTarget {
+                times: times.try_into().map_err(|_| "invalid usage target")?,
+                per: per.try_into()?,
+            }),
+            _ => None,
+        };
+
+        Ok(Self {
+            id: row.id.into(),
+            user_id: row.user_id.into(),
+            name: row.name,
+            description: row.description,
+            price: Money::new(row.amount, currency),
+            share: row.share.try_into().map_err(|_| "invalid share")?,
+            next_renewal_date: row.next_renewal_date,
+            billing_period: row
+                .billing_period
+                .try_into()
+                .map_err(|_| "invalid billing period")?,
+            billing_period_unit: row.billing_period_unit.try_into()?,
+            service_id: row.service_id.into(),
+            plan_id: row.service_plan_id.map(ServicePlanId::from),
+            category: Category::parse(&row.category)?,
+            tag_ids: row.tag_ids.into_iter().map(TagId::from).collect(),
+            trial,
+            price_phases,
+            contract,
+            payment_method_id: row.payment_method_id.map(PaymentMethodId::from),
+            usage_target,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            cancelled_at: row.cancelled_at,
+            cancel_reason: row.cancel_reason,
+            subscribed_at: row.subscribed_at,
+            deleted_at: row.deleted_at,
+        })
+    }
+}
+
+#[tracing::instrument(name = "Subscriptions index", skip_all)]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn subscriptions_index(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Query(options): Query<FormatOptions>,
+    Query(filter): Query<SubscriptionFilter>,
+    Query(page): Query<PageParams>,
+) -> Result<Json<Page<SubscriptionResponse>>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let locale = options.formatted.then_some(user.locale);
+    let conditions: SubscriptionConditions = filter
+        .try_into()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let query = ListQuery::parse(page, Sort::asc(SubscriptionSortKey::NextRenewalDate))
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    conditions
+        .ensure_sortable_by(query.sort.key)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let subscriptions = list_user_subscriptions(&database, user.id, &conditions, &query)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    let subscriptions = subscriptions
+        .try_map(|subscription| SubscriptionResponse::new(subscription, locale))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok(Json(subscriptions))
+}
+
+#[tracing::instrument(name = "Create subscription", skip_all, fields(subscription_name = %input.name))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn create_subscription(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Query(options): Query<FormatOptions>,
+    Json(mut input): Json<CreateSubscription>,
+) -> Result<Json<SubscriptionResponse>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let locale = options.formatted.then_some(user.locale);
+    input.service_id = ensure_visible_service(&database, input.service_id, Some(&user))
+        .await?
+        .id;
+    if let Some(plan_id) = input.plan_id {
+        let plan = fetch_service_plan(&database, plan_id.into())
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?
+            .ok_or_else(|| (StatusCode::BAD_REQUEST, "Plan not found".to_string()))?;
+        input
+            .prefill(&plan)
+            .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    }
+    let new_subscription: NewSubscription =
+        input.try_into().map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    ensure_user_tags(&database, user.id, &new_subscription.tag_ids).await?;
+    ensure_user_payment_method(&database, user.id, new_subscription.payment_method_id).await?;
+
+    let subscription = insert_subscription(&database, user.id, new_subscription)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    if let Err(e) = check_budgets(&database, user.id, user.locale, Some(&subscription)).await {
+        tracing::error!("Failed to check budget alerts: {}", e);
+    }
+
+    let subscription = SubscriptionResponse::new(subscription, locale)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok(Json(subscription))
+}
+
+/// Replaces the tags of the subscription
+#[tracing::instrument(name = "Set subscription tags", skip_all, fields(subscription_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn set_subscription_tags(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+    Query(options): Query<FormatOptions>,
+    Json(input): Json<SubscriptionTags>,
+) -> Result<Json<SubscriptionResponse>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let locale = options.formatted.then_some(user.locale);
+    let id = SubscriptionId::from(id);
+    let tag_ids: Vec<TagId> = input.tag_ids.into_iter().map(TagId::from).collect();
+
+    ensure_user_tags(&database, user.id, &tag_ids).await?;
+
+    let internal_error = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    let mut transaction = database.begin().await.map_err(internal_error)?;
+    let result = sqlx::query!(
+        r#"
+        UPDATE subscriptions SET updated_at = now()
+        WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL
+        "#,
+        Into::<Uuid>::into(id),
+        Into::<Uuid>::into(user.id)
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(internal_error)?;
+
+    let not_found = || (StatusCode::NOT_FOUND, "Subscription not found".to_string());
+    if result.rows_affected() == 0 {
+        return Err(not_found());
+    }
+    replace_subscription_tags(&mut transaction, id, &tag_ids)
+        .await
+        .map_err(internal_error)?;
+    transaction.commit().await.map_err(internal_error)?;
+
+    let subscription = fetch_subscription(&database, user.id, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .ok_or_else(not_found)?;
+
+    let subscription = SubscriptionResponse::new(subscription, locale)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok(Json(subscription))
+}
+
+/// Links the subscription to one of the payment methods of the user
+#[tracing::instrument(name = "Set subscription payment method", skip_all, fields(subscription_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn set_subscription_payment_method(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+    Query(options): Query<FormatOptions>,
+    Json(input): Json<SubscriptionPaymentMethod>,
+) -> Result<Json<SubscriptionResponse>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let locale = options.formatted.then_some(user.locale);
+    let id = SubscriptionId::from(id);
+    let payment_method_id = input.payment_method_id.map(PaymentMethodId::from);
+    ensure_user_payment_method(&database, user.id, payment_method_id).await?;
+
+    let result = sqlx::query!(
+        r#"
+        UPDATE subscriptions SET payment_method_id = $3, updated_at = now()
+        WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL
+        "#,
+        Into::<Uuid>::into(id),
+        Into::<Uuid>::into(user.id),
+        payment_method_id.map(Uuid::from)
+    )
+    .execute(&database)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let not_found = || (StatusCode::NOT_FOUND, "Subscription not found".to_string());
+    if result.rows_affected() == 0 {
+        return Err(not_found());
+    }
+
+    let subscription = fetch_subscription(&database, user.id, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .ok_or_else(not_found)?;
+
+    let subscription = SubscriptionResponse::new(subscription, locale)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok(Json(subscription))
+}
+
+/// Sets how often the user wants to use the subscription
+#[tracing::instrument(name = "Set subscription usage target", skip_all, fields(subscription_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn set_subscription_usage_target(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+    Query(options): Query<FormatOptions>,
+    Json(input): Json<SubscriptionUsageTarget>,
+) -> Result<Json<SubscriptionResponse>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let locale = options.formatted.then_some(user.locale);
+    let id = SubscriptionId::from(id);
+    let times = match input.usage_target {
+        Some(target) if target.times == 0 || target.times > i16::MAX as u16 => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Usage target has to be a positive number of times.".to_string(),
+            ))
+        }
+        Some(target) => Some(target.times as i16),
+        None => None,
+    };
+
+    let result = sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET usage_target_times = $3, usage_target_unit = $4, updated_at = now()
+        WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL
+        "#,
+        Into::<Uuid>::into(id),
+        Into::<Uuid>::into(user.id),
+        times,
+        input
+            .usage_target
+            .as_ref()
+            .map(|target| target.per.as_ref())
+    )
+    .execute(&database)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let not_found = || (StatusCode::NOT_FOUND, "Subscription not found".to_string());
+    if result.rows_affected() == 0 {
+        return Err(not_found());
+    }
+
+    let subscription = fetch_subscription(&database, user.id, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .ok_or_else(not_found)?;
+
+    let subscription = SubscriptionResponse::new(subscription, locale)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok(Json(subscription))
+}
+
+/// Accepts the current price of the plan the subscription was created from, e.g. after the
+/// price changed
+#[tracing::instrument(name = "Apply plan price", skip_all, fields(subscription_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn apply_plan_price(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+    Query(options): Query<FormatOptions>,
+) -> Result<Json<SubscriptionResponse>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let locale = options.formatted.then_some(user.locale);
+    let id = SubscriptionId::from(id);
+    let not_found = || (StatusCode::NOT_FOUND, "Subscription not found".to_string());
+
+    let subscription = fetch_subscription(&database, user.id, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .ok_or_else(not_found)?;
+    let no_plan = || {
+        (
+            StatusCode::CONFLICT,
+            "Subscription is not on a plan".to_string(),
+        )
+    };
+    let plan_id = subscription.plan_id.ok_or_else(no_plan)?;
+    let plan = fetch_service_plan(&database, plan_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .ok_or_else(no_plan)?;
+    if plan.price.currency() != subscription.price.currency() {
+        return Err((
+            StatusCode::CONFLICT,
+            "Plan is priced in a different currency".to_string(),
+        ));
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions SET amount = $3, updated_at = now()
+        WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL
+        "#,
+        Into::<Uuid>::into(id),
+        Into::<Uuid>::into(user.id),
+        plan.price.minor_units()
+    )
+    .execute(&database)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let subscription = fetch_subscription(&database, user.id, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .ok_or_else(not_found)?;
+
+    let subscription = SubscriptionResponse::new(subscription, locale)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok(Json(subscription))
+}
+
+/// Yearly cost of the subscription compared to the known plans of its service
+#[tracing::instrument(name = "Compare subscription plans", skip_all, fields(subscription_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn plan_comparison_handler(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+) -> Result<Json<PlanComparison>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+
+    let subscription = fetch_subscription(&database, user.id, id.into())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Subscription not found".to_string()))?;
+    let plans = fetch_service_plans(&database, &[subscription.service_id.into()])
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    PlanComparison::compute(&subscription, &plans)
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+/// Query of the [`SubscriptionRow`]s, the conditions on the subscription `s` follow
+fn select_subscription_rows<'a>() -> QueryBuilder<'a, Postgres> {
+    QueryBuilder::new(
+        r#"
+        SELECT
+            s.id, s.user_id, s.service_id, s.name, s.description, s.amount, s.currency, s.share,
+            s.next_renewal_date, s.billing_period, s.billing_period_unit, s.subscribed_at,
+            s.cancelled_at, s.cancel_reason, s.deleted_at, s.created_at, s.updated_at,
+            COALESCE(s.category, services.category) AS category,
+            ARRAY(
+                SELECT tag_id FROM subscription_tags WHERE subscription_id = s.id ORDER BY tag_id
+            ) AS tag_ids,
+            services.name AS service_name,
+            s.trial_ends_on, s.trial_amount, s.trial_reminder_days,
+            ARRAY(
+                SELECT cycles FROM subscription_price_phases
+                WHERE subscription_id = s.id ORDER BY position
+            ) AS phase_cycles,
+            ARRAY(
+                SELECT amount FROM subscription_price_phases
+                WHERE subscription_id = s.id ORDER BY position
+            ) AS phase_amounts,
+            s.contract_ends_on, s.contract_auto_renew, s.notice_period, s.notice_period_unit,
+            s.payment_method_id, s.usage_target_times, s.usage_target_unit, s.service_plan_id
+        FROM subscriptions s
+        JOIN services ON services.id = s.service_id
+        WHERE "#,
+    )
+}
+
+async fn fetch_subscription_rows(
+    database: &PgPool,
+    mut builder: QueryBuilder<'_, Postgres>,
+) -> Result<Vec<Subscription>, String> {
+    builder
+        .build_query_as::<SubscriptionRow>()
+        .fetch_all(database)
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(Subscription::try_from)
+        .collect()
+}
+
+/// All subscriptions of the user which were not deleted
+#[tracing::instrument(name = "Fetching user subscriptions", skip_all)]
+pub(crate) async fn fetch_user_subscriptions(
+    database: &PgPool,
+    user_id: UserId,
+) -> Result<Vec<Subscription>, String> {
+    let mut builder = select_subscription_rows();
+    builder
+        .push("s.user_id = ")
+        .push_bind(Uuid::from(user_id))
+        .push(" AND s.deleted_at IS NULL ORDER BY s.next_renewal_date, s.id");
+
+    fetch_subscription_rows(database, builder).await
+}
+
+/// Up to `limit` subscriptions of the user following the one with the ID `after`, for going
+/// through all of them in batches
+pub(crate) async fn fetch_user_subscriptions_after(
+    database: &PgPool,
+    user_id: UserId,
+    after: Option<SubscriptionId>,
+    limit: i64,
+) -> Result<Vec<Subscription>, String> {
+    let mut builder = select_subscription_rows();
+    builder
+        .push("s.user_id = ")
+        .push_bind(Uuid::from(user_id))
+        .push(" AND s.deleted_at IS NULL");
+    if let Some(after) = after {
+        builder.push(" AND s.id > ").push_bind(Uuid::from(after));
+    }
+    builder.push(" ORDER BY s.id LIMIT ").push_bind(limit);
+
+    fetch_subscription_rows(database, builder).await
+}
+
+/// Page of subscriptions of the user matching the `conditions`
+#[tracing::instrument(name = "Listing user subscriptions", skip(database))]
+async fn list_user_subscriptions(
+    database: &PgPool,
+    user_id: UserId,
+    conditions: &SubscriptionConditions,
+    query: &ListQuery<SubscriptionSortKey>,
+) -> Result<Page<Subscription>, String> {
+    let mut builder = select_subscription_rows();
+    builder.push("s.user_id = ");
+    builder.push_bind(Uuid::from(user_id));
+
+    builder.push(match conditions.status {
+        None => " AND s.deleted_at IS NULL",
+        Some(SubscriptionStatus::Active) => " AND s.deleted_at IS NULL AND s.cancelled_at IS NULL",
+        Some(SubscriptionStatus::Cancelled) => {
+            " AND s.deleted_at IS NULL AND s.cancelled_at IS NOT NULL"
+        }
+        Some(SubscriptionStatus::Deleted) => " AND s.deleted_at IS NOT NULL",
+    });
+    if let Some(service) = conditions.service {
+        builder
+            .push(" AND s.service_id = ")
+            .push_bind(Uuid::from(service));
+    }
+    if let Some(tag) = conditions.tag {
+        builder
+            .push(" AND EXISTS (SELECT 1 FROM subscription_tags WHERE subscription_id = s.id AND tag_id = ")
+            .push_bind(Uuid::from(tag))
+            .push(")");
+    }
+    if let Some(currency) = conditions.currency {
+        builder
+            .push(" AND s.currency = ")
+            .push_bind(currency.code());
+    }
+    if let Some(min_amount) = conditions.min_amount {
+        builder
+            .push(" AND s.amount >= ")
+            .push_bind(min_amount.minor_units());
+    }
+    if let Some(max_amount) = conditions.max_amount {
+        builder
+            .push(" AND s.amount <= ")
+            .push_bind(max_amount.minor_units());
+    }
+    if let Some(renews_from) = conditions.renews_from {
+        builder
+            .push(" AND s.next_renewal_date >= ")
+            .push_bind(renews_from);
+    }
+    if let Some(renews_to) = conditions.renews_to {
+        builder
+            .push(" AND s.next_renewal_date <= ")
+            .push_bind(renews_to);
+    }
+    query.push_keyset(&mut builder, "s.id");
+    query.push_order_and_limit(&mut builder, "s.id");
+
+    let rows: Vec<SubscriptionRow> = builder
+        .build_query_as()
+        .fetch_all(database)
+        .await
+        .map_err(|e| e.to_string())?;
+    let page = query.page(rows, |row| (query.sort.key.value(row), row.id));
+
+    Ok(Page {
+        items: page
+            .items
+            .into_iter()
+            .map(Subscription::try_from)
+            .collect::<Result<_, _>>()?,
+        next_cursor: page.next_cursor,
+    })
+}
+
+#[tracing::instrument(name = "Fetching user subscription", skip_all)]
+pub(crate) async fn fetch_subscription(
+    database: &PgPool,
+    user_id: UserId,
+    id: SubscriptionId,
+) -> Result<Option<Subscription>, String> {
+    let mut builder = select_subscription_rows();
+    builder
+        .push("s.id = ")
+        .push_bind(Uuid::from(id))
+        .push(" AND s.user_id = ")
+        .push_bind(Uuid::from(user_id))
+        .push(" AND s.deleted_at IS NULL");
+
+    Ok(fetch_subscription_rows(database, builder).await?.pop())
+}
+
+#[tracing::instrument(name = "Save subscription in the database", skip_all)]
+async fn insert_subscription(
+    database: &PgPool,
+    user_id: UserId,
+    subscription: NewSubscription,
+) -> Result<Subscription, String> {
+    let mut transaction = database.begin().await.map_err(|e| e.to_string())?;
+    let id = insert_subscription_with(&mut transaction, user_id, &subscription)
+        .await
+        .map_err(|e| e.to_string())?;
+    transaction.commit().await.map_err(|e| e.to_string())?;
+
+    fetch_subscription(database, user_id, id)
+        .await?
+        .ok_or_else(|| "Saved subscription not found".to_string())
+}
+
+/// Saves the subscription with its tags and price phases as part of the `transaction`
+pub(crate) async fn insert_subscription_with(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_id: UserId,
+    subscription: &NewSubscription,
+) -> sqlx::Result<SubscriptionId> {
+    let id = SubscriptionId::from(Uuid::new_v4());
+
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (
+            id, user_id, service_id, name, description, amount, currency, share,
+            next_renewal_date, billing_period, billing_period_unit, subscribed_at, category,
+            trial_ends_on, trial_amount, trial_reminder_days,
+            contract_ends_on, contract_auto_renew, notice_period, notice_period_unit,
+            payment_method_id, usage_target_times, usage_target_unit, service_plan_id
+        )
+        VALUES (
+            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19,
+            $20, $21, $22, $23, $24
+        )
+        "#,
+        Into::<Uuid>::into(id),
+        Into::<Uuid>::into(user_id),
+        Into::<Uuid>::into(subscription.service_id),
+        subscription.name,
+        subscription.description,
+        subscription.price.minor_units(),
+        subscription.price.currency().code(),
+        i16::from(subscription.share),
+        subscription.next_renewal_date,
+        i16::from(subscription.billing_period),
+        subscription.billing_period_unit.as_ref(),
+        subscription.subscribed_at,
+        subscription.category.as_ref().map(AsRef::<str>::as_ref),
+        subscription.trial.as_ref().map(|trial| trial.ends_on),
+        subscription
+            .trial
+            .as_ref()
+            .map(|trial| trial.price.minor_units()),
+        subscription
+            .trial
+            .as_ref()
+            .map(|trial| i16::from(trial.reminder_days)),
+        subscription
+            .contract
+            .as_ref()
+            .map(|contract| contract.ends_on),
+        subscription
+            .contract
+            .as_ref()
+            .map(|contract| contract.auto_renew),
+        subscription
+            .contract
+            .as_ref()
+            .map(|contract| i16::from(contract.notice_period)),
+        subscription
+            .contract
+            .as_ref()
+            .map(|contract| contract.notice_period_unit.as_ref()),
+        subscription.payment_method_id.map(Uuid::from),
+        subscription.usage_target.map(|target| target.times as i16),
+        subscription
+            .usage_target
+            .as_ref()
+            .map(|target| target.per.as_ref()),
+        subscription.plan_id.map(Uuid::from)
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    replace_subscription_tags(transaction, id, &subscription.tag_ids).await?;
+    insert_price_phases(transaction, id, &subscription.price_phases).await?;
+
+    Ok(id)
+}
+
+async fn replace_subscription_tags(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscription_id: SubscriptionId,
+    tag_ids: &[TagId],
+) -> sqlx::Result<()> {
+    let tag_ids: Vec<Uuid> = tag_ids.iter().copied().map(Uuid::from).collect();
+
+    sqlx::query!(
+        "DELETE FROM subscription_tags WHERE subscription_id = $1",
+        Into::<Uuid>::into(subscription_id)
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO subscription_tags (subscription_id, tag_id)
+        SELECT $1, tag_id FROM UNNEST($2::uuid[]) AS tag_id
+        ON CONFLICT DO NOTHING
+        "#,
+        Into::<Uuid>::into(subscription_id),
+        &tag_ids
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(())
+}
+
+async fn insert_price_phases(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscription_id: SubscriptionId,
+    price_phases: &[PricePhase],
+) -> sqlx::Result<()> {
+    let cycles: Vec<i16> = price_phases
+        .iter()
+        .map(|phase| phase.cycles as i16)
+        .collect();
+    let amounts: Vec<i64> = price_phases
+        .iter()
+        .map(|phase| phase.price.minor_units())
+        .collect();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO subscription_price_phases (subscription_id, position, cycles, amount)
+        SELECT $1, position::smallint, cycles, amount
+        FROM UNNEST($2::smallint[], $3::bigint[]) WITH ORDINALITY AS phase(cycles, amount, position)
+        "#,
+        Into::<Uuid>::into(subscription_id),
+        &cycles,
+        &amounts
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+    use iso_currency::Currency;
+    use uuid::Uuid;
+
+    use crate::{
+        domain::Money,
+        routes::{ListQuery, Sort, SortDirection},
+        test_database::create_migrated_db_for_tests,
+    };
+
+    use super::{list_user_subscriptions, SubscriptionConditions, SubscriptionSortKey};
+
+    #[test]
+    fn sorting_by_amount_requires_a_currency() {
+        let mut conditions = SubscriptionConditions::default();
+        assert_err!(conditions.ensure_sortable_by(SubscriptionSortKey::Amount));
+        assert_ok!(conditions.ensure_sortable_by(SubscriptionSortKey::Name));
+
+        conditions.currency = Some(Currency::EUR);
+        assert_ok!(conditions.ensure_sortable_by(SubscriptionSortKey::Amount));
+    }
+
+    #[tokio::test]
+    async fn amounts_are_compared_within_the_filtered_currency() {
+        let database = create_migrated_db_for_tests("test_subscriptions").await;
+        let user_id = Uuid::new_v4();
+        let service_id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO users (id, login, role, password_hash) VALUES ($1, 'user', 'user', '')",
+        )
+        .bind(user_id)
+        .execute(&database)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO services (id, name) VALUES ($1, 'Service')")
+            .bind(service_id)
+            .execute(&database)
+            .await
+            .unwrap();
+        // 1500 yen are worth less than 10 euro although they are more minor units
+        for (name, amount, currency) in [
+            ("Euro 10", 1000, "EUR"),
+            ("Euro 20", 2000, "EUR"),
+            ("Yen 1500", 1500, "JPY"),
+        ] {
+            sqlx::query(
+                r#"
+                INSERT INTO subscriptions (
+                    id, user_id, service_id, name, amount, currency, next_renewal_date,
+                    billing_period, billing_period_unit
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, '2023-11-01', 1, 'month')
+                "#,
+            )
+            .bind(Uuid::new_v4())
+            .bind(user_id)
+            .bind(service_id)
+            .bind(name)
+            .bind(amount as i64)
+            .bind(currency)
+            .execute(&database)
+            .await
+            .unwrap();
+        }
+        let conditions = SubscriptionConditions {
+            currency: Some(Currency::EUR),
+            min_amount: Some(Money::new(1000, Currency::EUR)),
+            ..Default::default()
+        };
+        let query = ListQuery {
+            sort: Sort {
+                key: SubscriptionSortKey::Amount,
+                direction: SortDirection::Desc,
+            },
+            limit: 10,
+            after: None,
+        };
+
+        let page = list_user_subscriptions(&database, user_id.into(), &conditions, &query)
+            .await
+            .unwrap();
+
+        let names: Vec<_> = page.items.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["Euro 20", "Euro 10"]);
+    }
+}
@@ -0,0 +1,164 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use hyper::StatusCode;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    auth::{current_user, AuthContext},
+    domain::{Tag, TagId, TagName, UserId},
+    startup::AppState,
+};
+
+#[derive(Debug, serde::Deserialize)]
+pub struct TagInput {
+    name: String,
+}
+
+#[tracing::instrument(name = "Tags index", skip_all)]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn tags_index(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+) -> Result<Json<Vec<Tag>>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+
+    let tags = fetch_user_tags(&database, user.id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok(Json(tags))
+}
+
+#[tracing::instrument(name = "Create tag", skip_all, fields(tag_name = %input.name))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn create_tag(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Json(input): Json<TagInput>,
+) -> Result<Json<Tag>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let name = TagName::parse(input.name).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let tag = sqlx::query_as!(
+        Tag,
+        r#"
+        INSERT INTO tags (id, user_id, name)
+        VALUES ($1, $2, $3)
+        RETURNING id, name, created_at
+        "#,
+        Uuid::new_v4(),
+        Into::<Uuid>::into(user.id),
+        name.as_ref()
+    )
+    .fetch_one(&database)
+    .await
+    .map_err(|e| tag_error(e, &name))?;
+
+    Ok(Json(tag))
+}
+
+#[tracing::instrument(name = "Rename tag", skip_all, fields(tag_id = %id, tag_name = %input.name))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn update_tag(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+    Json(input): Json<TagInput>,
+) -> Result<Json<Tag>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let name = TagName::parse(input.name).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let tag = sqlx::query_as!(
+        Tag,
+        r#"
+        UPDATE tags SET name = $3
+        WHERE id = $1 AND user_id = $2
+        RETURNING id, name, created_at
+        "#,
+        id,
+        Into::<Uuid>::into(user.id),
+        name.as_ref()
+    )
+    .fetch_optional(&database)
+    .await
+    .map_err(|e| tag_error(e, &name))?
+    .ok_or_else(|| (StatusCode::NOT_FOUND, "Tag not found".to_string()))?;
+
+    Ok(Json(tag))
+}
+
+/// Deletes the tag and removes it from all subscriptions
+#[tracing::instrument(name = "Delete tag", skip_all, fields(tag_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn delete_tag(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let user = current_user(auth)?;
+
+    let result = sqlx::query!(
+        "DELETE FROM tags WHERE id = $1 AND user_id = $2",
+        id,
+        Into::<Uuid>::into(user.id)
+    )
+    .execute(&database)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Tag not found".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[tracing::instrument(name = "Fetching user tags", skip_all)]
+pub(crate) async fn fetch_user_tags(
+    database: &PgPool,
+    user_id: UserId,
+) -> Result<Vec<Tag>, String> {
+    sqlx::query_as!(
+        Tag,
+        "SELECT id, name, created_at FROM tags WHERE user_id = $1 ORDER BY lower(name)",
+        Into::<Uuid>::into(user_id)
+    )
+    .fetch_all(database)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Checks that all `tag_ids` belong to the user
+pub(crate) async fn ensure_user_tags(
+    database: &PgPool,
+    user_id: UserId,
+    tag_ids: &[TagId],
+) -> Result<(), (StatusCode, String)> {
+    let tags = fetch_user_tags(database, user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    match tag_ids
+        .iter()
+        .find(|id| !tags.iter().any(|tag| tag.id == **id))
+    {
+        Some(id) => Err((
+            StatusCode::BAD_REQUEST,
+            format!("Tag {} does not exist.", Uuid::from(*id)),
+        )),
+        None => Ok(()),
+    }
+}
+
+fn tag_error(error: sqlx::Error, name: &TagName) -> (StatusCode, String) {
+    match error {
+        sqlx::Error::Database(e) if e.is_unique_violation() => (
+            StatusCode::CONFLICT,
+            format!("Tag {} already exists.", name.as_ref()),
+        ),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
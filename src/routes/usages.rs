@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use chrono::{NaiveDate, Utc};
+use hyper::StatusCode;
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::{
+    auth::{current_user, AuthContext},
+    domain::{SubscriptionId, Usage, UsageSummary, UserId},
+    startup::AppState,
+};
+
+use super::{
+    fetch_subscription, ListQuery, Page, PageParams, Sort, SortDirection, SortKey, SortValue,
+    SortValueType,
+};
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageInput {
+    /// Today when missing
+    used_on: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum UsageSortKey {
+    UsedOn,
+}
+
+impl SortKey for UsageSortKey {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "usedOn" => Ok(Self::UsedOn),
+            other => Err(format!("Usages cannot be sorted by {}.", other)),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::UsedOn => "usedOn",
+        }
+    }
+
+    fn column(&self) -> &'static str {
+        match self {
+            Self::UsedOn => "used_on",
+        }
+    }
+
+    fn value_type(&self) -> SortValueType {
+        match self {
+            Self::UsedOn => SortValueType::Date,
+        }
+    }
+}
+
+/// Logs that the subscription was used
+#[tracing::instrument(name = "Create usage", skip_all, fields(subscription_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn create_usage(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+    Json(input): Json<UsageInput>,
+) -> Result<Json<Usage>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let id = SubscriptionId::from(id);
+    let today = Utc::now().date_naive();
+    let used_on = input.used_on.unwrap_or(today);
+    if used_on > today {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Usage cannot be logged in advance.".into(),
+        ));
+    }
+    ensure_user_subscription(&database, user.id, id).await?;
+
+    let usage = sqlx::query_as!(
+        Usage,
+        r#"
+        INSERT INTO subscription_usages (id, subscription_id, used_on)
+        VALUES ($1, $2, $3)
+        RETURNING id, used_on, created_at
+        "#,
+        Uuid::new_v4(),
+        Into::<Uuid>::into(id),
+        used_on
+    )
+    .fetch_one(&database)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(usage))
+}
+
+/// Check-ins of the subscription, latest first
+#[tracing::instrument(name = "Usages index", skip_all, fields(subscription_id = %id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn usages_index(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+    Query(page): Query<PageParams>,
+) -> Result<Json<Page<Usage>>, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let id = SubscriptionId::from(id);
+    let default_sort = Sort {
+        key: UsageSortKey::UsedOn,
+        direction: SortDirection::Desc,
+    };
+    let query = ListQuery::parse(page, default_sort).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    ensure_user_subscription(&database, user.id, id).await?;
+
+    let mut builder = QueryBuilder::<Postgres>::new(
+        "SELECT id, used_on, created_at FROM subscription_usages WHERE subscription_id = ",
+    );
+    builder.push_bind(Uuid::from(id));
+    query.push_keyset(&mut builder, "id");
+    query.push_order_and_limit(&mut builder, "id");
+
+    let usages: Vec<Usage> = builder
+        .build_query_as()
+        .fetch_all(&database)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(query.page(usages, |usage| {
+        (SortValue::Date(usage.used_on), usage.id.into())
+    })))
+}
+
+#[tracing::instrument(name = "Delete usage", skip_all, fields(subscription_id = %id, usage_id = %usage_id))]
+#[axum::debug_handler(state = crate::startup::AppState)]
+pub(crate) async fn delete_usage(
+    State(AppState { database, .. }): State<AppState>,
+    auth: AuthContext,
+    Path((id, usage_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let user = current_user(auth)?;
+    let id = SubscriptionId::from(id);
+    ensure_user_subscription(&database, user.id, id).await?;
+
+    let result = sqlx::query!(
+        "DELETE FROM subscription_usages WHERE id = $1 AND subscription_id = $2",
+        usage_id,
+        Into::<Uuid>::into(id)
+    )
+    .execute(&database)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Usage not found".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Number of check-ins since `since` and the latest check-in of every subscription of the user
+#[tracing::instrument(name = "Fetching usage summaries", skip(database))]
+pub(crate) async fn fetch_usage_summaries(
+    database: &PgPool,
+    user_id: UserId,
+    since: NaiveDate,
+) -> Result<HashMap<SubscriptionId, UsageSummary>, String> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            u.subscription_id,
+            COUNT(*) FILTER (WHERE u.used_on >= $2) AS "uses!",
+            MAX(u.used_on) AS last_used_on
+        FROM subscription_usages u
+        JOIN subscriptions s ON s.id = u.subscription_id
+        WHERE s.user_id = $1
+        GROUP BY u.subscription_id
+        "#,
+        Into::<Uuid>::into(user_id),
+        since
+    )
+    .fetch_all(database)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    rows.into_iter()
+        .map(|row| {
+            let summary = UsageSummary {
+                uses: row.uses.try_into().map_err(|_| "invalid usage count")?,
+                last_used_on: row.last_used_on,
+            };
+            Ok((row.subscription_id.into(), summary))
+        })
+        .collect()
+}
+
+async fn ensure_user_subscription(
+    database: &PgPool,
+    user_id: UserId,
+    id: SubscriptionId,
+) -> Result<(), (StatusCode, String)> {
+    fetch_subscription(database, user_id, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .map(|_| ())
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Subscription not found".to_string()))
+}
@@ -0,0 +1,312 @@
+use std::collections::HashSet;
+
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    domain::{normalize_name, Category, NewServiceAlias, ServiceName, ServiceStatus, ServiceUrl},
+    routes::insert_service_aliases,
+};
+
+/// Catalog of popular services shipped with the application
+pub const BUNDLED_CATALOG: &str = include_str!("../data/services.json");
+
+#[derive(Debug, serde::Deserialize)]
+struct CatalogFile {
+    version: i32,
+    services: Vec<CatalogEntry>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CatalogEntry {
+    key: String,
+    name: String,
+    category: String,
+    #[serde(default)]
+    aliases: Vec<String>,
+    website_url: Option<String>,
+    cancellation_url: Option<String>,
+}
+
+/// Validated catalog file, services are only updated from a catalog with a higher `version`
+#[derive(Debug)]
+pub struct SeedCatalog {
+    pub version: i32,
+    pub services: Vec<SeedService>,
+}
+
+#[derive(Debug)]
+pub struct SeedService {
+    /// Stable identifier of the service across catalog versions
+    pub key: String,
+    pub name: ServiceName,
+    pub category: Category,
+    pub aliases: Vec<NewServiceAlias>,
+    pub website_url: Option<ServiceUrl>,
+    pub cancellation_url: Option<ServiceUrl>,
+}
+
+impl SeedCatalog {
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let file: CatalogFile = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        if file.version < 1 {
+            return Err(format!("{} is not a valid catalog version.", file.version));
+        }
+
+        let mut keys = HashSet::new();
+        let services = file
+            .services
+            .into_iter()
+            .map(|entry| {
+                let key = entry.key.trim().to_owned();
+                let is_valid_key = !key.is_empty()
+                    && key
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+                if !is_valid_key {
+                    return Err(format!("{} is not a valid catalog key.", entry.key));
+                }
+                if !keys.insert(key.clone()) {
+                    return Err(format!("{} is listed more than once.", key));
+                }
+                let in_entry = |e: String| format!("{}: {}", key, e);
+
+                Ok(SeedService {
+                    name: ServiceName::parse(entry.name).map_err(in_entry)?,
+                    category: Category::parse(&entry.category).map_err(in_entry)?,
+                    aliases: entry
+                        .aliases
+                        .iter()
+                        .map(|alias| NewServiceAlias::parse(alias))
+                        .collect::<Result<_, _>>()
+                        .map_err(in_entry)?,
+                    website_url: entry
+                        .website_url
+                        .as_deref()
+                        .map(ServiceUrl::parse)
+                        .transpose()
+                        .map_err(in_entry)?,
+                    cancellation_url: entry
+                        .cancellation_url
+                        .as_deref()
+                        .map(ServiceUrl::parse)
+                        .transpose()
+                        .map_err(in_entry)?,
+                    key,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(Self {
+            version: file.version,
+            services,
+        })
+    }
+}
+
+/// What [`seed_services`] did with the services of the catalog
+#[derive(Debug, Default, PartialEq)]
+pub struct SeedSummary {
+    pub inserted: usize,
+    pub updated: usize,
+    /// Existing services with the same name which were taken over by the catalog
+    pub linked: usize,
+    /// Up to date, edited by an admin or no longer approved
+    pub skipped: usize,
+}
+
+/// Upserts the catalog, running it again with the same file changes nothing.
+///
+/// Services are matched by their catalog key, then by their normalized name so that services
+/// created by admins before the catalog existed are not duplicated. A service is only updated
+/// while it is approved and no admin edited it, linked services keep their name and only get
+/// the URLs they are missing.
+#[tracing::instrument(name = "Seed services", skip_all, fields(version = catalog.version))]
+pub async fn seed_services(
+    database: &PgPool,
+    catalog: &SeedCatalog,
+) -> Result<SeedSummary, String> {
+    let mut transaction = database.begin().await.map_err(|e| e.to_string())?;
+    let mut summary = SeedSummary::default();
+
+    for service in &catalog.services {
+        let seeded = sqlx::query!(
+            r#"
+            SELECT id, status, seed_version, edited_at FROM services
+            WHERE seed_key = $1
+            FOR UPDATE
+            "#,
+            service.key
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        if let Some(seeded) = seeded {
+            let is_outdated = seeded.seed_version.unwrap_or_default() < catalog.version;
+            if seeded.status != ServiceStatus::Approved.as_ref()
+                || seeded.edited_at.is_some()
+                || !is_outdated
+            {
+                summary.skipped += 1;
+                continue;
+            }
+            update_seeded_service(&mut transaction, seeded.id, service, catalog.version).await?;
+            summary.updated += 1;
+        } else if let Some(id) = link_service(&mut transaction, service, catalog.version).await? {
+            summary.linked += 1;
+            tracing::info!("Linked {} to the existing service {}", service.key, id);
+        } else {
+            insert_seeded_service(&mut transaction, service, catalog.version).await?;
+            summary.inserted += 1;
+        }
+    }
+
+    transaction.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(summary)
+}
+
+async fn update_seeded_service(
+    transaction: &mut Transaction<'_, Postgres>,
+    id: Uuid,
+    service: &SeedService,
+    version: i32,
+) -> Result<(), String> {
+    sqlx::query!(
+        r#"
+        UPDATE services SET
+          name = $2, normalized_name = $3, category = $4, website_url = $5,
+          cancellation_url = $6, seed_version = $7
+        WHERE id = $1
+        "#,
+        id,
+        service.name.as_ref(),
+        normalize_name(service.name.as_ref()),
+        service.category.as_ref(),
+        service.website_url.as_ref().map(AsRef::<str>::as_ref),
+        service.cancellation_url.as_ref().map(AsRef::<str>::as_ref),
+        version
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|e| e.to_string())?;
+    insert_service_aliases(transaction, id, service.aliases.clone()).await?;
+
+    Ok(())
+}
+
+/// Takes over an approved service of the same name which is not in the catalog yet
+async fn link_service(
+    transaction: &mut Transaction<'_, Postgres>,
+    service: &SeedService,
+    version: i32,
+) -> Result<Option<Uuid>, String> {
+    let linked = sqlx::query!(
+        r#"
+        UPDATE services SET
+          seed_key = $3, seed_version = $4,
+          website_url = COALESCE(website_url, $5),
+          cancellation_url = COALESCE(cancellation_url, $6)
+        WHERE id = (
+          SELECT id FROM services
+          WHERE normalized_name = $1 AND status = $2 AND seed_key IS NULL
+          ORDER BY created_at, id
+          LIMIT 1
+        )
+        RETURNING id, edited_at
+        "#,
+        normalize_name(service.name.as_ref()),
+        ServiceStatus::Approved.as_ref(),
+        service.key,
+        version,
+        service.website_url.as_ref().map(AsRef::<str>::as_ref),
+        service.cancellation_url.as_ref().map(AsRef::<str>::as_ref)
+    )
+    .fetch_optional(&mut **transaction)
+    .await
+    .map_err(|e| e.to_string())?;
+    let Some(linked) = linked else {
+        return Ok(None);
+    };
+
+    // Admins may have removed some of the aliases on purpose
+    if linked.edited_at.is_none() {
+        insert_service_aliases(transaction, linked.id, service.aliases.clone()).await?;
+    }
+
+    Ok(Some(linked.id))
+}
+
+async fn insert_seeded_service(
+    transaction: &mut Transaction<'_, Postgres>,
+    service: &SeedService,
+    version: i32,
+) -> Result<(), String> {
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO services (
+          id, name, category, status, normalized_name, website_url, cancellation_url, seed_key,
+          seed_version
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        RETURNING id
+        "#,
+        Uuid::new_v4(),
+        service.name.as_ref(),
+        service.category.as_ref(),
+        ServiceStatus::Approved.as_ref(),
+        normalize_name(service.name.as_ref()),
+        service.website_url.as_ref().map(AsRef::<str>::as_ref),
+        service.cancellation_url.as_ref().map(AsRef::<str>::as_ref),
+        service.key,
+        version
+    )
+    .fetch_one(&mut **transaction)
+    .await
+    .map_err(|e| e.to_string())?;
+    insert_service_aliases(transaction, id, service.aliases.clone()).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+
+    use super::{SeedCatalog, BUNDLED_CATALOG};
+
+    fn catalog(services: &str) -> String {
+        format!(r#"{{"version": 1, "services": [{}]}}"#, services)
+    }
+
+    #[test]
+    fn bundled_catalog_is_valid() {
+        let catalog = assert_ok!(SeedCatalog::parse(BUNDLED_CATALOG));
+        assert!(!catalog.services.is_empty());
+    }
+
+    #[test]
+    fn keys_must_be_unique() {
+        let entry = r#"{"key": "mubi", "name": "Mubi", "category": "streaming"}"#;
+
+        assert_ok!(SeedCatalog::parse(&catalog(entry)));
+        assert_err!(SeedCatalog::parse(&catalog(&format!(
+            "{},{}",
+            entry, entry
+        ))));
+    }
+
+    #[test]
+    fn invalid_entries_are_rejected() {
+        for entry in [
+            r#"{"key": "Mubi", "name": "Mubi", "category": "streaming"}"#,
+            r#"{"key": "mubi", "name": "Mubi", "category": "cinema"}"#,
+            r#"{"key": "mubi", "name": "Mubi", "category": "streaming", "aliases": ["***"]}"#,
+            r#"{"key": "mubi", "name": "Mubi", "category": "streaming", "websiteUrl": "mubi.com"}"#,
+        ] {
+            assert_err!(SeedCatalog::parse(&catalog(entry)));
+        }
+    }
+}
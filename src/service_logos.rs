@@ -0,0 +1,118 @@
+//! Logos of services, normalised into square PNGs of a few fixed sizes
+use std::io::Cursor;
+
+use image::{
+    imageops::{self, FilterType},
+    DynamicImage, ImageFormat, RgbaImage,
+};
+use uuid::Uuid;
+
+/// Edge lengths in pixels the logos are served in, the largest one is the default
+pub const LOGO_SIZES: [u32; 3] = [32, 64, 256];
+pub const MAX_LOGO_BYTES: usize = 1024 * 1024;
+/// Larger images are rejected before decoding them
+const MAX_LOGO_DIMENSION: u32 = 4096;
+
+/// Formats accepted for uploads, by their content type
+const LOGO_FORMATS: [(&str, ImageFormat); 3] = [
+    ("image/png", ImageFormat::Png),
+    ("image/jpeg", ImageFormat::Jpeg),
+    ("image/webp", ImageFormat::WebP),
+];
+
+/// Key of a logo in the blob store, every upload gets a new `version` so that cached images of
+/// earlier uploads do not have to be invalidated
+pub fn logo_key(service_id: Uuid, version: i32, size: u32) -> String {
+    format!("logos/{}/{}/{}.png", service_id, version, size)
+}
+
+/// Checks the uploaded image and renders it centred on a transparent square in each of the
+/// [`LOGO_SIZES`], returned in the same order
+pub fn normalize_logo(bytes: &[u8], content_type: &str) -> Result<Vec<Vec<u8>>, String> {
+    let format = LOGO_FORMATS
+        .into_iter()
+        .find(|(accepted, _)| content_type.trim().eq_ignore_ascii_case(accepted))
+        .map(|(_, format)| format)
+        .ok_or_else(|| format!("{} is not a supported logo type.", content_type))?;
+    if bytes.len() > MAX_LOGO_BYTES {
+        return Err(format!(
+            "Logos can be at most {} KiB.",
+            MAX_LOGO_BYTES / 1024
+        ));
+    }
+
+    let mut limits = image::io::Limits::default();
+    limits.max_image_width = Some(MAX_LOGO_DIMENSION);
+    limits.max_image_height = Some(MAX_LOGO_DIMENSION);
+    let mut reader = image::io::Reader::with_format(Cursor::new(bytes), format);
+    reader.limits(limits);
+    let image = reader
+        .decode()
+        .map_err(|e| format!("Logo could not be read: {}", e))?;
+
+    LOGO_SIZES
+        .into_iter()
+        .map(|size| encode_png(&square(&image, size)))
+        .collect()
+}
+
+/// Scales the image to fit into the square keeping its aspect ratio
+fn square(image: &DynamicImage, size: u32) -> RgbaImage {
+    let scaled = image.resize(size, size, FilterType::Lanczos3).to_rgba8();
+    let mut canvas = RgbaImage::new(size, size);
+    let x = (size - scaled.width()) / 2;
+    let y = (size - scaled.height()) / 2;
+    imageops::overlay(&mut canvas, &scaled, x.into(), y.into());
+
+    canvas
+}
+
+fn encode_png(image: &RgbaImage) -> Result<Vec<u8>, String> {
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| e.to_string())?;
+
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use claims::assert_err;
+    use image::{ImageFormat, Rgba, RgbaImage};
+
+    use super::{normalize_logo, LOGO_SIZES};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        RgbaImage::from_pixel(width, height, Rgba([200, 20, 20, 255]))
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn logos_are_rendered_into_squares() {
+        let logos = normalize_logo(&png(120, 40), "image/png").unwrap();
+
+        assert_eq!(logos.len(), LOGO_SIZES.len());
+        for (logo, size) in logos.iter().zip(LOGO_SIZES) {
+            let logo = image::load_from_memory_with_format(logo, ImageFormat::Png)
+                .unwrap()
+                .to_rgba8();
+            assert_eq!(logo.dimensions(), (size, size));
+            // Letterboxed with transparent bands above and below
+            assert_eq!(logo.get_pixel(size / 2, 0)[3], 0);
+            assert_eq!(logo.get_pixel(size / 2, size / 2)[3], 255);
+        }
+    }
+
+    #[test]
+    fn unsupported_or_broken_images_are_rejected() {
+        assert_err!(normalize_logo(&png(10, 10), "image/svg+xml"));
+        assert_err!(normalize_logo(&png(10, 10), "image/jpeg"));
+        assert_err!(normalize_logo(b"not an image", "image/png"));
+    }
+}
@@ -129,31 +129,14 @@ impl SessionStore for PostgresSessionStore {
 
 #[cfg(test)]
 mod tests {
-    use crate::configuration::get_configuration;
+    use crate::test_database::create_db_for_tests;
 
     use super::*;
     use chrono::DateTime;
     use std::time::Duration;
 
-    async fn create_db_for_tests() -> PgPool {
-        let config = get_configuration().expect("Failed to read configuration");
-        let connection_opts = config.database.without_db();
-
-        let pool = PgPool::connect_lazy_with(connection_opts.clone());
-
-        let db_name = uuid::Uuid::new_v4().to_string().replace('-', "");
-        let db_name = format!("test_sessions_{db_name}");
-        sqlx::query(&format!("CREATE DATABASE {db_name}"))
-            .execute(&pool)
-            .await
-            .expect("Failed to create db for tests");
-
-        let connection_opts = connection_opts.database(&db_name);
-        PgPool::connect_lazy_with(connection_opts)
-    }
-
     async fn test_store() -> PostgresSessionStore {
-        let database = create_db_for_tests().await;
+        let database = create_db_for_tests("test_sessions").await;
         let store = PostgresSessionStore::new(database, "async_sessions");
 
         store
@@ -1,15 +1,36 @@
-use std::{net::TcpListener, time::Duration};
+use std::{net::TcpListener, sync::Arc, time::Duration};
 
 use axum::{
-    routing::{get, post},
+    extract::DefaultBodyLimit,
+    handler::Handler,
+    routing::{delete, get, patch, post, put},
     Router,
 };
 use sqlx::{postgres::PgPoolOptions, PgPool};
 
 use crate::{
     auth::{setup_auth, RequireAuth},
+    blob_store::{BlobStore, LocalBlobStore},
     configuration::{AuthSettings, DatabaseSettings, Settings},
-    routes::{create_service, health_check, login_handler, register_handler, services_index},
+    domain::{UserRole, MAX_ATTACHMENT_BYTES},
+    routes::{
+        apply_plan_price, approve_service, attachment_handler, attachments_index, budgets_index,
+        cancellation_guide_handler, cancellation_guide_versions, create_attachment,
+        create_payment_method, create_service, create_service_alias, create_service_plan,
+        create_subscription, create_tag, create_usage, delete_attachment, delete_budget,
+        delete_payment_method, delete_profile, delete_service, delete_service_alias,
+        delete_service_logo, delete_tag, delete_usage, digest_handler, export_attachment_files,
+        export_attachments, export_payments, export_subscriptions, health_check,
+        import_subscriptions, login_handler, merge_service, payment_methods_index,
+        plan_comparison_handler, profile_handler, register_handler, reject_service,
+        save_cancellation_guide, search_services, service_aliases_index, service_handler,
+        service_logo_handler, service_plans_index, service_suggestions_index, services_index,
+        set_budget, set_subscription_payment_method, set_subscription_tags,
+        set_subscription_usage_target, simulate_handler, stats_handler, subscriptions_index,
+        tags_index, update_payment_method, update_profile, update_service, update_service_plan,
+        update_tag, upload_service_logo, usages_index,
+    },
+    subscription_import::MAX_IMPORT_BYTES,
 };
 
 pub struct Application {
@@ -20,6 +41,8 @@ pub struct Application {
 #[derive(Clone)]
 pub struct AppState {
     pub database: PgPool,
+    /// Uploaded files such as service logos
+    pub blobs: Arc<dyn BlobStore>,
 }
 
 impl Application {
@@ -36,6 +59,7 @@ impl Application {
 
         let state = AppState {
             database: connection_pool.clone(),
+            blobs: Arc::new(LocalBlobStore::new(&configuration.storage.blob_path)),
         };
 
         let router = app(state, &configuration.auth).await;
@@ -53,7 +77,7 @@ impl Application {
 }
 
 pub async fn app(state: AppState, auth_config: &AuthSettings) -> Router {
-    let AppState { database } = state.clone();
+    let AppState { database, .. } = state.clone();
 
     let (auth_layer, session_layer) = setup_auth(database, auth_config).await;
 
@@ -74,6 +98,184 @@ fn api_router(state: AppState) -> Router {
             "/services",
             post(create_service).layer(RequireAuth::login()),
         )
+        .route("/services/search", get(search_services))
+        .route("/services/:id", get(service_handler))
+        .route(
+            "/services/:id",
+            patch(update_service)
+                .delete(delete_service)
+                .layer(RequireAuth::login()),
+        )
+        .route("/services/:id/aliases", get(service_aliases_index))
+        .route(
+            "/services/:id/aliases",
+            post(create_service_alias).layer(RequireAuth::login()),
+        )
+        .route(
+            "/services/:id/aliases/:alias_id",
+            delete(delete_service_alias).layer(RequireAuth::login()),
+        )
+        .route(
+            "/services/suggestions",
+            get(service_suggestions_index).layer(RequireAuth::login_with_role(UserRole::Admin..)),
+        )
+        .route(
+            "/services/:id/approve",
+            post(approve_service).layer(RequireAuth::login_with_role(UserRole::Admin..)),
+        )
+        .route(
+            "/services/:id/reject",
+            post(reject_service).layer(RequireAuth::login_with_role(UserRole::Admin..)),
+        )
+        .route(
+            "/services/:id/merge",
+            post(merge_service).layer(RequireAuth::login_with_role(UserRole::Admin..)),
+        )
+        .route(
+            "/services/:id/cancellation-guide",
+            get(cancellation_guide_handler),
+        )
+        .route(
+            "/services/:id/cancellation-guide",
+            put(save_cancellation_guide).layer(RequireAuth::login()),
+        )
+        .route(
+            "/services/:id/cancellation-guide/versions",
+            get(cancellation_guide_versions),
+        )
+        .route("/services/:id/logo", get(service_logo_handler))
+        .route(
+            "/services/:id/logo",
+            put(upload_service_logo)
+                .delete(delete_service_logo)
+                .layer(RequireAuth::login_with_role(UserRole::Admin..)),
+        )
+        .route("/services/:id/plans", get(service_plans_index))
+        .route(
+            "/services/:id/plans",
+            post(create_service_plan).layer(RequireAuth::login()),
+        )
+        .route(
+            "/services/:id/plans/:plan_id",
+            patch(update_service_plan).layer(RequireAuth::login_with_role(UserRole::Admin..)),
+        )
+        .route(
+            "/profile",
+            get(profile_handler)
+                .patch(update_profile)
+                .delete(delete_profile)
+                .layer(RequireAuth::login()),
+        )
+        .route(
+            "/subscriptions",
+            get(subscriptions_index)
+                .post(create_subscription)
+                .layer(RequireAuth::login()),
+        )
+        .route(
+            "/subscriptions/import",
+            post(import_subscriptions.layer(DefaultBodyLimit::max(MAX_IMPORT_BYTES)))
+                .layer(RequireAuth::login()),
+        )
+        .route(
+            "/subscriptions/:id/tags",
+            put(set_subscription_tags).layer(RequireAuth::login()),
+        )
+        .route(
+            "/subscriptions/:id/plan-price",
+            post(apply_plan_price).layer(RequireAuth::login()),
+        )
+        .route(
+            "/subscriptions/:id/plan-comparison",
+            get(plan_comparison_handler).layer(RequireAuth::login()),
+        )
+        .route(
+            "/subscriptions/:id/payment-method",
+            put(set_subscription_payment_method).layer(RequireAuth::login()),
+        )
+        .route(
+            "/subscriptions/:id/usage-target",
+            put(set_subscription_usage_target).layer(RequireAuth::login()),
+        )
+        .route(
+            "/subscriptions/:id/usages",
+            get(usages_index)
+                .post(create_usage)
+                .layer(RequireAuth::login()),
+        )
+        .route(
+            "/subscriptions/:id/usages/:usage_id",
+            delete(delete_usage).layer(RequireAuth::login()),
+        )
+        .route(
+            "/subscriptions/:id/attachments",
+            get(attachments_index)
+                .post(create_attachment.layer(DefaultBodyLimit::max(MAX_ATTACHMENT_BYTES)))
+                .layer(RequireAuth::login()),
+        )
+        .route(
+            "/attachments/:id",
+            get(attachment_handler)
+                .delete(delete_attachment)
+                .layer(RequireAuth::login()),
+        )
+        .route(
+            "/tags",
+            get(tags_index).post(create_tag).layer(RequireAuth::login()),
+        )
+        .route(
+            "/tags/:id",
+            patch(update_tag)
+                .delete(delete_tag)
+                .layer(RequireAuth::login()),
+        )
+        .route(
+            "/budgets",
+            get(budgets_index)
+                .put(set_budget)
+                .layer(RequireAuth::login()),
+        )
+        .route(
+            "/budgets/:id",
+            delete(delete_budget).layer(RequireAuth::login()),
+        )
+        .route(
+            "/payment-methods",
+            get(payment_methods_index)
+                .post(create_payment_method)
+                .layer(RequireAuth::login()),
+        )
+        .route(
+            "/payment-methods/:id",
+            patch(update_payment_method)
+                .delete(delete_payment_method)
+                .layer(RequireAuth::login()),
+        )
+        .route(
+            "/export/subscriptions",
+            get(export_subscriptions).layer(RequireAuth::login()),
+        )
+        .route(
+            "/export/payments",
+            get(export_payments).layer(RequireAuth::login()),
+        )
+        .route(
+            "/export/attachments",
+            get(export_attachments).layer(RequireAuth::login()),
+        )
+        .route(
+            "/export/attachments/files",
+            get(export_attachment_files).layer(RequireAuth::login()),
+        )
+        .route("/stats", get(stats_handler).layer(RequireAuth::login()))
+        .route(
+            "/stats/digest",
+            get(digest_handler).layer(RequireAuth::login()),
+        )
+        .route(
+            "/stats/simulate",
+            post(simulate_handler).layer(RequireAuth::login()),
+        )
         .with_state(state)
 }
 
@@ -0,0 +1,374 @@
+//! Import of subscriptions from CSV files such as exported spreadsheets, the user maps the
+//! columns of the file to the fields of a subscription
+use chrono::NaiveDate;
+use iso_currency::Currency;
+
+use crate::domain::{BillingPeriodUnit, CurrencyCode, Money};
+
+/// Larger files have to be split into several imports
+pub const MAX_IMPORT_ROWS: usize = 1000;
+/// Largest request accepted for an import, generous for [`MAX_IMPORT_ROWS`] rows
+pub const MAX_IMPORT_BYTES: usize = 1024 * 1024;
+
+/// Headers of the columns holding the fields of a subscription
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportColumns {
+    pub name: String,
+    pub amount: String,
+    /// The preferred currency of the user is used when missing
+    pub currency: Option<String>,
+    /// e.g. `monthly`, `quarterly` or `2 weeks`
+    pub billing_period: String,
+    pub next_renewal: String,
+    /// ID or name of the service, the subscription name is looked up when missing
+    pub service: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+pub enum DateFormat {
+    #[serde(rename = "YYYY-MM-DD")]
+    Iso,
+    #[serde(rename = "DD.MM.YYYY")]
+    DayMonthYearDots,
+    #[serde(rename = "DD/MM/YYYY")]
+    DayMonthYear,
+    #[serde(rename = "MM/DD/YYYY")]
+    MonthDayYear,
+}
+
+impl DateFormat {
+    pub fn parse(&self, value: &str) -> Result<NaiveDate, String> {
+        let format = match self {
+            Self::Iso => "%Y-%m-%d",
+            Self::DayMonthYearDots => "%d.%m.%Y",
+            Self::DayMonthYear => "%d/%m/%Y",
+            Self::MonthDayYear => "%m/%d/%Y",
+        };
+
+        NaiveDate::parse_from_str(value.trim(), format)
+            .map_err(|_| format!("{} is not a valid date.", value))
+    }
+}
+
+/// Separator of the decimal places, the other one is taken as thousands separator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DecimalFormat {
+    /// e.g. `1,299.00`
+    Point,
+    /// e.g. `1.299,00`
+    Comma,
+}
+
+impl DecimalFormat {
+    pub fn parse(&self, value: &str, currency: Currency) -> Result<Money, String> {
+        let invalid = || format!("{} is not a valid amount of {}.", value, currency.code());
+        let (thousands, decimal) = match self {
+            Self::Point => (',', '.'),
+            Self::Comma => ('.', ','),
+        };
+        let (whole, fraction) = match value.trim().split_once(decimal) {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (value.trim(), None),
+        };
+        // Thousands separators only count between groups of three digits, `9,99` is not 999
+        let mut groups = whole.split(thousands);
+        let first = groups.next().unwrap_or_default();
+        let groups: Vec<&str> = groups.collect();
+        if !groups.is_empty() && (first.is_empty() || first.len() > 3)
+            || groups.iter().any(|group| group.len() != 3)
+        {
+            return Err(invalid());
+        }
+        let mut normalized = [first, &groups.concat()].concat();
+        if let Some(fraction) = fraction {
+            normalized = format!("{}.{}", normalized, fraction);
+        }
+
+        Money::parse(&normalized, currency).map_err(|_| invalid())
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportOptions {
+    pub columns: ImportColumns,
+    pub date_format: DateFormat,
+    pub decimal_format: DecimalFormat,
+    /// Defaults to a comma
+    pub delimiter: Option<char>,
+}
+
+/// Row of the file with the subscription it describes, or what is wrong with it
+#[derive(Debug, PartialEq)]
+pub struct ImportRow {
+    /// Line of the row in the file, the header is line 1
+    pub line: u64,
+    pub name: String,
+    /// ID or name of the service
+    pub service: String,
+    pub subscription: Result<ImportedSubscription, Vec<String>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ImportedSubscription {
+    pub name: String,
+    pub price: Money,
+    pub billing_period: u8,
+    pub billing_period_unit: BillingPeriodUnit,
+    pub next_renewal_date: NaiveDate,
+}
+
+/// Reads the rows of the file, every row is checked on its own so that all problems can be
+/// reported at once. Errors are returned for the file as a whole, e.g. a mapped column missing.
+pub fn parse_import(
+    csv: &str,
+    options: &ImportOptions,
+    default_currency: Currency,
+) -> Result<Vec<ImportRow>, String> {
+    let delimiter = options.delimiter.unwrap_or(',');
+    let delimiter =
+        u8::try_from(delimiter).map_err(|_| format!("{} is not a valid delimiter.", delimiter))?;
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(csv.as_bytes());
+
+    let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+    let column = |header: &str| {
+        headers
+            .iter()
+            .position(|column| column.eq_ignore_ascii_case(header.trim()))
+            .ok_or_else(|| format!("The file has no column {}.", header))
+    };
+    let columns = &options.columns;
+    let name_column = column(&columns.name)?;
+    let amount_column = column(&columns.amount)?;
+    let currency_column = columns.currency.as_deref().map(column).transpose()?;
+    let billing_period_column = column(&columns.billing_period)?;
+    let next_renewal_column = column(&columns.next_renewal)?;
+    let service_column = columns.service.as_deref().map(column).transpose()?;
+
+    let mut rows = vec![];
+    for record in reader.records() {
+        let record = record.map_err(|e| e.to_string())?;
+        if record.iter().all(str::is_empty) {
+            continue;
+        }
+        if rows.len() == MAX_IMPORT_ROWS {
+            return Err(format!(
+                "Files can have at most {} subscriptions.",
+                MAX_IMPORT_ROWS
+            ));
+        }
+        let cell = |column: usize| record.get(column).unwrap_or_default();
+        let mut errors = vec![];
+
+        let name = cell(name_column).to_owned();
+        let service = service_column
+            .map(cell)
+            .filter(|service| !service.is_empty())
+            .unwrap_or(&name)
+            .to_owned();
+        if name.is_empty() {
+            errors.push("Subscription name cannot be empty.".into());
+        }
+        let currency = match currency_column.map(cell).filter(|code| !code.is_empty()) {
+            Some(code) => collect_error(CurrencyCode::parse(code), &mut errors).map(|code| *code),
+            None => Some(default_currency),
+        };
+        let price = currency.and_then(|currency| {
+            let price = options.decimal_format.parse(cell(amount_column), currency);
+            collect_error(price, &mut errors)
+        });
+        let billing_period = collect_error(
+            parse_billing_period(cell(billing_period_column)),
+            &mut errors,
+        );
+        let next_renewal_date = collect_error(
+            options.date_format.parse(cell(next_renewal_column)),
+            &mut errors,
+        );
+
+        let subscription = match (price, billing_period, next_renewal_date) {
+            (Some(price), Some((billing_period, billing_period_unit)), Some(next_renewal_date))
+                if errors.is_empty() =>
+            {
+                Ok(ImportedSubscription {
+                    name: name.clone(),
+                    price,
+                    billing_period,
+                    billing_period_unit,
+                    next_renewal_date,
+                })
+            }
+            _ => Err(errors),
+        };
+        rows.push(ImportRow {
+            line: record.position().map_or(0, |position| position.line()),
+            name,
+            service,
+            subscription,
+        });
+    }
+
+    Ok(rows)
+}
+
+fn collect_error<T>(result: Result<T, String>, errors: &mut Vec<String>) -> Option<T> {
+    result.map_err(|e| errors.push(e)).ok()
+}
+
+/// Reads billing periods as written in spreadsheets, e.g. `monthly`, `every 2 weeks` or `1 year`
+pub fn parse_billing_period(value: &str) -> Result<(u8, BillingPeriodUnit), String> {
+    let invalid = || format!("{} is not a valid billing period.", value);
+    let normalized = value.trim().to_lowercase();
+
+    let period = match normalized.as_str() {
+        "daily" => (1, BillingPeriodUnit::Day),
+        "weekly" => (1, BillingPeriodUnit::Week),
+        "biweekly" => (2, BillingPeriodUnit::Week),
+        "monthly" => (1, BillingPeriodUnit::Month),
+        "quarterly" => (3, BillingPeriodUnit::Month),
+        "yearly" | "annually" | "annual" => (1, BillingPeriodUnit::Year),
+        other => {
+            let other = other.strip_prefix("every ").unwrap_or(other);
+            let (count, unit) = match other.split_whitespace().collect::<Vec<_>>()[..] {
+                [unit] => (1, unit),
+                [count, unit] => (count.parse::<u8>().map_err(|_| invalid())?, unit),
+                _ => return Err(invalid()),
+            };
+            let unit = match unit {
+                "day" | "days" => BillingPeriodUnit::Day,
+                "week" | "weeks" => BillingPeriodUnit::Week,
+                "month" | "months" => BillingPeriodUnit::Month,
+                "year" | "years" => BillingPeriodUnit::Year,
+                _ => return Err(invalid()),
+            };
+            (count, unit)
+        }
+    };
+    if period.0 == 0 {
+        return Err("Billing period has to be greater than 0.".into());
+    }
+
+    Ok(period)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use claims::{assert_err, assert_ok};
+    use iso_currency::Currency;
+
+    use crate::domain::{BillingPeriodUnit, Money};
+
+    use super::{
+        parse_billing_period, parse_import, DateFormat, DecimalFormat, ImportColumns,
+        ImportOptions, MAX_IMPORT_ROWS,
+    };
+
+    fn options(date_format: DateFormat, decimal_format: DecimalFormat) -> ImportOptions {
+        ImportOptions {
+            columns: ImportColumns {
+                name: "Name".into(),
+                amount: "Price".into(),
+                currency: Some("Currency".into()),
+                billing_period: "Period".into(),
+                next_renewal: "Renews".into(),
+                service: Some("Service".into()),
+            },
+            date_format,
+            decimal_format,
+            delimiter: Some(';'),
+        }
+    }
+
+    #[test]
+    fn rows_are_read_with_the_chosen_formats() {
+        let csv = "Name;Price;Currency;Period;Renews;Service\n\
+                   Family plan;1.299,00;;yearly;15.10.2023;Spotify\n\
+                   Music;9,99;usd;every 2 weeks;01.11.2023;\n";
+        let options = options(DateFormat::DayMonthYearDots, DecimalFormat::Comma);
+
+        let rows = parse_import(csv, &options, Currency::EUR).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].line, 2);
+        assert_eq!(rows[0].service, "Spotify");
+        let family = rows[0].subscription.as_ref().unwrap();
+        assert_eq!(family.price, Money::new(129900, Currency::EUR));
+        assert_eq!(family.billing_period_unit, BillingPeriodUnit::Year);
+        assert_eq!(
+            family.next_renewal_date,
+            NaiveDate::from_ymd_opt(2023, 10, 15).unwrap()
+        );
+        // Without a service the subscription name is looked up
+        assert_eq!(rows[1].service, "Music");
+        let music = rows[1].subscription.as_ref().unwrap();
+        assert_eq!(music.price, Money::new(999, Currency::USD));
+        assert_eq!(
+            (music.billing_period, music.billing_period_unit),
+            (2, BillingPeriodUnit::Week)
+        );
+    }
+
+    #[test]
+    fn every_problem_of_a_row_is_reported() {
+        let csv = "Name;Price;Currency;Period;Renews;Service\n\
+                   ;9.99;EUR;monthly;2023-10-15;Netflix\n\
+                   Netflix;9,99;XYZ;fortnightly;15.10.2023;\n\
+                   Netflix;9.99;EUR;monthly;2023-10-15;\n";
+        let options = options(DateFormat::Iso, DecimalFormat::Point);
+
+        let rows = parse_import(csv, &options, Currency::EUR).unwrap();
+
+        assert_eq!(rows[0].subscription.as_ref().unwrap_err().len(), 1);
+        assert_eq!(rows[1].subscription.as_ref().unwrap_err().len(), 3);
+        assert_ok!(&rows[2].subscription);
+    }
+
+    #[test]
+    fn mapped_columns_have_to_exist() {
+        let csv = "Name;Price;Period;Renews\nNetflix;9.99;monthly;2023-10-15\n";
+        let options = options(DateFormat::Iso, DecimalFormat::Point);
+
+        assert_err!(parse_import(csv, &options, Currency::EUR));
+    }
+
+    #[test]
+    fn files_with_too_many_rows_are_rejected() {
+        let options = options(DateFormat::Iso, DecimalFormat::Point);
+        let csv = |rows: usize| {
+            let mut csv = String::from("Name;Price;Currency;Period;Renews;Service\n");
+            csv.push_str(&"Netflix;9.99;EUR;monthly;2023-10-15;\n".repeat(rows));
+            csv
+        };
+
+        let rows = parse_import(&csv(MAX_IMPORT_ROWS), &options, Currency::EUR).unwrap();
+        assert_eq!(rows.len(), MAX_IMPORT_ROWS);
+
+        let error = parse_import(&csv(MAX_IMPORT_ROWS + 1), &options, Currency::EUR).unwrap_err();
+        assert_eq!(error, "Files can have at most 1000 subscriptions.");
+    }
+
+    #[test]
+    fn billing_periods_are_read_from_words() {
+        assert_eq!(
+            parse_billing_period("Quarterly"),
+            Ok((3, BillingPeriodUnit::Month))
+        );
+        assert_eq!(
+            parse_billing_period("1 year"),
+            Ok((1, BillingPeriodUnit::Year))
+        );
+        assert_eq!(
+            parse_billing_period("week"),
+            Ok((1, BillingPeriodUnit::Week))
+        );
+        assert_err!(parse_billing_period("0 months"));
+        assert_err!(parse_billing_period("every other month"));
+    }
+}
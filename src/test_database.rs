@@ -0,0 +1,30 @@
+//! Throwaway databases for tests that need Postgres
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::configuration::get_configuration;
+
+/// Creates an empty database named after `prefix` and a random suffix
+pub(crate) async fn create_db_for_tests(prefix: &str) -> PgPool {
+    let config = get_configuration().expect("Failed to read configuration");
+    let connection_opts = config.database.without_db();
+    let pool = PgPool::connect_lazy_with(connection_opts.clone());
+
+    let db_name = format!("{prefix}_{}", Uuid::new_v4().simple());
+    sqlx::query(&format!("CREATE DATABASE {db_name}"))
+        .execute(&pool)
+        .await
+        .expect("Failed to create db for tests");
+
+    PgPool::connect_lazy_with(connection_opts.database(&db_name))
+}
+
+/// Creates a database like [`create_db_for_tests`] and runs all migrations on it
+pub(crate) async fn create_migrated_db_for_tests(prefix: &str) -> PgPool {
+    let database = create_db_for_tests(prefix).await;
+    sqlx::migrate!("./migrations")
+        .run(&database)
+        .await
+        .expect("Failed to migrate the db for tests");
+    database
+}